@@ -0,0 +1,33 @@
+fn main() {
+    // Write the C header for the bindings in src/ffi.rs.
+    #[cfg(feature = "ffi")]
+    {
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        cbindgen::Builder::new()
+            .with_src(format!("{}/src/ffi.rs", dir))
+            .with_config(cbindgen::Config {
+                language: cbindgen::Language::C,
+                include_guard: Some("WORDSEARCH_H".to_string()),
+                cpp_compat: true,
+                usize_is_size_t: true,
+                ..Default::default()
+            })
+            .generate()
+            .expect("Couldn't generate the C header")
+            .write_to_file(format!("{}/include/wordsearch.h", dir));
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+    }
+
+    // The font isn't checked in, so say where to get it rather than leaving include_bytes! to fail.
+    #[cfg(feature = "dyslexia-font")]
+    {
+        let font = "fonts/OpenDyslexic-Regular.otf";
+        if !std::path::Path::new(font).exists() {
+            panic!(
+                "The dyslexia-font feature needs {}, which isn't checked in. See fonts/README.md for where to get it.",
+                font
+            );
+        }
+        println!("cargo:rerun-if-changed={}", font);
+    }
+}
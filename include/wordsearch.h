@@ -0,0 +1,75 @@
+#ifndef WORDSEARCH_H
+#define WORDSEARCH_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Make a puzzle from a word list with one word per line. A width or height of 0 picks a size to fit the words.
+ *
+ * Returns the puzzle as JSON, like `{"grid": ["CAT", ...], "placements": [{"word": "CAT", "x": 0, "y": 0,
+ * "direction": "east"}, ...]}`, or null on failure.
+ *
+ * # Safety
+ *
+ * `words` must be a valid, NUL-terminated string.
+ */
+char *wordsearch_generate(const char *words,
+                          size_t width,
+                          size_t height,
+                          uint64_t seed);
+
+/**
+ * Make a puzzle the same way as `wordsearch_generate`, and render the page as a PNG image, with the answers marked
+ * if `solution` is set. The length of the image is written to `len`.
+ *
+ * Returns the image, or null on failure.
+ *
+ * # Safety
+ *
+ * `words` must be a valid, NUL-terminated string, and `len` must point to writable memory.
+ */
+uint8_t *wordsearch_render_png(const char *words,
+                               size_t width,
+                               size_t height,
+                               uint64_t seed,
+                               bool solution,
+                               size_t *len);
+
+/**
+ * Free a string returned by one of these functions.
+ *
+ * # Safety
+ *
+ * `s` must have come from this library, and not been freed already. Null is ignored.
+ */
+void wordsearch_free_string(char *s);
+
+/**
+ * Free a buffer returned by one of these functions, along with the length it was returned with.
+ *
+ * # Safety
+ *
+ * `bytes` must have come from this library with this length, and not been freed already. Null is ignored.
+ */
+void wordsearch_free_bytes(uint8_t *bytes,
+                           size_t len);
+
+/**
+ * Return why the last call on this thread failed, or null if none has. The string is owned by the library, and is
+ * good until the next failure on this thread.
+ */
+const char *wordsearch_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WORDSEARCH_H */
@@ -0,0 +1,101 @@
+//! A short checksum of where the answers are, to print on the page, so solvers can check that they've found every
+//! word without seeing the key.
+
+use crate::error::WordSearchError;
+use crate::grid::{self, Placement};
+
+/// The letters codes and checksums are written with: Crockford's base 32, which leaves out letters that are easy to
+/// mistake for others.
+pub(crate) const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// How many letters of the alphabet a checksum is written with.
+const LETTERS: usize = 8;
+
+/// Where one answer starts and ends, as column and row.
+pub type Answer = ((usize, usize), (usize, usize));
+
+/// Return where each word starts and ends.
+pub fn answers(placements: &[Placement]) -> Vec<Answer> {
+    placements.iter().map(|p| ((p.x, p.y), p.end())).collect()
+}
+
+/// Return the checksum of the answers, like "7QX2-M4KD". The order of the answers, and which end of each comes
+/// first, don't matter.
+pub fn checksum(answers: &[Answer]) -> String {
+    let mut answers: Vec<Answer> = answers
+        .iter()
+        .map(|&(start, end)| (start.min(end), start.max(end)))
+        .collect();
+    answers.sort();
+    answers.dedup();
+    // FNV-1a, which is simple enough to stay the same from one version to the next.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for ((x0, y0), (x1, y1)) in answers {
+        for n in [x0, y0, x1, y1] {
+            for byte in (n as u32).to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+    }
+    let letters: String = (0..LETTERS)
+        .map(|i| ALPHABET[(hash >> (5 * i)) as usize & 31] as char)
+        .collect();
+    format!("{}-{}", &letters[..LETTERS / 2], &letters[LETTERS / 2..])
+}
+
+/// Return whether a checksum the solver typed is this one, ignoring case and dashes, and taking O for 0 and I or L
+/// for 1.
+pub fn matches(typed: &str, checksum: &str) -> bool {
+    let normalize = |s: &str| -> String {
+        s.chars()
+            .filter(|&c| c != '-')
+            .map(|c| match c.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            })
+            .collect()
+    };
+    normalize(typed) == normalize(checksum)
+}
+
+/// Read the solver's answers, one word to a line, each as the cells it starts and ends on, like "D13 K6" or
+/// "D13-K6". Anything up to a colon is taken to be the word, so a line like "BUTTE: M5 → M1 (north)" works too.
+/// Blank lines are skipped.
+pub fn parse_answers(text: &str) -> Result<Vec<Answer>, WordSearchError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let cells = line.rsplit(':').next().unwrap_or(line);
+            let cells: Vec<_> = cells
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter_map(grid::parse_cell)
+                .collect();
+            match cells[..] {
+                [start, end] => Ok((start, end)),
+                _ => Err(WordSearchError::InvalidInput(format!(
+                    "Expected the cells a word starts and ends on, like \"D13 K6\": {}",
+                    line
+                ))),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum, matches, parse_answers};
+
+    #[test]
+    fn test_checksum() {
+        let answers = parse_answers("BUTTE: M5 → M1 (north)\n\nA1-A5\n").unwrap();
+        assert_eq!(vec![((12, 4), (12, 0)), ((0, 0), (0, 4))], answers);
+        let sum = checksum(&answers);
+        assert_eq!(9, sum.len());
+        let reordered = parse_answers("a5 a1\nm1 m5").unwrap();
+        assert!(matches(&sum.to_lowercase(), &checksum(&reordered)));
+        let wrong = parse_answers("A1 A5\nM1 M4").unwrap();
+        assert!(!matches(&sum, &checksum(&wrong)));
+        assert!(parse_answers("A1").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::checksum::ALPHABET;
+use crate::config::{Args, FillerKind};
+use crate::error::WordSearchError;
+use crate::grid::{Direction, Mirror, Order, Puzzle};
+
+/// What's needed to make the same puzzle again from the same word list, written as a short code like
+/// "1F3K-9QX2-M7". Options that aren't recorded, like --max-attempts, --optimize, or a --filler other than random,
+/// english, or distractor, have to be given again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Code {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub directions: Vec<Direction>,
+    pub order: Order,
+    pub no_overlap: bool,
+    pub no_fill: bool,
+    pub allow_drop: bool,
+    pub criss_cross: bool,
+    pub use_all_directions: bool,
+    pub mixed_case: bool,
+    /// How far the finished puzzle is turned clockwise, in degrees.
+    pub rotate: Option<u32>,
+    pub mirror: Option<Mirror>,
+    /// The filler, if it's one that can be recorded.
+    pub filler: Option<FillerKind>,
+    pub inner_margin: usize,
+    pub min_intersections: usize,
+    pub candidates: usize,
+}
+
+impl Code {
+    /// Record how a puzzle was made.
+    pub fn new(args: &Args, seed: u64, puzzle: &Puzzle, directions: &[Direction]) -> Self {
+        Self {
+            seed,
+            width: puzzle.grid[0].len(),
+            height: puzzle.grid.len(),
+            directions: directions.to_vec(),
+            order: args.order,
+            no_overlap: args.no_overlap,
+            no_fill: args.no_fill,
+            allow_drop: args.allow_drop,
+            criss_cross: args.criss_cross,
+            use_all_directions: args.use_all_directions,
+            mixed_case: args.mixed_case,
+            rotate: args.rotate,
+            mirror: args.mirror,
+            filler: match args.filler {
+                FillerKind::Random | FillerKind::English | FillerKind::Distractor => {
+                    Some(args.filler.clone())
+                }
+                _ => None,
+            },
+            inner_margin: args.inner_margin,
+            min_intersections: args.min_intersections,
+            candidates: args.candidates,
+        }
+    }
+
+    /// Set the options to make the puzzle the code was made from. The directions are left for the caller to apply.
+    pub fn apply(&self, args: &mut Args) {
+        args.seed = Some(self.seed);
+        args.grid_width = Some(self.width);
+        args.grid_height = Some(self.height);
+        args.order = self.order;
+        args.no_overlap = self.no_overlap;
+        args.no_fill = self.no_fill;
+        args.allow_drop = self.allow_drop;
+        args.criss_cross = self.criss_cross;
+        args.use_all_directions = self.use_all_directions;
+        args.mixed_case = self.mixed_case;
+        args.rotate = self.rotate;
+        args.mirror = self.mirror;
+        if let Some(filler) = &self.filler {
+            args.filler = filler.clone();
+        }
+        args.inner_margin = self.inner_margin;
+        args.min_intersections = self.min_intersections;
+        args.candidates = self.candidates;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut flags = match self.order {
+            Order::LongestFirst => 0,
+            Order::Input => 1,
+            Order::Shuffle => 2,
+        };
+        for (bit, set) in [self.no_overlap, self.no_fill, self.allow_drop]
+            .into_iter()
+            .enumerate()
+        {
+            flags |= (set as u8) << (bit + 2);
+        }
+        flags |= match self.filler {
+            Some(FillerKind::Random) => 0,
+            Some(FillerKind::English) => 1,
+            Some(FillerKind::Distractor) => 2,
+            _ => 3,
+        } << 5;
+        flags |= (self.criss_cross as u8) << 7;
+        bytes.push(flags);
+        bytes.push(
+            Direction::ALL
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| self.directions.contains(d))
+                .fold(0, |mask, (i, _)| mask | 1 << i),
+        );
+        let mut numbers = vec![
+            self.seed,
+            self.width as u64,
+            self.height as u64,
+            self.inner_margin as u64,
+            self.min_intersections as u64,
+            self.candidates.saturating_sub(1) as u64,
+            // A second byte of flags, kept with the numbers so codes made before it still read the same.
+            self.use_all_directions as u64
+                | (self.mixed_case as u64) << 1
+                | ((self.rotate.unwrap_or(0) / 90 % 4) as u64) << 2
+                | match self.mirror {
+                    None => 0,
+                    Some(Mirror::Horizontal) => 1,
+                    Some(Mirror::Vertical) => 2,
+                } << 4,
+        ];
+        // Options left at zero at the end are left out, to keep the code short.
+        while numbers.last() == Some(&0) {
+            numbers.pop();
+        }
+        for n in numbers {
+            write_varint(&mut bytes, n);
+        }
+        // A check byte, so a mistyped code is caught instead of making a different puzzle.
+        bytes.push(
+            bytes
+                .iter()
+                .fold(0, |sum: u8, b| sum.wrapping_mul(31).wrapping_add(*b)),
+        );
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&check, bytes) = bytes.split_last()?;
+        if bytes
+            .iter()
+            .fold(0, |sum: u8, b| sum.wrapping_mul(31).wrapping_add(*b))
+            != check
+        {
+            return None;
+        }
+        let (&flags, rest) = bytes.split_first()?;
+        let (&mask, mut rest) = rest.split_first()?;
+        let mut numbers = vec![];
+        while !rest.is_empty() {
+            numbers.push(read_varint(&mut rest)?);
+        }
+        if numbers.len() > 7 {
+            return None;
+        }
+        numbers.resize(7, 0);
+        let [seed, width, height, inner_margin, min_intersections, candidates, more_flags] =
+            numbers[..]
+        else {
+            return None;
+        };
+        let mirror = match more_flags >> 4 {
+            0 => None,
+            1 => Some(Mirror::Horizontal),
+            2 => Some(Mirror::Vertical),
+            _ => return None,
+        };
+        let order = match flags & 3 {
+            0 => Order::LongestFirst,
+            1 => Order::Input,
+            2 => Order::Shuffle,
+            _ => return None,
+        };
+        Some(Self {
+            seed,
+            width: width as usize,
+            height: height as usize,
+            directions: Direction::ALL
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| mask & 1 << i != 0)
+                .map(|(_, d)| d)
+                .collect(),
+            order,
+            no_overlap: flags & 4 != 0,
+            no_fill: flags & 8 != 0,
+            allow_drop: flags & 16 != 0,
+            criss_cross: flags & 128 != 0,
+            use_all_directions: more_flags & 1 != 0,
+            mixed_case: more_flags & 2 != 0,
+            rotate: match more_flags >> 2 & 3 {
+                0 => None,
+                turns => Some(turns as u32 * 90),
+            },
+            mirror,
+            filler: match flags >> 5 & 3 {
+                0 => Some(FillerKind::Random),
+                1 => Some(FillerKind::English),
+                2 => Some(FillerKind::Distractor),
+                3 => None,
+                _ => return None,
+            },
+            inner_margin: inner_margin as usize,
+            min_intersections: min_intersections as usize,
+            candidates: candidates as usize + 1,
+        })
+    }
+}
+
+/// Write the code in groups of four letters, separated by dashes.
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut letters = vec![];
+        let (mut acc, mut bits) = (0u32, 0);
+        for byte in self.to_bytes() {
+            acc = acc << 8 | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                letters.push(ALPHABET[(acc >> bits) as usize & 31] as char);
+            }
+        }
+        if bits > 0 {
+            letters.push(ALPHABET[(acc << (5 - bits)) as usize & 31] as char);
+        }
+        let groups: Vec<String> = letters.chunks(4).map(|g| g.iter().collect()).collect();
+        f.write_str(&groups.join("-"))
+    }
+}
+
+/// Read a code, ignoring case and dashes, and taking O for 0 and I or L for 1.
+impl FromStr for Code {
+    type Err = WordSearchError;
+
+    fn from_str(s: &str) -> Result<Self, WordSearchError> {
+        let invalid = || WordSearchError::InvalidInput(format!("Not a valid puzzle code: {}", s));
+        let mut bytes = vec![];
+        let (mut acc, mut bits) = (0u32, 0);
+        for c in s.chars().filter(|&c| c != '-') {
+            let c = match c.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            };
+            let value = ALPHABET
+                .iter()
+                .position(|&a| a as char == c)
+                .ok_or_else(invalid)?;
+            acc = acc << 5 | value as u32;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((acc >> bits) as u8);
+            }
+        }
+        Self::from_bytes(&bytes).ok_or_else(invalid)
+    }
+}
+
+/// Write a number in as few bytes as it needs, seven bits at a time.
+fn write_varint(bytes: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FillerKind;
+    use crate::grid::{Direction, Mirror, Order};
+
+    use super::Code;
+
+    #[test]
+    fn test_code() {
+        let code = Code {
+            seed: 3_141_592_653,
+            width: 15,
+            height: 12,
+            directions: vec![Direction::East, Direction::South],
+            order: Order::LongestFirst,
+            no_overlap: true,
+            no_fill: false,
+            allow_drop: false,
+            criss_cross: false,
+            use_all_directions: false,
+            mixed_case: false,
+            rotate: None,
+            mirror: None,
+            filler: Some(FillerKind::Distractor),
+            inner_margin: 0,
+            min_intersections: 0,
+            candidates: 1,
+        };
+        let text = code.to_string();
+        assert!(text.len() <= 19, "{}", text);
+        assert_eq!(code, text.parse().unwrap());
+        assert_eq!(code, text.to_lowercase().replace('-', "").parse().unwrap());
+
+        let mut typo: Vec<char> = text.chars().collect();
+        typo[1] = if typo[1] == '7' { '8' } else { '7' };
+        assert!(typo.iter().collect::<String>().parse::<Code>().is_err());
+
+        let code = Code {
+            use_all_directions: true,
+            mixed_case: true,
+            rotate: Some(270),
+            mirror: Some(Mirror::Vertical),
+            ..code
+        };
+        let text = code.to_string();
+        assert_eq!(code, text.parse().unwrap());
+    }
+}
@@ -1,18 +1,62 @@
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf, rc::Rc, time::Duration};
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
-#[derive(Parser, Debug)]
+use icu_locale_core::Locale;
+use image::{ImageFormat, Rgb};
+
+use crate::code::Code;
+use crate::error::WordSearchError;
+use crate::filler::{self, Filler};
+use crate::grid::{self, Direction, Grid, Mirror, Order, Puzzle, QualityWeights};
+use crate::script;
+use crate::wordlist::{self, Entry, WordList};
+
+/// The formats for --formats that aren't raster images.
+const DOCUMENT_FORMATS: [&str; 6] = ["svg", "html", "docx", "odt", "xlsx", "ipuz"];
+
+/// How much of a criss-cross puzzle's grid its words fill, when the size isn't given.
+const CRISS_CROSS_DENSITY: f32 = 0.3;
+
+#[derive(Parser, Clone, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// File containing list of words to make into a wordsearch puzzle
     #[arg(short = 'f', long = "file", default_value = "words.txt")]
     pub wordlist: PathBuf,
 
+    /// Words to make into the puzzle instead of reading them from a file, separated by commas, like "CAT,DOG,BIRD".
+    /// Can be given more than once
+    #[arg(long, value_delimiter = ',', conflicts_with = "wordlist")]
+    pub words: Vec<String>,
+
+    /// Hide just one word, this many times, like "--repeat CAT 12", for the solver to find every copy. The copies
+    /// don't share any letters, and the filler never spells out another one
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["WORD", "COUNT"],
+        conflicts_with_all = ["wordlist", "words", "grid", "base", "roster", "tiers", "criss_cross", "min_intersections", "allow_drop"]
+    )]
+    pub repeat: Vec<String>,
+
+    /// Fail on anything odd in the word list, instead of tidying it up: blank lines, spaces around a word, control
+    /// characters, or a word with no letters to hide
+    #[arg(long, global = true)]
+    pub strict: bool,
+
     /// Output image file. Defaults to <wordlist>.png
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub output: Option<PathBuf>,
 
+    /// Save the puzzle in each of these formats, like "png,svg,html", all laid out the same way. The files are named
+    /// like the output file, with each format's extension. "ipuz" saves the grid and the answers as an ipuz puzzle
+    #[arg(long, global = true, value_delimiter = ',', value_parser = parse_format)]
+    pub formats: Vec<String>,
+
     /// Width of wordsearch grid, in letters
     #[arg(short = 'c', long = "columns")]
     pub grid_width: Option<usize>,
@@ -21,11 +65,1062 @@ pub struct Args {
     #[arg(short = 'r', long = "rows")]
     pub grid_height: Option<usize>,
 
+    /// Save the puzzle to this project file, with its word list, options, and layout, so it can be drawn again later
+    #[arg(long, conflicts_with_all = ["roster", "tiers", "project"])]
+    pub save_project: Option<PathBuf>,
+
+    /// Draw the puzzle saved in this project file, instead of making a new one. It's drawn with the options it was
+    /// made with, unless they're given again
+    #[arg(long)]
+    pub project: Option<PathBuf>,
+
+    /// Build on this earlier puzzle, in ipuz format as written by --ipuz: keep its size and letters, and the places of
+    /// the words still in the list, and only place the new words. If they don't fit, a new puzzle is made instead
+    #[arg(long, conflicts_with_all = ["grid_width", "grid_height", "grid", "min_size", "density", "roster", "tiers"])]
+    pub base: Option<PathBuf>,
+
+    /// Lay out this hand-made grid instead of generating one. It's a text file with one row of letters per line
+    #[arg(long, conflicts_with_all = ["grid_width", "grid_height"])]
+    pub grid: Option<PathBuf>,
+
+    /// Check that every word in the list can be found in the --grid
+    #[arg(long, requires = "grid")]
+    pub verify: bool,
+
+    /// Seed for the random number generator, to make the same puzzle again
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Keep running, and make the puzzle again each time the word list changes
+    #[arg(long, global = true, conflicts_with = "open")]
+    pub watch: bool,
+
+    /// With --watch, use a new random seed each time, instead of keeping the same one
+    #[arg(long, global = true, requires = "watch")]
+    pub reseed: bool,
+
+    /// Make the puzzle again from the code printed on it by --show-code, with the same word list
+    #[arg(long, conflicts_with_all = ["seed", "grid_width", "grid_height", "grid", "min_size", "roster", "tiers"])]
+    pub from_code: Option<Code>,
+
+    /// The order to place the words in. Placing the longest words first makes it more likely they'll all fit in a
+    /// small grid
+    #[arg(long, default_value = "shuffle")]
+    pub order: Order,
+
+    /// How many places to try for each word before giving up
+    #[arg(long, default_value_t = grid::DEFAULT_MAX_ATTEMPTS)]
+    pub max_attempts: usize,
+
+    /// How many places to try for all the words together before giving up
+    #[arg(long, default_value_t = grid::DEFAULT_ATTEMPT_BUDGET)]
+    pub attempt_budget: usize,
+
+    /// Give up if the puzzle isn't generated in this many seconds
+    #[arg(long, value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+
+    /// Leave out any words that can't be placed, and list them, instead of failing. If the --timeout or
+    /// --attempt-budget runs out, the words that haven't been placed yet are left out too
+    #[arg(long, conflicts_with = "min_size")]
+    pub allow_drop: bool,
+
+    /// Use the smallest grid the words fit into, and print its size and layout. If --columns and --rows are both
+    /// given, keep their aspect ratio; otherwise the grid is square
+    #[arg(long, conflicts_with_all = ["grid", "roster", "tiers"])]
+    pub min_size: bool,
+
+    /// Size the grid so the words' letters fill about this much of it, from 0 to 1, unless --columns or --rows is
+    /// given. Without it, that's 0.5, or 0.3 for --criss-cross.
+    #[arg(long, value_parser = parse_fraction, conflicts_with_all = ["min_size", "tiers"])]
+    pub density: Option<f32>,
+
+    /// Keep the words at least this many cells away from the edges of the grid, where they're easy to spot
+    #[arg(long, default_value_t = 0)]
+    pub inner_margin: usize,
+
+    /// Make sure at least one word goes in each of the directions words can go in, so that if diagonals are allowed,
+    /// some words are diagonal
+    #[arg(long, conflicts_with = "criss_cross")]
+    pub use_all_directions: bool,
+
+    /// Don't let words share letters, so each one stands on its own
+    #[arg(long)]
+    pub no_overlap: bool,
+
+    /// Make a harder puzzle, where at least this many letters are shared by more than one word
+    #[arg(long, default_value_t = 0, conflicts_with = "no_overlap")]
+    pub min_intersections: usize,
+
+    /// Make this many layouts and keep the best: the one that covers the most of the grid, shares the most letters,
+    /// and spreads the words most evenly over the directions
+    #[arg(long, default_value_t = 1)]
+    pub candidates: usize,
+
+    /// How much each part of a layout's quality counts when --candidates picks the best: how much of the grid the
+    /// words cover, how many letters they share, and how evenly they're spread over the directions. Any left out
+    /// count 1.
+    #[arg(
+        long,
+        value_parser = parse_weights,
+        default_value = "density=1,intersections=1,direction-balance=1"
+    )]
+    pub optimize: QualityWeights,
+
+    /// Leave the cells that aren't part of any word empty
+    #[arg(long, conflicts_with = "filler")]
+    pub no_fill: bool,
+
+    /// Make a criss-cross puzzle instead: an empty grid of interlocking boxes, going across and down, for the solver
+    /// to write the words into. The solution fills in the letters.
+    #[arg(long, conflicts_with_all = ["no_overlap", "grid", "tiers", "filler"])]
+    pub criss_cross: bool,
+
+    /// Write each letter of the grid in uppercase or lowercase at random, which makes the words harder to spot. The
+    /// words can be in any mix of cases in the grid
+    #[arg(long, conflicts_with = "criss_cross")]
+    pub mixed_case: bool,
+
+    /// Turn the finished puzzle clockwise by 90, 180, or 270 degrees, so one layout can make several different-looking
+    /// copies. The words turn with it
+    #[arg(long, value_parser = parse_rotation)]
+    pub rotate: Option<u32>,
+
+    /// Flip the finished puzzle, after turning it: "h" left to right, or "v" top to bottom
+    #[arg(long)]
+    pub mirror: Option<Mirror>,
+
+    /// How to choose the letters that fill the rest of the grid: "random"; "english", as often as each letter is used
+    /// in English; "corpus:<file>", as often as each letter is used in the file; "markov:<file>", as often as each
+    /// letter follows the one before it in the file; "message:<text>", to spell out a message in the leftover cells,
+    /// in reading order, with random letters after it; "letter:<letter>", to fill every cell with the same letter,
+    /// so the words stand out for the youngest solvers; or "distractor", to hide the starts of the words among random
+    /// letters, as false leads
+    #[arg(long, default_value = "random", value_parser = parse_filler)]
+    pub filler: FillerKind,
+
+    /// Don't let any one letter fill more than this share of the cells the filler fills in, from 0 to 1, like 0.1, so
+    /// a clump of the same letter doesn't make the words around it stand out. Letters over it are chosen again
+    #[arg(long, value_parser = parse_fraction)]
+    pub max_filler_share: Option<f32>,
+
+    /// Print where each word was placed, how many places were tried before it fit, which other words it crosses, a
+    /// table of how many words each one crosses and which way it reads, and about how long the puzzle takes to solve
+    #[arg(long, conflicts_with = "grid")]
+    pub explain: bool,
+
+    /// Make a different puzzle for each student named in this file, with their name in the header
+    #[arg(long, conflicts_with_all = ["grid", "answers_csv", "answers_list", "ipuz"])]
+    pub roster: Option<PathBuf>,
+
+    /// With --roster, also hide each student's name in their puzzle as a bonus word
+    #[arg(long, requires = "roster")]
+    pub roster_bonus: bool,
+
+    /// Make a version of the puzzle, and its solution, for each of these difficulty tiers
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["roster", "grid", "answers_csv", "answers_list", "ipuz"])]
+    pub tiers: Vec<Tier>,
+
+    /// Sort the words in the key, instead of listing them in the order they were given
+    #[arg(long, global = true)]
+    pub sort_key: Option<SortKey>,
+
+    /// How the words are shown in the key. The answers always show the words themselves
+    #[arg(long, global = true, default_value = "words")]
+    pub key: KeyStyle,
+
+    /// Language of the words, like "de" or "cs", for sorting them by that language's rules
+    #[arg(long, global = true)]
+    pub lang: Option<Locale>,
+
     /// Width of produced image
-    #[arg(short = 'x', long, default_value = "768")]
+    #[arg(short = 'x', long, default_value = "768", global = true)]
     pub image_width: u32,
 
     /// Height of produced image
-    #[arg(short = 'y', long, default_value = "1024")]
+    #[arg(short = 'y', long, default_value = "1024", global = true)]
     pub image_height: u32,
+
+    /// What to do if the grid or the key doesn't fit on a page of that size, as can happen with --grid-font-size or
+    /// --key-font-size: fail, saying what size it needs, or make the page that size
+    #[arg(long, global = true, default_value = "fail")]
+    pub fit: Fit,
+
+    /// Size of the letters in the grid, in pixels. Defaults to as large as fits on the page
+    #[arg(long, global = true, value_parser = parse_positive)]
+    pub grid_font_size: Option<f32>,
+
+    /// How much room each letter in the grid gets, as a multiple of the width of an "M". The cells are always at
+    /// least as wide as the letters are tall
+    #[arg(long, global = true, default_value = "1.3", value_parser = parse_positive)]
+    pub letter_spacing: f32,
+
+    /// The typeface for the text on the page. "dyslexic" is OpenDyslexic, which is only there when wordsearch is
+    /// built with the "dyslexia-font" feature
+    #[arg(long, global = true)]
+    pub font: Option<Typeface>,
+
+    /// Settings for particular solvers. "dyslexia" uses the dyslexic font if it's built in, spaces the letters of the
+    /// grid further apart, and keeps letters that are easily mistaken for the letters of the words out of the filler
+    #[arg(long, global = true)]
+    pub preset: Option<Preset>,
+
+    /// Where to put the letters in the cells of the grid: centered, or at the top like older versions did
+    #[arg(long, global = true, default_value = "center")]
+    pub baseline: Baseline,
+
+    /// Label the columns of the grid with letters across the top, and the rows with numbers down the side, so cells
+    /// can be called by name, like "C7"
+    #[arg(long, global = true)]
+    pub coordinates: bool,
+
+    /// Size of the words in the key, in pixels. Defaults to a little smaller than the grid letters
+    #[arg(long, global = true, value_parser = parse_positive)]
+    pub key_font_size: Option<f32>,
+
+    /// Room between the grid and the key, like "10mm", "0.5in", or "40px". Defaults to the height of a line of the
+    /// key
+    #[arg(long, global = true, value_parser = parse_length)]
+    pub key_gap: Option<Length>,
+
+    /// Which images to make: the whole page, or separate images of the grid, the key, and the grid with the answers
+    /// marked. The separate images are named like <output>-grid.png
+    #[arg(long, global = true, value_delimiter = ',', default_value = "page")]
+    pub emit: Vec<Emit>,
+
+    /// With HTML output, make a page that can be played in a browser: drag across the letters of a word to find it, and
+    /// it's struck off the list. A timer runs until every word is found
+    #[arg(long, global = true)]
+    pub interactive: bool,
+
+    /// Also make a teacher's edition of each puzzle, with the answers marked, named like <output>-teacher.png
+    #[arg(long, global = true)]
+    pub teachers_edition: bool,
+
+    /// Also make a hint: a copy of the puzzle with only these words marked, named like <output>-hint.png
+    #[arg(long, global = true, value_delimiter = ',')]
+    pub reveal: Vec<String>,
+
+    /// Also make a hint with only the first this many words of the key marked, named like <output>-hint.png
+    #[arg(long, global = true, conflicts_with = "reveal")]
+    pub reveal_count: Option<usize>,
+
+    /// Lightly shade alternating cells or rows of the grid, to help keep your place
+    #[arg(long, global = true)]
+    pub cell_shading: Option<CellShading>,
+
+    /// Draw a decorative frame around the page, or around the grid with --frame-around
+    #[arg(long, global = true)]
+    pub frame: Option<FrameStyle>,
+
+    /// What --frame goes around
+    #[arg(long, global = true, default_value = "page", requires = "frame")]
+    pub frame_around: FrameAround,
+
+    /// How thick the frame is, in pixels
+    #[arg(long, global = true, default_value = "6", requires = "frame", value_parser = clap::value_parser!(u32).range(1..))]
+    pub frame_thickness: u32,
+
+    /// How much room to leave between the frame and what's inside it, in pixels
+    #[arg(long, global = true, default_value = "12", requires = "frame")]
+    pub frame_padding: u32,
+
+    /// Room to add around the page for a print shop to trim, like "3mm", "0.125in", or "36px". The page keeps its size
+    /// inside it, and nothing on it goes closer to the edge than this. Only for PNG and TIFF images
+    #[arg(long, global = true, value_parser = parse_length)]
+    pub bleed: Option<Length>,
+
+    /// Draw marks outside the page, and any bleed, where it's to be trimmed. Only for PNG and TIFF images
+    #[arg(long, global = true)]
+    pub crop_marks: bool,
+
+    /// Resolution to print the images at, in pixels per inch, for lengths like --bleed that aren't given in pixels
+    #[arg(long, global = true, default_value = "300", value_parser = parse_positive)]
+    pub dpi: f32,
+
+    /// How to mark the answers in solutions and teacher's editions
+    #[arg(long, global = true, default_value = "highlight")]
+    pub mark_style: MarkStyle,
+
+    /// Color of the answer marks, like "#ff0000" or "red". Defaults to red, or black with --theme high-contrast
+    #[arg(long, global = true, value_parser = parse_color)]
+    pub mark_color: Option<Rgb<u8>>,
+
+    /// Opacity of the answer marks, from 0 to 1. Defaults to 0.3 for highlights and 0.8 for other marks, or 1 with
+    /// --theme high-contrast
+    #[arg(long, global = true, value_parser = parse_opacity)]
+    pub mark_opacity: Option<f32>,
+
+    /// "high-contrast" draws everything in black, with bold letters, thicker lines, and darker shading, for solvers
+    /// with low vision printing at the usual size
+    #[arg(long, global = true)]
+    pub theme: Option<Theme>,
+
+    /// Draw lines for the solver's name and the date at the top of the page
+    #[arg(long, global = true)]
+    pub worksheet_header: bool,
+
+    /// Title to print at the top of the page. A plain text word list can give one with a "!title:" line instead
+    #[arg(long, global = true)]
+    pub title: Option<String>,
+
+    /// Color the letters of the grid: "rainbow" cycles through the colors of the rainbow along each diagonal,
+    /// "random" picks one of them for each letter, and a ".json" file gives a list of colors to cycle through
+    /// instead, like ["red", "#1976d2"]. HTML, PNG, TIFF, and SVG only
+    #[arg(long, global = true, value_parser = parse_letter_colors)]
+    pub letter_colors: Option<LetterColors>,
+
+    /// Print a code in the bottom corner of the page that --from-code can make the same puzzle again from
+    #[arg(long, conflicts_with_all = ["grid", "min_size", "roster_bonus"])]
+    pub show_code: bool,
+
+    /// The code printed on the page, if --show-code is given. Set for each puzzle as it's made.
+    #[arg(skip)]
+    pub code: Option<String>,
+
+    /// Print a checksum of where the answers are in the bottom corner of the page, for solvers to check their answers
+    /// against with "wordsearch check-answer"
+    #[arg(long, global = true)]
+    pub show_checksum: bool,
+
+    /// The answer checksum printed on the page, if --show-checksum is given. Set for each puzzle as it's drawn.
+    #[arg(skip)]
+    pub checksum: Option<String>,
+
+    /// Draw a box in the corner for the solver to fill in their time and how many words they found
+    #[arg(long, global = true)]
+    pub score_box: bool,
+
+    /// Also write the answers to this CSV file: the start row and column, direction, and end row and column of each
+    /// word
+    #[arg(long, global = true)]
+    pub answers_csv: Option<PathBuf>,
+
+    /// Also write the answers to this text file, one word per line with where it starts and ends, like
+    /// "WHALE: C7 → C11 (south)"
+    #[arg(long, global = true)]
+    pub answers_list: Option<PathBuf>,
+
+    /// Open the puzzle in the system's default viewer once it's saved. With --roster or --tiers, the first one is
+    /// opened
+    #[arg(long, global = true, conflicts_with = "preview")]
+    pub open: bool,
+
+    /// Print the grid and the key in the terminal instead of writing any files, to try out settings quickly. With
+    /// --preview=answers, the answers are highlighted in the mark color
+    #[arg(
+        long,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "plain"
+    )]
+    pub preview: Option<Preview>,
+
+    /// Also write the grid to a text file named like <output>-solution.txt, with the letters of the answers in
+    /// uppercase and the filler in lowercase
+    #[arg(long, global = true)]
+    pub solution_text: bool,
+
+    /// Also write the puzzle to this file in ipuz format
+    #[arg(long, global = true)]
+    pub ipuz: Option<PathBuf>,
+}
+
+/// How much room each letter in the grid gets with --preset dyslexia, at least.
+const DYSLEXIA_LETTER_SPACING: f32 = 1.6;
+
+impl Args {
+    /// Return the typeface to draw the text in: the one given with --font, or the one the preset calls for. The
+    /// dyslexia preset only uses the dyslexic font if it's built in.
+    pub fn typeface(&self) -> Typeface {
+        match (self.font, self.preset) {
+            (Some(typeface), _) => typeface,
+            (None, Some(Preset::Dyslexia)) if cfg!(feature = "dyslexia-font") => Typeface::Dyslexic,
+            _ => Typeface::Sans,
+        }
+    }
+
+    /// Return the words to hide: the ones given with --words, or else the ones in the word list file.
+    pub fn read_words(&self) -> Result<WordList, WordSearchError> {
+        if let [word, count] = &self.repeat[..] {
+            let count: usize = match count.parse() {
+                Ok(count) if count > 0 => count,
+                _ => {
+                    return Err(WordSearchError::InvalidInput(format!(
+                        "Bad count {:?} for --repeat: it should be a whole number above 0",
+                        count
+                    )))
+                }
+            };
+            let mut entry = Entry::new(word.trim().to_string());
+            entry.word = format!("{} ({} times)", entry.word, count);
+            return Ok(given(vec![entry]));
+        }
+        if self.words.is_empty() {
+            return wordlist::read_list(&self.wordlist, self.strict);
+        }
+        Ok(given(
+            self.words
+                .iter()
+                .map(|word| word.trim())
+                .filter(|word| !word.is_empty())
+                .map(|word| Entry::new(word.to_string()))
+                .collect(),
+        ))
+    }
+
+    /// Return the title the word list file gives the puzzle, if the words come from one.
+    pub fn list_title(&self) -> Result<Option<String>, WordSearchError> {
+        let from_file = self.command.is_none()
+            && self.project.is_none()
+            && self.words.is_empty()
+            && self.repeat.is_empty();
+        if !from_file {
+            return Ok(None);
+        }
+        wordlist::read_title(&self.wordlist)
+    }
+
+    /// Return the words to hide in the grid, in the form they take there. With --repeat, that's the one word, as
+    /// many times as it's hidden.
+    pub fn hidden_words(&self, words: &[Entry]) -> Vec<String> {
+        let count = match &self.repeat[..] {
+            [_, count] => count.parse().unwrap_or(1),
+            _ => 1,
+        };
+        words
+            .iter()
+            .flat_map(|entry| std::iter::repeat_n(entry.grid_form(), count))
+            .collect()
+    }
+
+    /// Turn and flip a finished puzzle the way --rotate and --mirror say.
+    pub fn orient(&self, puzzle: Puzzle) -> Puzzle {
+        let puzzle = match self.rotate {
+            Some(degrees) => puzzle.rotate(degrees / 90),
+            None => puzzle,
+        };
+        match self.mirror {
+            Some(mirror) => puzzle.mirror(mirror),
+            None => puzzle,
+        }
+    }
+
+    /// Return how much of the grid the words should fill.
+    pub fn density(&self) -> f32 {
+        // The words of a criss-cross puzzle only touch where they cross, so it needs more room.
+        self.density.unwrap_or(match self.criss_cross {
+            true => CRISS_CROSS_DENSITY,
+            false => grid::DEFAULT_DENSITY,
+        })
+    }
+
+    /// Return the line in the bottom corner of the page: the puzzle's code and its answer checksum, if they're wanted.
+    pub fn footer(&self) -> Option<String> {
+        let checksum = self.checksum.as_ref().map(|c| format!("Check: {}", c));
+        let parts: Vec<String> = self.code.iter().cloned().chain(checksum).collect();
+        (!parts.is_empty()).then(|| parts.join("   "))
+    }
+
+    /// Return whether the page is drawn with --theme high-contrast.
+    pub fn high_contrast(&self) -> bool {
+        self.theme == Some(Theme::HighContrast)
+    }
+
+    /// Return the color of the answer marks.
+    pub fn mark_color(&self) -> Rgb<u8> {
+        match self.mark_color {
+            Some(color) => color,
+            None if self.high_contrast() => Rgb([0, 0, 0]),
+            None => Rgb([255, 0, 0]),
+        }
+    }
+
+    /// Return the opacity of the answer marks. Highlights stay see-through with --theme high-contrast, so the letters
+    /// under them can still be read.
+    pub fn mark_opacity(&self) -> f32 {
+        match self.mark_opacity {
+            Some(opacity) => opacity,
+            None if self.high_contrast() && self.mark_style != MarkStyle::Highlight => 1.0,
+            None => self.mark_style.default_opacity(),
+        }
+    }
+
+    /// Return how much room each letter in the grid gets, as a multiple of the width of an "M".
+    pub fn letter_spacing(&self) -> f32 {
+        match self.preset {
+            Some(Preset::Dyslexia) => self.letter_spacing.max(DYSLEXIA_LETTER_SPACING),
+            None => self.letter_spacing,
+        }
+    }
+
+    /// Set up a grid for the words, with the placement and filler options the user asked for.
+    pub fn new_grid(
+        &self,
+        words: Vec<String>,
+        width: Option<usize>,
+        height: Option<usize>,
+    ) -> Result<Grid, WordSearchError> {
+        let size = Some(Grid::default_size(&words, self.density()));
+        let (width, height) = (width.or(size), height.or(size));
+        // A puzzle that mixes scripts gets filler from all of them.
+        let alphabet = script::filler_alphabet(&words);
+        let filler: Box<dyn Filler> = match &self.filler {
+            FillerKind::Random | FillerKind::Distractor => Box::new(filler::Random::new(alphabet)),
+            FillerKind::English => Box::new(filler::Frequency::english()),
+            FillerKind::Corpus(path) => Box::new(filler::Frequency::from_corpus(
+                &std::fs::read_to_string(path)?,
+            )?),
+            FillerKind::Markov(path) => Box::new(filler::Markov::from_corpus(
+                &std::fs::read_to_string(path)?,
+            )?),
+            FillerKind::Message(text) => Box::new(filler::Message::new(
+                &wordlist::grid_form(text),
+                Box::new(filler::Random::new(alphabet)),
+            )),
+            FillerKind::Letter(letter) => Box::new(filler::Random::new(vec![*letter])),
+        };
+        if self.max_filler_share.is_some()
+            && matches!(self.filler, FillerKind::Message(_) | FillerKind::Letter(_))
+        {
+            return Err(WordSearchError::InvalidInput(
+                "--max-filler-share would change the letters the --filler message or letter spells out"
+                    .to_string(),
+            ));
+        }
+        let filler: Rc<dyn Filler> = match self.preset {
+            Some(Preset::Dyslexia) => Rc::new(filler::Avoiding::new(
+                filler,
+                script::confusable_with(&words),
+            )),
+            None => filler.into(),
+        };
+        Ok(Grid::new(words, width, height)
+            .with_order(self.order)
+            .with_max_attempts(self.max_attempts, self.attempt_budget)
+            .with_allow_drop(self.allow_drop)
+            .with_overlap(!self.no_overlap && self.repeat.is_empty())
+            .with_exact(!self.repeat.is_empty())
+            .with_all_directions(self.use_all_directions)
+            .with_min_intersections(self.min_intersections)
+            .with_inner_margin(self.inner_margin)
+            .with_fill(!self.no_fill)
+            .with_distractors(matches!(self.filler, FillerKind::Distractor))
+            .with_max_filler_share(self.max_filler_share)
+            .with_criss_cross(self.criss_cross)
+            .with_mixed_case(self.mixed_case)
+            .with_candidates(self.candidates, self.optimize)
+            .with_filler(filler))
+    }
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Render an existing puzzle instead of generating a new one
+    Render {
+        /// Puzzle file to render, in ipuz format, or saved with --save-project (.wsproj). The output defaults to
+        /// <input>.png
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Change the layout of a puzzle saved with --save-project, in the terminal: move, turn, or re-place words one at
+    /// a time, choose new filler, and save or export it
+    Edit {
+        /// Project file to edit
+        project: PathBuf,
+    },
+    /// Check a solver's answers against the checksum printed on their puzzle with --show-checksum, without needing
+    /// the key
+    CheckAnswer {
+        /// The checksum printed on the puzzle, like "7QX2-M4KD"
+        checksum: String,
+        /// File with one answer to a line: the cells the word starts and ends on, like "D13 K6"
+        answers: PathBuf,
+    },
+    /// Check every puzzle in a directory, in ipuz format (.ipuz or .json) or saved with --save-project (.wsproj): that
+    /// each one loads, and has each of its words hidden exactly once. Prints which passed and which failed, and why
+    VerifyDir {
+        /// Directory of puzzles
+        dir: PathBuf,
+        /// How many puzzles to check at once. The default is one for each CPU
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    /// Solve a puzzle from a photo or scan of it: find the grid, read its letters, and save a copy of the photo with
+    /// the words marked. This is experimental, and works best on puzzles printed by this program. The output defaults
+    /// to <photo>-solved.png
+    SolveImage {
+        /// Photo of the puzzle
+        photo: PathBuf,
+        /// File with the words to look for, one to a line
+        #[arg(long)]
+        words: PathBuf,
+    },
+}
+
+/// One of the images we can make.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Emit {
+    /// The whole page: the grid and the key
+    Page,
+    /// Only the grid
+    Grid,
+    /// Only the key
+    Key,
+    /// Only the grid, with the answers marked
+    Solution,
+}
+
+impl fmt::Display for Emit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Page => "page",
+            Self::Grid => "grid",
+            Self::Key => "key",
+            Self::Solution => "solution",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How to sort the words in the key.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SortKey {
+    /// In alphabetical order
+    Alphabetical,
+    /// Longest words first
+    Length,
+}
+
+/// How the words are shown in the key.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyStyle {
+    /// As they were given
+    Words,
+    /// With their letters shuffled, to be unscrambled before they can be found
+    Scrambled,
+    /// As their first letter and a blank for each of the others, like "E _ _ _"
+    Blanks,
+    /// Along with their translations, from the word list's "translation" column, like "PERRO — dog"
+    Bilingual,
+}
+
+/// How to color the letters of the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LetterColors {
+    Rainbow,
+    Random,
+    Palette(PathBuf),
+}
+
+/// How to choose the letters that fill the rest of the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FillerKind {
+    Random,
+    English,
+    Corpus(PathBuf),
+    Markov(PathBuf),
+    Message(String),
+    Letter(char),
+    Distractor,
+}
+
+/// A length on the printed page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Pixels(f32),
+    Millimeters(f32),
+    Inches(f32),
+}
+
+impl Length {
+    /// How many pixels long this is, at this many pixels per inch.
+    pub fn pixels(&self, dpi: f32) -> u32 {
+        let pixels = match *self {
+            Self::Pixels(pixels) => pixels,
+            Self::Millimeters(mm) => mm / 25.4 * dpi,
+            Self::Inches(inches) => inches * dpi,
+        };
+        pixels.round() as u32
+    }
+}
+
+/// Where the letters go in the cells of the grid.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Baseline {
+    /// At the top of the cell
+    Top,
+    /// Centered in the cell
+    Center,
+}
+
+/// What to do when the page is too small for the puzzle.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fit {
+    /// Stop with an error that says how big the page needs to be
+    Fail,
+    /// Make the page as big as it needs to be
+    Grow,
+}
+
+/// What --preview shows.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preview {
+    /// Just the puzzle
+    Plain,
+    /// The puzzle with the answers highlighted
+    Answers,
+}
+
+/// Which cells of the grid to shade.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CellShading {
+    /// Alternate cells, like a checkerboard
+    Checkerboard,
+    /// Every other row
+    Rows,
+}
+
+/// The typefaces text can be drawn in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Typeface {
+    /// FreeSans
+    Sans,
+    /// OpenDyslexic, with heavier bottoms to the letters so they're harder to turn around
+    Dyslexic,
+}
+
+impl Typeface {
+    /// Return the CSS font family for the typeface, with fallbacks.
+    pub fn css_family(&self) -> &'static str {
+        match self {
+            Self::Sans => "FreeSans, Helvetica, Arial, sans-serif",
+            Self::Dyslexic => "OpenDyslexic, sans-serif",
+        }
+    }
+}
+
+/// Looks for the page.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    HighContrast,
+}
+
+/// Groups of settings for particular solvers.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Dyslexia,
+}
+
+/// What a --frame looks like.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameStyle {
+    /// A plain line
+    Simple,
+    /// Two thin lines
+    Double,
+    /// A plain line with rounded corners
+    Rounded,
+    /// Two thin lines, with blocks in the corners and diamonds halfway along each side
+    Fancy,
+}
+
+/// What a --frame goes around.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameAround {
+    /// The edge of the page
+    Page,
+    /// The grid, and its coordinate labels if there are any
+    Grid,
+    /// Both the page and the grid
+    Both,
+}
+
+/// How to mark where the words are in a solution.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkStyle {
+    /// A line through the letters
+    Line,
+    /// An oval around the whole word
+    Oval,
+    /// A highlighter band behind the letters
+    Highlight,
+    /// A circle around each letter
+    CircleLetters,
+}
+
+impl MarkStyle {
+    /// How opaque the marks should be, unless the user says otherwise.
+    pub fn default_opacity(&self) -> f32 {
+        match self {
+            Self::Highlight => 0.3,
+            _ => 0.8,
+        }
+    }
+}
+
+/// How hard a puzzle should be.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Tier {
+    /// Words only go across and down, in a roomy grid
+    Easy,
+    /// Words can also go diagonally, but never backwards
+    Medium,
+    /// Words go in every direction, in a crowded grid
+    Hard,
+}
+
+impl Tier {
+    /// Which directions words may be placed in.
+    pub fn directions(&self) -> &'static [Direction] {
+        match self {
+            Self::Easy => &[Direction::East, Direction::South],
+            Self::Medium => &[
+                Direction::East,
+                Direction::Southeast,
+                Direction::South,
+                Direction::Northeast,
+            ],
+            Self::Hard => &Direction::ALL,
+        }
+    }
+
+    /// About how much of the grid should be taken up by the words.
+    pub fn density(&self) -> f32 {
+        match self {
+            Self::Easy => 0.35,
+            Self::Medium => 0.5,
+            Self::Hard => 0.6,
+        }
+    }
+}
+
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Easy => "easy",
+            Self::Medium => "medium",
+            Self::Hard => "hard",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Parse a color given as "#rrggbb" hex, or as one of a few common names.
+/// Make a word list of the words given on the command line, which has no title.
+fn given(entries: Vec<Entry>) -> WordList {
+    WordList {
+        title: None,
+        entries,
+        left_out: vec![],
+    }
+}
+
+pub fn parse_color(s: &str) -> Result<Rgb<u8>, String> {
+    let named = match s.to_lowercase().as_str() {
+        "black" => Some([0, 0, 0]),
+        "white" => Some([255, 255, 255]),
+        "red" => Some([255, 0, 0]),
+        "green" => Some([0, 160, 0]),
+        "blue" => Some([0, 0, 255]),
+        "yellow" => Some([255, 255, 0]),
+        "orange" => Some([255, 165, 0]),
+        "purple" => Some([128, 0, 128]),
+        "gray" | "grey" => Some([128, 128, 128]),
+        _ => None,
+    };
+    if let Some(rgb) = named {
+        return Ok(Rgb(rgb));
+    }
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("not a color name or #rrggbb: {}", s));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok(Rgb([channel(0), channel(2), channel(4)]))
+}
+
+fn parse_letter_colors(s: &str) -> Result<LetterColors, String> {
+    match s {
+        "rainbow" => Ok(LetterColors::Rainbow),
+        "random" => Ok(LetterColors::Random),
+        _ if s.to_lowercase().ends_with(".json") => Ok(LetterColors::Palette(PathBuf::from(s))),
+        _ => Err(format!(
+            "expected rainbow, random, or a .json file of colors: {}",
+            s
+        )),
+    }
+}
+
+fn parse_filler(s: &str) -> Result<FillerKind, String> {
+    match s.split_once(':') {
+        None if s == "random" => Ok(FillerKind::Random),
+        None if s == "english" => Ok(FillerKind::English),
+        None if s == "distractor" => Ok(FillerKind::Distractor),
+        Some(("corpus", path)) => Ok(FillerKind::Corpus(path.into())),
+        Some(("markov", path)) => Ok(FillerKind::Markov(path.into())),
+        Some(("message", text)) => Ok(FillerKind::Message(text.to_string())),
+        Some(("letter", letter)) => {
+            let mut letters = letter.chars().flat_map(char::to_uppercase);
+            match (letters.next(), letters.next()) {
+                (Some(letter), None) if letter.is_alphanumeric() => Ok(FillerKind::Letter(letter)),
+                _ => Err(format!("expected a single letter after \"letter:\": {}", s)),
+            }
+        }
+        _ => Err(format!(
+            "expected random, english, corpus:<file>, markov:<file>, message:<text>, letter:<letter>, or distractor: {}",
+            s
+        )),
+    }
+}
+
+fn parse_opacity(s: &str) -> Result<f32, String> {
+    let opacity: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("must be between 0 and 1: {}", s));
+    }
+    Ok(opacity)
+}
+
+fn parse_fraction(s: &str) -> Result<f32, String> {
+    let fraction: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if !fraction.is_finite() || fraction <= 0.0 || fraction > 1.0 {
+        return Err(format!("must be more than 0, and at most 1: {}", s));
+    }
+    Ok(fraction)
+}
+
+fn parse_weights(s: &str) -> Result<QualityWeights, String> {
+    let mut weights = QualityWeights::default();
+    for part in s.split(',').filter(|part| !part.is_empty()) {
+        let (name, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("expected <name>=<weight>: {}", part))?;
+        let value: f32 = value.parse().map_err(|e| format!("{}: {}", value, e))?;
+        match name {
+            "density" => weights.density = value,
+            "intersections" => weights.intersections = value,
+            "direction-balance" => weights.direction_balance = value,
+            _ => {
+                return Err(format!(
+                    "not density, intersections, or direction-balance: {}",
+                    name
+                ))
+            }
+        }
+    }
+    Ok(weights)
+}
+
+fn parse_positive(s: &str) -> Result<f32, String> {
+    let value: f32 = s.parse().map_err(|e| format!("{}", e))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(format!("must be more than 0: {}", s));
+    }
+    Ok(value)
+}
+
+fn parse_length(s: &str) -> Result<Length, String> {
+    let units = [
+        ("mm", Length::Millimeters as fn(f32) -> Length),
+        ("in", Length::Inches),
+        ("px", Length::Pixels),
+    ];
+    // A number on its own is in pixels.
+    let (number, unit) = units
+        .iter()
+        .find_map(|(suffix, unit)| Some((s.strip_suffix(suffix)?, *unit)))
+        .unwrap_or((s, Length::Pixels));
+    let value: f32 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a length like 3mm, 0.125in, or 36px: {}", s))?;
+    if value < 0.0 {
+        return Err(format!("can't be negative: {}", s));
+    }
+    Ok(unit(value))
+}
+
+fn parse_format(s: &str) -> Result<String, String> {
+    let format = s.trim().to_ascii_lowercase();
+    let image = ImageFormat::from_extension(&format).is_some_and(|f| f.can_write());
+    match image || DOCUMENT_FORMATS.contains(&format.as_str()) {
+        true => Ok(format),
+        false => Err(format!(
+            "expected an image format like png or tiff, or svg, html, docx, odt, xlsx, or ipuz: {}",
+            s
+        )),
+    }
+}
+
+fn parse_rotation(s: &str) -> Result<u32, String> {
+    match s.trim().trim_end_matches('°') {
+        "90" => Ok(90),
+        "180" => Ok(180),
+        "270" => Ok(270),
+        _ => Err(format!("expected 90, 180, or 270: {}", s)),
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+    use image::Rgb;
+
+    use super::{parse_color, parse_fraction, parse_length, parse_positive, Args, Length};
+
+    #[test]
+    fn test_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_read_words() {
+        let args =
+            Args::try_parse_from(["wordsearch", "--words", "CAT,DOG", "--words", " BIRD , "])
+                .unwrap();
+        let words: Vec<_> = args
+            .read_words()
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.word)
+            .collect();
+        assert_eq!(vec!["CAT", "DOG", "BIRD"], words);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Ok(Rgb([255, 0, 0])), parse_color("red"));
+        assert_eq!(Ok(Rgb([0x12, 0xab, 0xff])), parse_color("#12abFF"));
+        assert_eq!(Ok(Rgb([0x12, 0xab, 0xff])), parse_color("12abff"));
+        assert!(parse_color("#12ab").is_err());
+        assert!(parse_color("chartreuse").is_err());
+    }
+
+    #[test]
+    fn test_parse_positive() {
+        assert_eq!(Ok(1.5), parse_positive("1.5"));
+        assert!(parse_positive("0").is_err());
+        assert!(parse_positive("NaN").is_err());
+        assert!(parse_positive("inf").is_err());
+        assert_eq!(Ok(0.5), parse_fraction("0.5"));
+        assert!(parse_fraction("NaN").is_err());
+        assert!(parse_fraction("inf").is_err());
+    }
+
+    #[test]
+    fn test_parse_length() {
+        assert_eq!(Ok(Length::Millimeters(3.0)), parse_length("3mm"));
+        assert_eq!(Ok(Length::Inches(0.125)), parse_length("0.125in"));
+        assert_eq!(Ok(Length::Pixels(36.0)), parse_length("36"));
+        assert!(parse_length("3cm").is_err());
+        assert_eq!(35, Length::Millimeters(3.0).pixels(300.0));
+    }
 }
@@ -0,0 +1,373 @@
+//! An editor for the layout of a saved project, in the terminal. Words can be dragged or nudged into new places,
+//! turned, or moved somewhere random, and the filler can be chosen again, before the project is saved or exported.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::Error;
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use wordsearch::grid::{self, Cells, Direction, Placement, Puzzle};
+use wordsearch::script;
+use wordsearch::wordlist::Entry;
+
+use crate::project;
+
+/// How many random places to try when moving a word somewhere new.
+const REROLL_ATTEMPTS: usize = 1000;
+
+const HELP: &str = "Tab: next word  arrows/drag: move  r: turn  n: new place  f: new filler  s: save  e: export  q: quit";
+
+struct Editor {
+    args: Vec<String>,
+    seed: Option<u64>,
+    words: Vec<Entry>,
+    grid: Cells,
+    placements: Vec<Placement>,
+    alphabet: Vec<char>,
+    selected: usize,
+    /// Where a drag started, and where the selected word was then.
+    drag: Option<((usize, usize), (usize, usize))>,
+    message: String,
+    saved: bool,
+}
+
+/// Puts the terminal back the way it was when the editor exits, even on an error.
+struct RawMode;
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            event::DisableMouseCapture,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Edit the layout of the project saved in this file.
+pub fn edit(path: &Path) -> Result<(), Error> {
+    let project = project::read_project(path)?;
+    let grid_words: Vec<String> = project.words.iter().map(Entry::grid_form).collect();
+    let mut editor = Editor {
+        args: project.args,
+        seed: project.seed,
+        words: project.words,
+        grid: project.puzzle.grid,
+        placements: project.puzzle.placements,
+        alphabet: script::filler_alphabet(&grid_words),
+        selected: 0,
+        drag: None,
+        message: String::new(),
+        saved: true,
+    };
+    if editor.placements.is_empty() {
+        return Err(anyhow::anyhow!("There are no words to edit in {:?}", path));
+    }
+
+    terminal::enable_raw_mode()?;
+    let _raw_mode = RawMode;
+    execute!(
+        io::stdout(),
+        terminal::EnterAlternateScreen,
+        cursor::Hide,
+        event::EnableMouseCapture
+    )?;
+    loop {
+        editor.draw(&mut io::stdout())?;
+        match event::read()? {
+            Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) => {
+                editor.message.clear();
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc if editor.saved => return Ok(()),
+                    KeyCode::Char('q') | KeyCode::Esc => {
+                        editor.message =
+                            "Not saved. Press s to save, or Q to quit anyway".to_string();
+                    }
+                    KeyCode::Char('Q') => return Ok(()),
+                    KeyCode::Tab => {
+                        editor.selected = (editor.selected + 1) % editor.placements.len()
+                    }
+                    KeyCode::BackTab => {
+                        let n = editor.placements.len();
+                        editor.selected = (editor.selected + n - 1) % n;
+                    }
+                    KeyCode::Left => editor.nudge(-1, 0),
+                    KeyCode::Right => editor.nudge(1, 0),
+                    KeyCode::Up => editor.nudge(0, -1),
+                    KeyCode::Down => editor.nudge(0, 1),
+                    KeyCode::Char('r') => editor.turn(),
+                    KeyCode::Char('n') => editor.reroll(&mut rand::thread_rng()),
+                    KeyCode::Char('f') => editor.refill(&mut rand::thread_rng()),
+                    KeyCode::Char('s') => editor.save(path)?,
+                    KeyCode::Char('e') => editor.export(path)?,
+                    _ => (),
+                }
+            }
+            Event::Mouse(mouse) => editor.mouse(mouse),
+            _ => (),
+        }
+    }
+}
+
+impl Editor {
+    fn draw<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        let selected = &self.placements[self.selected];
+        for (y, row) in self.grid.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, y as u16))?;
+            for (x, letter) in row.iter().enumerate() {
+                // The selected word is reversed, the other words are bold, and the filler is dim.
+                let attribute = if selected.cells().any(|c| c == (x, y)) {
+                    Attribute::Reverse
+                } else if self
+                    .placements
+                    .iter()
+                    .any(|p| p.cells().any(|c| c == (x, y)))
+                {
+                    Attribute::Bold
+                } else {
+                    Attribute::Dim
+                };
+                let letter = letter.unwrap_or(wordsearch::grid::EMPTY);
+                queue!(
+                    out,
+                    SetAttribute(attribute),
+                    Print(letter),
+                    SetAttribute(Attribute::Reset),
+                    Print(' ')
+                )?;
+            }
+        }
+        let key_x = 2 * self.grid[0].len() as u16 + 2;
+        for (i, p) in self.placements.iter().enumerate() {
+            let attribute = match i == self.selected {
+                true => Attribute::Reverse,
+                false => Attribute::Reset,
+            };
+            queue!(
+                out,
+                cursor::MoveTo(key_x, i as u16),
+                SetAttribute(attribute),
+                Print(&p.word),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+        let bottom = self.grid.len().max(self.placements.len()) as u16 + 1;
+        queue!(
+            out,
+            cursor::MoveTo(0, bottom),
+            Print(HELP),
+            cursor::MoveTo(0, bottom + 1),
+            Print(&self.message)
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Return whether the selected word could go here: in the grid, and agreeing with every other word it crosses.
+    fn fits(&self, placement: &Placement) -> bool {
+        let (width, height) = (self.grid[0].len() as isize, self.grid.len() as isize);
+        let (dx, dy) = placement.direction.next();
+        let len = placement.word.chars().count() as isize - 1;
+        let (end_x, end_y) = (
+            placement.x as isize + dx * len,
+            placement.y as isize + dy * len,
+        );
+        if !(0..width).contains(&end_x) || !(0..height).contains(&end_y) {
+            return false;
+        }
+        placement
+            .word
+            .chars()
+            .zip(placement.cells())
+            .all(|(letter, (x, y))| {
+                self.placements
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, p)| *i != self.selected && p.cells().any(|c| c == (x, y)))
+                    .all(|_| self.grid[y][x].is_some_and(|c| grid::same_letter(c, letter)))
+            })
+    }
+
+    /// Move the selected word here, if it fits, and fill the cells it leaves with new filler.
+    fn place(&mut self, placement: Placement) -> bool {
+        if !self.fits(&placement) {
+            return false;
+        }
+        let old = self.placements[self.selected].clone();
+        self.placements[self.selected] = placement.clone();
+        let mut rng = rand::thread_rng();
+        for (x, y) in old.cells() {
+            if !self
+                .placements
+                .iter()
+                .any(|p| p.cells().any(|c| c == (x, y)))
+            {
+                self.grid[y][x] = self.alphabet.choose(&mut rng).copied();
+            }
+        }
+        for (letter, (x, y)) in placement.word.chars().zip(placement.cells()) {
+            self.grid[y][x] = Some(letter);
+        }
+        self.saved = false;
+        true
+    }
+
+    fn nudge(&mut self, dx: isize, dy: isize) {
+        let p = self.placements[self.selected].clone();
+        let moved = match (p.x.checked_add_signed(dx), p.y.checked_add_signed(dy)) {
+            (Some(x), Some(y)) => self.place(Placement { x, y, ..p.clone() }),
+            _ => false,
+        };
+        if !moved {
+            self.message = format!("{} can't move there", p.word);
+        }
+    }
+
+    /// Turn the selected word to the next direction it fits in, about its first letter.
+    fn turn(&mut self) {
+        let p = self.placements[self.selected].clone();
+        let start = Direction::ALL
+            .iter()
+            .position(|&d| d == p.direction)
+            .unwrap_or(0);
+        let turned = (1..Direction::ALL.len())
+            .map(|i| Direction::ALL[(start + i) % Direction::ALL.len()])
+            .any(|direction| {
+                self.place(Placement {
+                    direction,
+                    ..p.clone()
+                })
+            });
+        if !turned {
+            self.message = format!("{} can't turn", p.word);
+        }
+    }
+
+    /// Move the selected word to a random place it fits.
+    fn reroll<R: Rng>(&mut self, rng: &mut R) {
+        let p = self.placements[self.selected].clone();
+        let (width, height) = (self.grid[0].len(), self.grid.len());
+        let placed = (0..REROLL_ATTEMPTS).any(|_| {
+            self.place(Placement {
+                x: rng.gen_range(0..width),
+                y: rng.gen_range(0..height),
+                direction: *Direction::ALL.choose(rng).unwrap(),
+                ..p.clone()
+            })
+        });
+        if !placed {
+            self.message = format!("Couldn't find another place for {}", p.word);
+        }
+    }
+
+    /// Choose new filler for every cell that isn't part of a word.
+    fn refill<R: Rng>(&mut self, rng: &mut R) {
+        for (y, row) in self.grid.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !self
+                    .placements
+                    .iter()
+                    .any(|p| p.cells().any(|c| c == (x, y)))
+                {
+                    *cell = self.alphabet.choose(rng).copied();
+                }
+            }
+        }
+        self.saved = false;
+    }
+
+    /// Select the word under the mouse, and drag it along.
+    fn mouse(&mut self, mouse: MouseEvent) {
+        // Each letter takes two columns, with the space after it.
+        let (x, y) = (mouse.column as usize / 2, mouse.row as usize);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Keep the selected word if it's the one clicked, even where it crosses another.
+                if !self.placements[self.selected].cells().any(|c| c == (x, y)) {
+                    let under = self
+                        .placements
+                        .iter()
+                        .position(|p| p.cells().any(|c| c == (x, y)));
+                    if let Some(i) = under {
+                        self.selected = i;
+                    }
+                }
+                let p = &self.placements[self.selected];
+                self.drag = Some(((x, y), (p.x, p.y)));
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(((x0, y0), (px, py))) = self.drag {
+                    let new_x = (px + x).checked_sub(x0);
+                    let new_y = (py + y).checked_sub(y0);
+                    if let (Some(x), Some(y)) = (new_x, new_y) {
+                        let p = self.placements[self.selected].clone();
+                        self.place(Placement { x, y, ..p });
+                    }
+                }
+            }
+            MouseEventKind::Up(_) => self.drag = None,
+            _ => (),
+        }
+    }
+
+    fn puzzle(&self) -> Puzzle {
+        Puzzle {
+            grid: self.grid.clone(),
+            placements: self.placements.clone(),
+            dropped: vec![],
+            attempts: vec![],
+        }
+    }
+
+    fn save(&mut self, path: &Path) -> Result<(), Error> {
+        let puzzle = self.puzzle();
+        project::write_project(
+            File::create(path)?,
+            &self.args,
+            self.seed,
+            &self.words,
+            &puzzle,
+        )?;
+        self.saved = true;
+        self.message = format!("Saved {}", path.to_string_lossy());
+        Ok(())
+    }
+
+    /// Save the images, the way they'd be saved by drawing the project.
+    fn export(&mut self, path: &Path) -> Result<(), Error> {
+        let args = project::merge_args(self.args.clone(), vec!["wordsearch".into()])?;
+        let answers = args.teachers_edition.then_some("teacher");
+        crate::save_images(
+            &self.words,
+            &self.puzzle(),
+            &args,
+            None,
+            path,
+            None,
+            answers,
+        )?;
+        self.message = "Exported".to_string();
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+//! The ways making a puzzle can fail. Written out by hand, rather than derived, so the placement engine needs no
+//! crate but rand. The conversions from the errors of the drawing crates are only there with the "render" feature.
+
+use std::sync::Arc;
+use std::{fmt, io};
+
+use rand::distributions::WeightedError;
+
+#[derive(Clone, Debug)]
+pub enum WordSearchError {
+    EmptyWordList,
+
+    /// One of the words has no letters, so there's nothing of it to hide.
+    EmptyWord,
+
+    /// The word has an emoji made of more than one character, which can't fit in one cell.
+    EmojiSequence {
+        word: String,
+    },
+
+    WordTooLong {
+        word: String,
+        width: usize,
+        height: usize,
+        margin: usize,
+    },
+
+    PlacementFailed {
+        word: String,
+        attempts: usize,
+    },
+
+    /// In a criss-cross puzzle, there was nowhere the word could cross the words already placed.
+    NoCrossing {
+        word: String,
+    },
+
+    TimedOut {
+        word: String,
+    },
+
+    BudgetExhausted {
+        word: String,
+    },
+
+    /// The deadline passed while retrying layouts, after this many of them.
+    LayoutTimedOut {
+        tries: usize,
+    },
+
+    /// Some of the words couldn't be placed, and dropping them wasn't allowed. Each failure says why.
+    WordsNotPlaced {
+        failures: Vec<WordSearchError>,
+        total: usize,
+    },
+
+    TooFewIntersections {
+        wanted: usize,
+        tries: usize,
+        most: usize,
+    },
+
+    DistractorsFailed {
+        tries: usize,
+    },
+
+    ExtraCopies {
+        tries: usize,
+    },
+
+    DirectionsUnused {
+        tries: usize,
+    },
+
+    TooFewWordsForDirections {
+        words: usize,
+        directions: usize,
+    },
+
+    /// The cancel flag was set while the puzzle was being made.
+    Cancelled {
+        placed: usize,
+        total: usize,
+    },
+
+    NoGridSize,
+
+    SmallestTimedOut {
+        width: usize,
+        height: usize,
+    },
+
+    BadLetterWeights(WeightedError),
+
+    EmptyCorpus,
+
+    /// Reading or writing a file failed.
+    Io(Arc<io::Error>),
+
+    /// The word list couldn't be read: it's empty, or has a line or column that doesn't make sense.
+    WordList(String),
+
+    /// The font couldn't be loaded, or has no glyph for one of the letters.
+    Font(String),
+
+    /// The page couldn't be drawn or encoded.
+    Render(String),
+
+    /// An option, puzzle code, or other input doesn't make sense.
+    InvalidInput(String),
+
+    /// A bug made the library panic, caught before it could unwind into a caller in another language.
+    Panicked(String),
+}
+
+impl fmt::Display for WordSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use WordSearchError::*;
+        match self {
+            EmptyWordList => write!(f, "There are no words to place"),
+            EmptyWord => write!(f, "One of the words has no letters to hide"),
+            EmojiSequence { word } => write!(
+                f,
+                "{word}: has an emoji made of more than one character, like a flag or a skin tone, which can't fit in \
+                 one cell"
+            ),
+            WordTooLong {
+                word,
+                width,
+                height,
+                margin,
+            } => write!(
+                f,
+                "{word}: too long to fit in the {width}x{height} grid{} in any of the allowed directions",
+                margin_text(*margin)
+            ),
+            PlacementFailed { word, attempts } => write!(
+                f,
+                "{word}: no room left for it among the other words after {attempts} tries"
+            ),
+            NoCrossing { word } => write!(f, "{word}: nowhere it can cross the other words"),
+            TimedOut { word } => write!(f, "{word}: timed out before it was placed"),
+            BudgetExhausted { word } => write!(
+                f,
+                "{word}: the attempt budget ran out before it was placed"
+            ),
+            LayoutTimedOut { tries } => write!(f, "Timed out after trying {tries} layouts"),
+            WordsNotPlaced { failures, total } => write!(
+                f,
+                "Couldn't place {} of {total} words:\n  {}",
+                failures.len(),
+                list(failures)
+            ),
+            TooFewIntersections {
+                wanted,
+                tries,
+                most,
+            } => write!(
+                f,
+                "Couldn't get {wanted} intersections after {tries} tries; the most was {most}"
+            ),
+            DistractorsFailed { tries } => write!(
+                f,
+                "Couldn't place the distractors without spelling out a word twice, after {tries} tries"
+            ),
+            ExtraCopies { tries } => write!(
+                f,
+                "Couldn't fill the grid without spelling out an extra copy of a word, after {tries} tries"
+            ),
+            DirectionsUnused { tries } => write!(
+                f,
+                "Couldn't get a word going in every direction, after {tries} tries"
+            ),
+            TooFewWordsForDirections { words, directions } => write!(
+                f,
+                "Only {words} words, too few for one to go in each of the {directions} directions"
+            ),
+            Cancelled { placed, total } => {
+                write!(f, "Cancelled after placing {placed} of {total} words")
+            }
+            NoGridSize => write!(f, "Couldn't fit the words into a grid of any size"),
+            SmallestTimedOut { width, height } => write!(
+                f,
+                "Timed out looking for the smallest grid, after trying {width}x{height}"
+            ),
+            BadLetterWeights(e) => write!(f, "Bad letter weights: {e}"),
+            EmptyCorpus => write!(f, "No letters in the corpus"),
+            Io(e) => write!(f, "{e}"),
+            WordList(message) | Font(message) | Render(message) | InvalidInput(message) => {
+                write!(f, "{message}")
+            }
+            Panicked(message) => write!(f, "Internal error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for WordSearchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WordSearchError::BadLetterWeights(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<WeightedError> for WordSearchError {
+    fn from(e: WeightedError) -> Self {
+        WordSearchError::BadLetterWeights(e)
+    }
+}
+
+impl From<io::Error> for WordSearchError {
+    fn from(e: io::Error) -> Self {
+        WordSearchError::Io(Arc::new(e))
+    }
+}
+
+/// Writing the text of a page into a string.
+impl From<fmt::Error> for WordSearchError {
+    fn from(e: fmt::Error) -> Self {
+        WordSearchError::Render(e.to_string())
+    }
+}
+
+#[cfg(feature = "render")]
+impl From<image::ImageError> for WordSearchError {
+    fn from(e: image::ImageError) -> Self {
+        WordSearchError::Render(e.to_string())
+    }
+}
+
+#[cfg(feature = "render")]
+impl From<png::EncodingError> for WordSearchError {
+    fn from(e: png::EncodingError) -> Self {
+        match e {
+            png::EncodingError::IoError(e) => e.into(),
+            e => WordSearchError::Render(e.to_string()),
+        }
+    }
+}
+
+/// A JSON file given as input, like a --letter-colors palette, that doesn't parse.
+#[cfg(feature = "render")]
+impl From<serde_json::Error> for WordSearchError {
+    fn from(e: serde_json::Error) -> Self {
+        WordSearchError::InvalidInput(e.to_string())
+    }
+}
+
+fn margin_text(margin: usize) -> String {
+    match margin {
+        0 => String::new(),
+        n => format!(", inside its {}-cell margin,", n),
+    }
+}
+
+fn list(failures: &[WordSearchError]) -> String {
+    let lines: Vec<String> = failures.iter().map(ToString::to_string).collect();
+    lines.join("\n  ")
+}
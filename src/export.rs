@@ -0,0 +1,199 @@
+use std::io::Write;
+
+use anyhow::Error;
+use image::Rgb;
+use serde_json::json;
+
+use crate::grid::{self, Placement, Puzzle};
+use crate::wordlist::Entry;
+
+/// Write the answers as CSV: one row per word, with its start, direction, and end. Rows and columns are numbered from
+/// 1, as they would be in a spreadsheet.
+pub fn write_answers_csv<W: Write>(mut out: W, placements: &[Placement]) -> Result<(), Error> {
+    writeln!(out, "word,start_row,start_col,direction,end_row,end_col")?;
+    for p in placements {
+        let (end_x, end_y) = p.end();
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            p.word,
+            p.y + 1,
+            p.x + 1,
+            p.direction,
+            end_y + 1,
+            end_x + 1
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the answers as a list a person can read: one line per word, naming the cells where it starts and ends, like
+/// "WHALE: C7 → C11 (south)".
+pub fn write_answers_list<W: Write>(mut out: W, placements: &[Placement]) -> Result<(), Error> {
+    for p in placements {
+        let (end_x, end_y) = p.end();
+        writeln!(
+            out,
+            "{}: {} → {} ({})",
+            p.word,
+            grid::cell_name(p.x, p.y),
+            grid::cell_name(end_x, end_y),
+            p.direction
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the puzzle for a terminal: the grid, with a space between letters so it comes out about square, and then the
+/// words of the key. The letters of the answers are drawn in bold in the `highlight` color, if there is one, using
+/// ANSI escape codes.
+pub fn write_preview<W: Write>(
+    mut out: W,
+    words: &[Entry],
+    puzzle: &Puzzle,
+    highlight: Option<Rgb<u8>>,
+) -> Result<(), Error> {
+    for (y, row) in puzzle.grid.iter().enumerate() {
+        let mut line = String::new();
+        for (x, c) in row.iter().enumerate() {
+            if x > 0 {
+                line.push(' ');
+            }
+            let letter = c.unwrap_or(grid::EMPTY);
+            let answer = puzzle
+                .placements
+                .iter()
+                .any(|p| p.cells().any(|cell| cell == (x, y)));
+            match highlight {
+                Some(Rgb([r, g, b])) if answer => {
+                    line.push_str(&format!("\x1b[1;38;2;{r};{g};{b}m{letter}\x1b[0m"))
+                }
+                _ => line.push(letter),
+            }
+        }
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out)?;
+    let words: Vec<&str> = words.iter().map(|e| e.word.as_str()).collect();
+    writeln!(out, "{}", words.join(", "))?;
+    Ok(())
+}
+
+/// Write the grid as text, one row per line, with the letters of the answers in uppercase, the filler in lowercase,
+/// and any empty cells as dots.
+pub fn write_solution_text<W: Write>(mut out: W, puzzle: &Puzzle) -> Result<(), Error> {
+    let mut grid: Vec<Vec<String>> = puzzle
+        .grid
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|c| c.unwrap_or(grid::EMPTY).to_lowercase().collect())
+                .collect()
+        })
+        .collect();
+    for (x, y) in puzzle.placements.iter().flat_map(Placement::cells) {
+        // A --mixed-case grid has lowercase letters in the answers too.
+        grid[y][x] = puzzle.grid[y][x]
+            .unwrap_or(grid::EMPTY)
+            .to_uppercase()
+            .collect();
+    }
+    for row in grid {
+        writeln!(out, "{}", row.concat())?;
+    }
+    Ok(())
+}
+
+/// Write the puzzle in the ipuz open puzzle format, as a "wordsearch" kind puzzle. Empty cells are null.
+pub fn write_ipuz<W: Write>(out: W, puzzle: &Puzzle) -> Result<(), Error> {
+    let grid: Vec<Vec<Option<String>>> = puzzle
+        .grid
+        .iter()
+        .map(|row| row.iter().map(|c| c.map(String::from)).collect())
+        .collect();
+    let words: Vec<&str> = puzzle.placements.iter().map(|p| p.word.as_str()).collect();
+    let ipuz = json!({
+        "version": "http://ipuz.org/v2",
+        "kind": ["http://ipuz.org/wordsearch#1"],
+        "dimensions": {
+            "width": puzzle.grid[0].len(),
+            "height": puzzle.grid.len(),
+        },
+        "puzzle": grid,
+        "solution": words,
+    });
+    serde_json::to_writer_pretty(out, &ipuz)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+
+    use crate::grid::{Direction, Placement, Puzzle};
+
+    use super::{write_answers_csv, write_answers_list, write_solution_text};
+
+    fn placements() -> Vec<Placement> {
+        vec![
+            Placement {
+                word: "CAT".to_string(),
+                x: 0,
+                y: 0,
+                direction: Direction::East,
+            },
+            Placement {
+                word: "DOG".to_string(),
+                x: 4,
+                y: 2,
+                direction: Direction::Northwest,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_write_answers_csv() -> Result<(), Error> {
+        let mut out = vec![];
+        write_answers_csv(&mut out, &placements())?;
+        let expected = "word,start_row,start_col,direction,end_row,end_col\n\
+                        CAT,1,1,east,1,3\n\
+                        DOG,3,5,northwest,1,3\n";
+        assert_eq!(expected, String::from_utf8(out)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_answers_list() -> Result<(), Error> {
+        let mut out = vec![];
+        write_answers_list(&mut out, &placements())?;
+        let expected = "CAT: A1 → C1 (east)\nDOG: E3 → C1 (northwest)\n";
+        assert_eq!(expected, String::from_utf8(out)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_solution_text() -> Result<(), Error> {
+        let mut placements = placements();
+        placements[1] = Placement {
+            word: "DOG".to_string(),
+            x: 3,
+            y: 0,
+            direction: Direction::South,
+        };
+        for rows in [["CATD", "XXXO", "XXXG"], ["cAtD", "xXxo", "XxXg"]] {
+            let puzzle = Puzzle {
+                grid: rows
+                    .iter()
+                    .map(|row| row.chars().map(Some).collect())
+                    .collect(),
+                placements: placements.clone(),
+                dropped: vec![],
+                attempts: vec![],
+            };
+            let mut out = vec![];
+            write_solution_text(&mut out, &puzzle)?;
+            assert_eq!("CATD\nxxxO\nxxxG\n", String::from_utf8(out)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+//! C bindings, so puzzles can be made from C, C++, or Swift. Building with the "ffi" feature writes the header for
+//! these functions to include/wordsearch.h.
+//!
+//! Strings and buffers returned by these functions belong to the caller, who frees them with
+//! `wordsearch_free_string` and `wordsearch_free_bytes`. A null return means the call failed, and
+//! `wordsearch_last_error` says why.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::rc::Rc;
+
+use clap::Parser;
+use image::ImageOutputFormat;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::json;
+
+use crate::config::Args;
+use crate::error::WordSearchError;
+use crate::filler;
+use crate::grid::{self, Grid, Puzzle};
+use crate::render;
+use crate::script;
+use crate::wordlist::Entry;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Make a puzzle from a word list with one word per line. A width or height of 0 picks a size to fit the words.
+///
+/// Returns the puzzle as JSON, like `{"grid": ["CAT", ...], "placements": [{"word": "CAT", "x": 0, "y": 0,
+/// "direction": "east"}, ...]}`, or null on failure.
+///
+/// # Safety
+///
+/// `words` must be a valid, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wordsearch_generate(
+    words: *const c_char,
+    width: usize,
+    height: usize,
+    seed: u64,
+) -> *mut c_char {
+    let json = catch_panic(|| {
+        let puzzle = generate(&read_words(words)?, width, height, seed)?;
+        CString::new(to_json(&puzzle).to_string())
+            .map_err(|e| WordSearchError::InvalidInput(e.to_string()))
+    });
+    match json {
+        Ok(json) => json.into_raw(),
+        Err(e) => fail(e, ptr::null_mut()),
+    }
+}
+
+/// Make a puzzle the same way as `wordsearch_generate`, and render the page as a PNG image, with the answers marked
+/// if `solution` is set. The length of the image is written to `len`.
+///
+/// Returns the image, or null on failure.
+///
+/// # Safety
+///
+/// `words` must be a valid, NUL-terminated string, and `len` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn wordsearch_render_png(
+    words: *const c_char,
+    width: usize,
+    height: usize,
+    seed: u64,
+    solution: bool,
+    len: *mut usize,
+) -> *mut u8 {
+    let png = catch_panic(|| {
+        let words = read_words(words)?;
+        let puzzle = generate(&words, width, height, seed)?;
+        let args = Args::parse_from(["wordsearch"]);
+        let image = render::make_image(&words, &puzzle, &args, None, solution)?;
+        let mut png = Cursor::new(vec![]);
+        image.write_to(&mut png, ImageOutputFormat::Png)?;
+        Ok(png.into_inner().into_boxed_slice())
+    });
+    match png {
+        Ok(png) => {
+            *len = png.len();
+            Box::into_raw(png) as *mut u8
+        }
+        Err(e) => fail(e, ptr::null_mut()),
+    }
+}
+
+/// Free a string returned by one of these functions.
+///
+/// # Safety
+///
+/// `s` must have come from this library, and not been freed already. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn wordsearch_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Free a buffer returned by one of these functions, along with the length it was returned with.
+///
+/// # Safety
+///
+/// `bytes` must have come from this library with this length, and not been freed already. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn wordsearch_free_bytes(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
+
+/// Return why the last call on this thread failed, or null if none has. The string is owned by the library, and is
+/// good until the next failure on this thread.
+#[no_mangle]
+pub extern "C" fn wordsearch_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+unsafe fn read_words(words: *const c_char) -> Result<Vec<Entry>, WordSearchError> {
+    if words.is_null() {
+        return Err(WordSearchError::InvalidInput("No word list".to_string()));
+    }
+    let words: Vec<Entry> = CStr::from_ptr(words)
+        .to_str()
+        .map_err(|e| WordSearchError::InvalidInput(e.to_string()))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Entry::new(line.to_string()))
+        .collect();
+    Ok(words)
+}
+
+fn generate(
+    words: &[Entry],
+    width: usize,
+    height: usize,
+    seed: u64,
+) -> Result<Puzzle, WordSearchError> {
+    let grid_words: Vec<String> = words.iter().map(Entry::grid_form).collect();
+    let filler = filler::Random::new(script::filler_alphabet(&grid_words));
+    let size = |n| (n > 0).then_some(n);
+    let puzzle = Grid::new(grid_words, size(width), size(height))
+        .with_filler(Rc::new(filler))
+        .generate(&mut StdRng::seed_from_u64(seed))?;
+    Ok(puzzle)
+}
+
+fn to_json(puzzle: &Puzzle) -> serde_json::Value {
+    let grid: Vec<String> = puzzle
+        .grid
+        .iter()
+        .map(|row| row.iter().map(|c| c.unwrap_or(grid::EMPTY)).collect())
+        .collect();
+    let placements: Vec<_> = puzzle
+        .placements
+        .iter()
+        .map(|p| json!({"word": p.word, "x": p.x, "y": p.y, "direction": p.direction.to_string()}))
+        .collect();
+    json!({"grid": grid, "placements": placements})
+}
+
+/// Run the body of one of these functions, turning a panic into an error, since unwinding into the caller's C code
+/// is undefined behavior.
+fn catch_panic<T>(body: impl FnOnce() -> Result<T, WordSearchError>) -> Result<T, WordSearchError> {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(WordSearchError::Panicked(message))
+    })
+}
+
+/// Remember the error for `wordsearch_last_error`, and return the value that means failure.
+fn fail<T>(error: WordSearchError, failed: T) -> T {
+    let message = CString::new(error.to_string().replace('\0', "")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use super::{catch_panic, wordsearch_last_error, wordsearch_render_png};
+    use super::{wordsearch_free_bytes, wordsearch_free_string, wordsearch_generate};
+
+    #[test]
+    fn test_ffi() {
+        let words = CString::new("Cat\nDog\n").unwrap();
+        unsafe {
+            let json = wordsearch_generate(words.as_ptr(), 5, 5, 1);
+            let puzzle: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(5, puzzle["grid"].as_array().unwrap().len());
+            assert_eq!("CAT", puzzle["placements"][0]["word"]);
+            wordsearch_free_string(json);
+
+            let mut len = 0;
+            let png = wordsearch_render_png(words.as_ptr(), 5, 5, 1, true, &mut len);
+            assert_eq!(b"\x89PNG", std::slice::from_raw_parts(png, 4));
+            wordsearch_free_bytes(png, len);
+
+            let empty = CString::new("").unwrap();
+            assert!(wordsearch_generate(empty.as_ptr(), 0, 0, 1).is_null());
+            let error = CStr::from_ptr(wordsearch_last_error());
+            assert_eq!("There are no words to place", error.to_str().unwrap());
+        }
+
+        let caught = catch_panic::<()>(|| panic!("out of {}", "letters"));
+        assert_eq!(
+            "Internal error: out of letters",
+            caught.unwrap_err().to_string()
+        );
+    }
+}
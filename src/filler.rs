@@ -0,0 +1,220 @@
+//! Ways to choose the letters that fill the cells of the grid not taken by any word.
+
+use std::collections::BTreeMap;
+
+use rand::distributions::{Distribution, Uniform, WeightedIndex};
+use rand::RngCore;
+
+use crate::error::WordSearchError;
+
+/// Chooses the letters for the blank cells of the grid.
+pub trait Filler {
+    /// Return the letter for the `index`th blank cell, counting row by row from the top left. `previous` is the
+    /// letter to the left of the cell, if it's not at the start of its row.
+    fn letter(&self, index: usize, previous: Option<char>, rng: &mut dyn RngCore) -> char;
+}
+
+/// Every letter of the alphabet is equally likely.
+pub struct Random {
+    alphabet: Vec<char>,
+    // Sampled the same way as a range of chars, so the puzzles made from a given seed stay the same as they've always
+    // been.
+    index: Uniform<u32>,
+}
+
+impl Random {
+    /// Choose from these letters. There must be at least one.
+    pub fn new(alphabet: Vec<char>) -> Self {
+        let index = Uniform::new_inclusive(0, alphabet.len() as u32 - 1);
+        Self { alphabet, index }
+    }
+}
+
+impl Default for Random {
+    /// The letters A through Z.
+    fn default() -> Self {
+        Self::new(('A'..='Z').collect())
+    }
+}
+
+impl Filler for Random {
+    fn letter(&self, _index: usize, _previous: Option<char>, mut rng: &mut dyn RngCore) -> char {
+        self.alphabet[self.index.sample(&mut rng) as usize]
+    }
+}
+
+/// Letters are chosen as often as they're used in a language, so the filler doesn't stand out from the words.
+pub struct Frequency {
+    letters: Vec<char>,
+    weights: WeightedIndex<f64>,
+}
+
+/// How often each letter is used in English text, in percent.
+const ENGLISH: [f64; 26] = [
+    8.2, 1.5, 2.8, 4.3, 12.7, 2.2, 2.0, 6.1, 7.0, 0.15, 0.77, 4.0, 2.4, 6.7, 7.5, 1.9, 0.095, 6.0,
+    6.3, 9.1, 2.8, 0.98, 2.4, 0.15, 2.0, 0.074,
+];
+
+impl Frequency {
+    /// Use these letters, each in proportion to its weight.
+    pub fn new(letters: Vec<char>, weights: &[f64]) -> Result<Self, WordSearchError> {
+        let weights = WeightedIndex::new(weights)?;
+        Ok(Self { letters, weights })
+    }
+
+    /// Use the letters of English, as often as they appear in English text.
+    pub fn english() -> Self {
+        Self::new(('A'..='Z').collect(), &ENGLISH).unwrap()
+    }
+
+    /// Use the letters that appear in the text, as often as they appear in it.
+    pub fn from_corpus(text: &str) -> Result<Self, WordSearchError> {
+        let mut letters = vec![];
+        let mut counts = vec![];
+        for c in text
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .flat_map(char::to_uppercase)
+        {
+            match letters.iter().position(|&l| l == c) {
+                Some(i) => counts[i] += 1.0,
+                None => {
+                    letters.push(c);
+                    counts.push(1.0);
+                }
+            }
+        }
+        if letters.is_empty() {
+            return Err(WordSearchError::EmptyCorpus);
+        }
+        Self::new(letters, &counts)
+    }
+}
+
+impl Filler for Frequency {
+    fn letter(&self, _index: usize, _previous: Option<char>, mut rng: &mut dyn RngCore) -> char {
+        self.letters[self.weights.sample(&mut rng)]
+    }
+}
+
+/// Spells out a message in the blank cells, in reading order, for the solver to find once all the words are
+/// crossed out. Any cells left over after the message are filled by another filler.
+pub struct Message {
+    message: Vec<char>,
+    rest: Box<dyn Filler>,
+}
+
+impl Message {
+    pub fn new(message: &str, rest: Box<dyn Filler>) -> Self {
+        Self {
+            message: message.chars().collect(),
+            rest,
+        }
+    }
+}
+
+impl Filler for Message {
+    fn letter(&self, index: usize, previous: Option<char>, rng: &mut dyn RngCore) -> char {
+        match self.message.get(index) {
+            Some(&c) => c,
+            None => self.rest.letter(index, previous, rng),
+        }
+    }
+}
+
+/// Another filler, kept from using some letters: it's asked again when it picks one of them, up to a point, in case
+/// it can't pick anything else.
+pub struct Avoiding {
+    filler: Box<dyn Filler>,
+    letters: Vec<char>,
+}
+
+/// How many times [`Avoiding`] asks for another letter before it settles for one it would rather avoid.
+const AVOID_ATTEMPTS: usize = 100;
+
+impl Avoiding {
+    pub fn new(filler: Box<dyn Filler>, letters: Vec<char>) -> Self {
+        Self { filler, letters }
+    }
+}
+
+impl Filler for Avoiding {
+    fn letter(&self, index: usize, previous: Option<char>, rng: &mut dyn RngCore) -> char {
+        let mut letter = self.filler.letter(index, previous, rng);
+        for _ in 1..AVOID_ATTEMPTS {
+            if !self.letters.contains(&letter) {
+                break;
+            }
+            letter = self.filler.letter(index, previous, rng);
+        }
+        letter
+    }
+}
+
+/// Each letter is chosen based on the one before it, as often as it follows that letter in a corpus, so runs of
+/// filler look like bits of real words.
+pub struct Markov {
+    start: Frequency,
+    next: BTreeMap<char, Frequency>,
+}
+
+impl Markov {
+    /// Learn which letters follow which from the words of the text.
+    pub fn from_corpus(text: &str) -> Result<Self, WordSearchError> {
+        let start = Frequency::from_corpus(text)?;
+        let mut counts: BTreeMap<char, BTreeMap<char, f64>> = BTreeMap::new();
+        for word in text.split(|c: char| !c.is_alphabetic()) {
+            let letters: Vec<char> = word.chars().flat_map(char::to_uppercase).collect();
+            for pair in letters.windows(2) {
+                *counts
+                    .entry(pair[0])
+                    .or_default()
+                    .entry(pair[1])
+                    .or_default() += 1.0;
+            }
+        }
+        let mut next = BTreeMap::new();
+        for (letter, followers) in counts {
+            let (letters, weights): (Vec<char>, Vec<f64>) = followers.into_iter().unzip();
+            next.insert(letter, Frequency::new(letters, &weights)?);
+        }
+        Ok(Self { start, next })
+    }
+}
+
+impl Filler for Markov {
+    /// Letters at the start of a row, or after a letter that never had anything after it, are chosen by how often
+    /// they appear in the corpus overall.
+    fn letter(&self, index: usize, previous: Option<char>, rng: &mut dyn RngCore) -> char {
+        let model = previous
+            .and_then(|c| self.next.get(&c))
+            .unwrap_or(&self.start);
+        model.letter(index, previous, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{Avoiding, Filler, Frequency, Markov, Message, Random};
+
+    #[test]
+    fn test_fillers() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let corpus = Frequency::from_corpus("a a, a!").unwrap();
+        assert!((0..10).all(|i| corpus.letter(i, None, &mut rng) == 'A'));
+        assert!(Frequency::from_corpus("123").is_err());
+
+        let message = Message::new("HI", Box::new(Random::new(vec!['X'])));
+        let letters: String = (0..4).map(|i| message.letter(i, None, &mut rng)).collect();
+        assert_eq!("HIXX", letters);
+
+        let markov = Markov::from_corpus("quiz quip, aqua").unwrap();
+        assert!((0..10).all(|i| markov.letter(i, Some('Q'), &mut rng) == 'U'));
+
+        let avoiding = Avoiding::new(Box::new(Random::new(vec!['M', 'W'])), vec!['W']);
+        assert!((0..10).all(|i| avoiding.letter(i, None, &mut rng) == 'M'));
+    }
+}
@@ -1,26 +1,412 @@
-use std::cmp::max;
+use std::cell::Cell;
+use std::cmp::{max, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-use anyhow::{anyhow, Error};
-use rand::rngs::ThreadRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
-use rand_derive2::RandGen;
 
+use crate::error::WordSearchError;
+use crate::filler::{self, Filler};
+use crate::script;
+
+/// The letters of a grid, by row. Cells without a letter are `None`.
+pub type Cells = Vec<Vec<Option<char>>>;
+
+#[derive(Clone)]
 pub struct Grid {
     wordlist: Vec<String>,
     width: usize,
     height: usize,
-    grid: Vec<Vec<Option<char>>>,
+    grid: Cells,
+    placements: Vec<Placement>,
+    attempts: Vec<usize>,
+    directions: Vec<Direction>,
+    /// Whether every direction a word fits in has to be used by at least one word.
+    all_directions: bool,
+    order: Order,
+    max_attempts: usize,
+    /// How many more places can be tried, shared by every layout tried for this puzzle: each retry, and each of the
+    /// --candidates.
+    budget: Rc<Cell<usize>>,
+    deadline: Option<Instant>,
+    candidates: usize,
+    weights: QualityWeights,
+    cancel: Option<Arc<AtomicBool>>,
+    allow_drop: bool,
+    /// The words that can be left out if they don't all fit, with how likely each is to be kept.
+    optional: HashMap<String, f32>,
+    overlap: bool,
+    min_intersections: usize,
+    margin: usize,
+    fill: bool,
+    distractors: bool,
+    /// Whether each word has to turn up only where it was hidden, and nowhere else in the filled grid.
+    exact: bool,
+    /// The most of the filled-in cells any one letter can fill.
+    max_filler_share: Option<f32>,
+    criss_cross: bool,
+    mixed_case: bool,
+    /// How many words in a row have been put off until later, in a criss-cross puzzle, because they couldn't cross
+    /// any of the words placed so far.
+    deferred: usize,
+    /// Whether a word couldn't be placed by trying random places, so the rest of the words are placed the most
+    /// constrained first, each in one of all the places it fits.
+    constrained: bool,
+    dropped: Vec<String>,
+    failures: Vec<WordSearchError>,
+    filler: Rc<dyn Filler>,
+    /// The letters of the puzzle this one is based on, to keep in the cells that aren't part of any new word.
+    base: Option<Cells>,
+}
+
+/// How an empty cell is written when a grid is written as text.
+pub const EMPTY: char = '.';
+
+/// How many places to try for each word before giving up.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 1000;
+
+/// How many places to try for all the words together before giving up.
+pub const DEFAULT_ATTEMPT_BUDGET: usize = 100_000;
+
+/// How much of the grid the words fill, when the size isn't given.
+pub const DEFAULT_DENSITY: f32 = 0.5;
+
+/// How many places that fit to compare for each word, when looking for the one that crosses the most letters.
+const INTERSECTION_CANDIDATES: usize = 50;
+
+/// How many layouts to try before giving up on getting enough intersections, on keeping the distractors or the
+/// filler from spelling out an extra copy of a word, or on connecting every word of a criss-cross puzzle.
+const LAYOUT_RETRIES: usize = 100;
+
+/// How many letters to draw from the filler for a cell whose letter is used too much, before choosing one of the
+/// other letters it filled in instead.
+const BALANCE_TRIES: usize = 20;
+
+/// How many places to try for each distractor before leaving it out.
+const DISTRACTOR_ATTEMPTS: usize = 100;
+
+/// The order to place the words in.
+#[cfg_attr(feature = "render", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// Longest words first, while there's the most room for them
+    LongestFirst,
+    /// The order they're listed in
+    Input,
+    /// A random order
+    #[default]
+    Shuffle,
+}
+
+/// Which way to flip a finished puzzle.
+#[cfg_attr(feature = "render", derive(clap::ValueEnum))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirror {
+    /// Left to right
+    #[cfg_attr(feature = "render", value(name = "h"))]
+    Horizontal,
+    /// Top to bottom
+    #[cfg_attr(feature = "render", value(name = "v"))]
+    Vertical,
+}
+
+/// A finished puzzle: the grid of letters, and where each word was hidden in it.
+pub struct Puzzle {
+    /// The letters, by row. Every cell has one, unless the puzzle was made without filler.
+    pub grid: Cells,
+    pub placements: Vec<Placement>,
+    /// Words that were left out because they couldn't be placed.
+    pub dropped: Vec<String>,
+    /// How many places were tried for each word before it fit, in the same order as the placements. Empty if the
+    /// puzzle wasn't generated.
+    pub attempts: Vec<usize>,
+}
+
+impl Puzzle {
+    /// Return the puzzle turned clockwise by this many quarter turns, with the words turned along with it.
+    pub fn rotate(&self, quarter_turns: u32) -> Puzzle {
+        let (width, height) = (self.grid[0].len() as isize, self.grid.len() as isize);
+        match quarter_turns % 4 {
+            1 => self.transform(height, width, |x, y| (height - 1 - y, x)),
+            2 => self.transform(width, height, |x, y| (width - 1 - x, height - 1 - y)),
+            3 => self.transform(height, width, |x, y| (y, width - 1 - x)),
+            _ => self.transform(width, height, |x, y| (x, y)),
+        }
+    }
+
+    /// Return the puzzle flipped, with the words flipped along with it, so they read backwards or upside down.
+    pub fn mirror(&self, mirror: Mirror) -> Puzzle {
+        let (width, height) = (self.grid[0].len() as isize, self.grid.len() as isize);
+        match mirror {
+            Mirror::Horizontal => self.transform(width, height, |x, y| (width - 1 - x, y)),
+            Mirror::Vertical => self.transform(width, height, |x, y| (x, height - 1 - y)),
+        }
+    }
+
+    /// Move every cell of the puzzle to where `point` says, in a grid of the new width and height. Each word's
+    /// direction comes from where its first two cells end up.
+    fn transform(
+        &self,
+        width: isize,
+        height: isize,
+        point: impl Fn(isize, isize) -> (isize, isize),
+    ) -> Puzzle {
+        let mut grid = vec![vec![None; width as usize]; height as usize];
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                let (x, y) = point(x as isize, y as isize);
+                grid[y as usize][x as usize] = cell;
+            }
+        }
+        let placements = self
+            .placements
+            .iter()
+            .map(|p| {
+                let (dx, dy) = p.direction.next();
+                let (x, y) = point(p.x as isize, p.y as isize);
+                let (next_x, next_y) = point(p.x as isize + dx, p.y as isize + dy);
+                let direction = Direction::ALL
+                    .into_iter()
+                    .find(|d| d.next() == (next_x - x, next_y - y))
+                    .unwrap();
+                Placement {
+                    word: p.word.clone(),
+                    x: x as usize,
+                    y: y as usize,
+                    direction,
+                }
+            })
+            .collect();
+        Puzzle {
+            grid,
+            placements,
+            dropped: self.dropped.clone(),
+            attempts: self.attempts.clone(),
+        }
+    }
+
+    /// Search the grid for the word, in every direction, and return where it was found.
+    pub fn find(&self, word: &str) -> Option<Placement> {
+        self.find_all(word).into_iter().next()
+    }
+
+    /// Search the grid for the word, in every direction, and return everywhere it was found. A word that reads the
+    /// same backwards is only returned once for each place it is.
+    pub fn find_all(&self, word: &str) -> Vec<Placement> {
+        let mut found: Vec<Placement> = vec![];
+        let letters: Vec<char> = word.chars().collect();
+        let (width, height) = (self.grid[0].len() as isize, self.grid.len() as isize);
+        for y in 0..height {
+            for x in 0..width {
+                for direction in Direction::ALL {
+                    let (dx, dy) = direction.next();
+                    let matches = letters.iter().enumerate().all(|(i, letter)| {
+                        let (cx, cy) = (x + dx * i as isize, y + dy * i as isize);
+                        (0..width).contains(&cx)
+                            && (0..height).contains(&cy)
+                            && self.grid[cy as usize][cx as usize]
+                                .is_some_and(|c| same_letter(c, *letter))
+                    });
+                    let placement = Placement {
+                        word: word.to_string(),
+                        x: x as usize,
+                        y: y as usize,
+                        direction,
+                    };
+                    let same_cells = |p: &Placement| {
+                        let mut cells: Vec<_> = p.cells().collect();
+                        cells.sort();
+                        cells
+                    };
+                    let seen = found
+                        .iter()
+                        .any(|p| same_cells(p) == same_cells(&placement));
+                    if matches && !seen {
+                        found.push(placement);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Return the words that can be found in more places than they were hidden.
+    pub fn duplicates(&self) -> Vec<&str> {
+        let mut duplicates: Vec<&str> = vec![];
+        for placement in &self.placements {
+            let word = placement.word.as_str();
+            let hidden = self.placements.iter().filter(|p| p.word == word).count();
+            if !duplicates.contains(&word) && self.find_all(word).len() > hidden {
+                duplicates.push(word);
+            }
+        }
+        duplicates
+    }
+
+    /// Return how many cells are shared by more than one word.
+    pub fn intersections(&self) -> usize {
+        let mut cells: Vec<_> = self.placements.iter().flat_map(Placement::cells).collect();
+        let all = cells.len();
+        cells.sort();
+        cells.dedup();
+        all - cells.len()
+    }
+
+    /// Return the other words that share a letter with this one.
+    pub fn crossings(&self, placement: &Placement) -> Vec<&Placement> {
+        let cells: Vec<_> = placement.cells().collect();
+        self.placements
+            .iter()
+            .filter(|p| p.word != placement.word && p.cells().any(|cell| cells.contains(&cell)))
+            .collect()
+    }
+
+    /// Score how good a puzzle the layout makes, higher being better. It adds up three things, each from 0 to about
+    /// 1 before it's weighted: how much of the grid the words cover, how many shared letters there are for each word,
+    /// and how evenly the words are spread over the directions.
+    pub fn quality(&self, weights: &QualityWeights) -> f32 {
+        let words = self.placements.len();
+        if words == 0 {
+            return 0.0;
+        }
+        let cells = self.grid.len() * self.grid[0].len();
+        let covered =
+            self.placements.iter().flat_map(Placement::cells).count() - self.intersections();
+        let density = covered as f32 / cells as f32;
+        let crossings = self.intersections() as f32 / words as f32;
+
+        // The entropy of the directions, as a fraction of the most there could be with this many words.
+        let entropy: f32 = Direction::ALL
+            .iter()
+            .map(|d| self.placements.iter().filter(|p| p.direction == *d).count())
+            .filter(|&n| n > 0)
+            .map(|n| {
+                let p = n as f32 / words as f32;
+                -p * p.ln()
+            })
+            .sum();
+        let most = (words.min(Direction::ALL.len()) as f32).ln();
+        let balance = if most > 0.0 { entropy / most } else { 1.0 };
+
+        weights.density * density
+            + weights.intersections * crossings
+            + weights.direction_balance * balance
+    }
+}
+
+/// How much each part of `Puzzle::quality` counts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityWeights {
+    pub density: f32,
+    pub intersections: f32,
+    pub direction_balance: f32,
+}
+
+impl Default for QualityWeights {
+    /// Count each part the same.
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            intersections: 1.0,
+            direction_balance: 1.0,
+        }
+    }
+}
+
+/// The location of one word in the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub word: String,
+    pub x: usize,
+    pub y: usize,
+    pub direction: Direction,
+}
+
+impl Placement {
+    /// Return the (x, y) position of the last letter of the word.
+    pub fn end(&self) -> (usize, usize) {
+        let (dx, dy) = self.direction.next();
+        let len = self.word.chars().count() as isize - 1;
+        (
+            (self.x as isize + dx * len) as usize,
+            (self.y as isize + dy * len) as usize,
+        )
+    }
+
+    /// Return the (x, y) position of each letter of the word.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (dx, dy) = self.direction.next();
+        (0..self.word.chars().count() as isize).map(move |i| {
+            (
+                (self.x as isize + dx * i) as usize,
+                (self.y as isize + dy * i) as usize,
+            )
+        })
+    }
+}
+
+/// Return how many letters are in the word, which may be more or less than the bytes in it.
+fn length(word: &str) -> usize {
+    word.chars().count()
+}
+
+/// Return whether two letters are the same, ignoring case.
+pub fn same_letter(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Return the lowercase form of a letter, or the letter itself if its lowercase form takes more than one character.
+fn lowercase(letter: char) -> char {
+    let mut lower = letter.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(c), None) => c,
+        _ => letter,
+    }
+}
+
+/// Return the label of a column of the grid, counting from 0: "A" through "Z", then "AA", "AB", and so on, as in a
+/// spreadsheet.
+pub fn column_label(x: usize) -> String {
+    let mut label = vec![];
+    let mut n = x + 1;
+    while n > 0 {
+        n -= 1;
+        label.push((b'A' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    label.into_iter().rev().collect()
+}
+
+/// Return the name of a cell of the grid, counting from 0, like "C7" for the third column of the seventh row.
+pub fn cell_name(x: usize, y: usize) -> String {
+    format!("{}{}", column_label(x), y + 1)
+}
+
+/// Read the name of a cell, like "C7" or "c7", as its column and row counting from 0.
+pub fn parse_cell(name: &str) -> Option<(usize, usize)> {
+    let split = name.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = name.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let x = letters
+        .to_ascii_uppercase()
+        .bytes()
+        .fold(0, |x, b| x * 26 + (b - b'A') as usize + 1);
+    let y: usize = digits.parse().ok()?;
+    Some((x - 1, y.checked_sub(1)?))
 }
 
 impl Grid {
     pub fn new(wordlist: Vec<String>, width: Option<usize>, height: Option<usize>) -> Self {
-        let longest_word = wordlist.iter().map(String::len).max().unwrap();
-        let avg_len =
-            wordlist.iter().map(String::len).sum::<usize>() as f32 / wordlist.len() as f32;
-        let num_letters = avg_len * wordlist.len() as f32;
-        let default_size = f32::sqrt(num_letters * 2.0).ceil() as usize;
+        let longest_word = wordlist.iter().map(|w| length(w)).max().unwrap_or(0);
+        let default_size = Self::default_size(&wordlist, DEFAULT_DENSITY);
         let w = max(longest_word, width.unwrap_or(default_size));
         let h = max(longest_word, height.unwrap_or(default_size));
 
@@ -29,119 +415,976 @@ impl Grid {
             width: w,
             height: h,
             grid: vec![vec![None; w]; h],
+            placements: vec![],
+            attempts: vec![],
+            directions: Direction::ALL.to_vec(),
+            order: Order::default(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            budget: Rc::new(Cell::new(DEFAULT_ATTEMPT_BUDGET)),
+            deadline: None,
+            candidates: 1,
+            weights: QualityWeights::default(),
+            cancel: None,
+            allow_drop: false,
+            optional: HashMap::new(),
+            overlap: true,
+            min_intersections: 0,
+            margin: 0,
+            fill: true,
+            distractors: false,
+            exact: false,
+            all_directions: false,
+            max_filler_share: None,
+            criss_cross: false,
+            mixed_case: false,
+            deferred: 0,
+            constrained: false,
+            dropped: vec![],
+            failures: vec![],
+            filler: Rc::new(filler::Random::default()),
+            base: None,
+        }
+    }
+
+    /// Only place words in these directions.
+    pub fn with_directions(self, directions: &[Direction]) -> Self {
+        Self {
+            directions: directions.to_vec(),
+            ..self
+        }
+    }
+
+    /// Place the words in this order.
+    pub fn with_order(self, order: Order) -> Self {
+        Self { order, ..self }
+    }
+
+    /// Give up after trying `per_word` places for any one word, or `total` places for all the words together.
+    pub fn with_max_attempts(self, per_word: usize, total: usize) -> Self {
+        Self {
+            max_attempts: per_word,
+            budget: Rc::new(Cell::new(total)),
+            ..self
+        }
+    }
+
+    /// Give up if the puzzle isn't finished by this time.
+    pub fn with_deadline(self, deadline: Option<Instant>) -> Self {
+        Self { deadline, ..self }
+    }
+
+    /// Make this many layouts, and keep the one with the best `Puzzle::quality`, as weighted by `weights`.
+    pub fn with_candidates(self, candidates: usize, weights: QualityWeights) -> Self {
+        Self {
+            candidates,
+            weights,
+            ..self
+        }
+    }
+
+    /// Give up as soon as this flag is set, from another thread or a signal handler.
+    pub fn with_cancel(self, cancel: Arc<AtomicBool>) -> Self {
+        Self {
+            cancel: Some(cancel),
+            ..self
         }
     }
 
-    pub fn generate(self) -> Result<Vec<Vec<char>>, Error> {
-        let mut rng = rand::thread_rng();
+    /// Leave out words that can't be placed, instead of failing.
+    pub fn with_allow_drop(self, allow_drop: bool) -> Self {
+        Self { allow_drop, ..self }
+    }
+
+    /// Let these words be left out if they don't all fit. They're placed after the other words, in a random order
+    /// that favors the ones with the most weight.
+    pub fn with_optional(self, optional: HashMap<String, f32>) -> Self {
+        Self { optional, ..self }
+    }
+
+    /// Let words share letters where they cross, or keep every word in cells of its own.
+    pub fn with_overlap(self, overlap: bool) -> Self {
+        Self { overlap, ..self }
+    }
+
+    /// Require at least this many cells shared by more than one word. Words are placed where they cross the most
+    /// letters, and the whole layout is tried again until there are enough.
+    pub fn with_min_intersections(self, min_intersections: usize) -> Self {
+        Self {
+            min_intersections,
+            ..self
+        }
+    }
+
+    /// Keep the words at least this many cells away from the edges of the grid, where they're easy to spot. The
+    /// margin still gets filler.
+    pub fn with_inner_margin(self, margin: usize) -> Self {
+        Self { margin, ..self }
+    }
+
+    /// Leave the cells that aren't part of any word empty, instead of filling them in.
+    pub fn with_fill(self, fill: bool) -> Self {
+        Self { fill, ..self }
+    }
+
+    /// Before filling in the grid, hide the first letters of some of the words in it, to give the solver false starts.
+    /// None of them spell out a whole word.
+    pub fn with_distractors(self, distractors: bool) -> Self {
+        Self {
+            distractors,
+            ..self
+        }
+    }
+
+    /// Make sure every direction gets used: place words in the directions that haven't been yet first, and try other
+    /// layouts if some still aren't.
+    pub fn with_all_directions(self, all_directions: bool) -> Self {
+        Self {
+            all_directions,
+            ..self
+        }
+    }
+
+    /// Try other layouts until each word can be found only where it was hidden, so the filler never spells out an
+    /// extra copy of one.
+    pub fn with_exact(self, exact: bool) -> Self {
+        Self { exact, ..self }
+    }
+
+    /// Don't let any one letter fill more than this share of the cells the filler fills in. After the grid is filled,
+    /// letters used more than that are chosen again.
+    pub fn with_max_filler_share(self, max_filler_share: Option<f32>) -> Self {
+        Self {
+            max_filler_share,
+            ..self
+        }
+    }
+
+    /// Make a criss-cross puzzle instead, where the solver writes the words into an empty grid. The words go across and
+    /// down, each one crossing a word already placed, and the rest of the grid is left empty.
+    pub fn with_criss_cross(self, criss_cross: bool) -> Self {
+        Self {
+            criss_cross,
+            ..self
+        }
+    }
+
+    /// Write each letter of the finished grid in uppercase or lowercase at random, which makes the words harder to
+    /// spot. The placements keep the words as they were given, and they're still found whatever case their letters
+    /// are in.
+    pub fn with_mixed_case(self, mixed_case: bool) -> Self {
+        Self { mixed_case, ..self }
+    }
+
+    /// Build on an earlier puzzle: keep its size, and the places of the words that are still in the list, and only
+    /// place the new words. The filler is kept too, wherever a new word doesn't cover it.
+    pub fn with_base(self, base: &Puzzle) -> Self {
+        let (width, height) = (base.grid[0].len(), base.grid.len());
+        let placements: Vec<Placement> = base
+            .placements
+            .iter()
+            .filter(|p| self.wordlist.contains(&p.word))
+            .cloned()
+            .collect();
+        let mut grid = vec![vec![None; width]; height];
+        for p in &placements {
+            for (letter, (x, y)) in p.word.chars().zip(p.cells()) {
+                grid[y][x] = Some(letter);
+            }
+        }
+        Self {
+            width,
+            height,
+            grid,
+            attempts: vec![0; placements.len()],
+            placements,
+            base: Some(base.grid.clone()),
+            ..self
+        }
+    }
+
+    /// Fill the blank cells with letters chosen by this filler.
+    pub fn with_filler(self, filler: Rc<dyn Filler>) -> Self {
+        Self { filler, ..self }
+    }
+
+    /// Find the smallest grid, with about the given width:height aspect ratio, that all the words fit into. Try
+    /// `attempts` random layouts at each size before moving on to the next size up.
+    pub fn smallest<R: Rng>(
+        self,
+        aspect: f32,
+        attempts: usize,
+        rng: &mut R,
+    ) -> Result<Puzzle, WordSearchError> {
+        let Some(longest_word) = self.wordlist.iter().map(|w| length(w)).max() else {
+            return Err(WordSearchError::EmptyWordList);
+        };
+        self.check_words()?;
+        let limit = 2 * max(
+            longest_word,
+            Self::default_size(&self.wordlist, DEFAULT_DENSITY),
+        );
+        for height in 1..=limit {
+            let width = (height as f32 * aspect).round() as usize;
+            if width < longest_word || height < longest_word {
+                continue;
+            }
+            for _ in 0..attempts {
+                if self.timed_out() {
+                    return Err(WordSearchError::SmallestTimedOut { width, height });
+                }
+                let grid = Self {
+                    width,
+                    height,
+                    grid: vec![vec![None; width]; height],
+                    // Every size would fit if we could leave words out.
+                    allow_drop: false,
+                    // Each layout gets the whole budget, since there can be many sizes to try.
+                    budget: Rc::new(Cell::new(self.budget.get())),
+                    ..self.clone()
+                };
+                match grid.generate(rng) {
+                    Ok(puzzle) => return Ok(puzzle),
+                    Err(e @ WordSearchError::Cancelled { .. }) => return Err(e),
+                    Err(_) => (),
+                }
+            }
+        }
+        Err(WordSearchError::NoGridSize)
+    }
+
+    /// Make sure each word can be hidden: it has letters, and each of them fits in a cell.
+    fn check_words(&self) -> Result<(), WordSearchError> {
+        if self.wordlist.iter().any(String::is_empty) {
+            return Err(WordSearchError::EmptyWord);
+        }
+        if let Some(word) = self
+            .wordlist
+            .iter()
+            .find(|w| w.chars().any(script::is_emoji_part))
+        {
+            return Err(WordSearchError::EmojiSequence { word: word.clone() });
+        }
+        Ok(())
+    }
+
+    /// Return the width and height of a square grid where the words would fill about `density` of the cells.
+    pub fn default_size(wordlist: &[String], density: f32) -> usize {
+        let num_letters = wordlist.iter().map(|w| length(w)).sum::<usize>() as f32;
+        f32::sqrt(num_letters / density).ceil() as usize
+    }
+
+    /// Return the width and height of a grid about `aspect` times as wide as it's tall, where the words would fill
+    /// about `density` of the cells.
+    pub fn default_shape(wordlist: &[String], density: f32, aspect: f32) -> (usize, usize) {
+        let cells = wordlist.iter().map(|w| length(w)).sum::<usize>() as f32 / density;
+        let side = |ratio: f32| max(1, f32::sqrt(cells * ratio).ceil() as usize);
+        (side(aspect), side(1.0 / aspect))
+    }
+
+    pub fn generate<R: Rng>(self, rng: &mut R) -> Result<Puzzle, WordSearchError> {
+        if self.wordlist.is_empty() {
+            return Err(WordSearchError::EmptyWordList);
+        }
+        self.check_words()?;
+        if self.candidates <= 1 {
+            return self.generate_layout(rng);
+        }
+        let (mut best, mut error): (Option<Puzzle>, _) = (None, None);
+        for _ in 0..self.candidates {
+            if self.timed_out() && best.is_some() {
+                break;
+            }
+            match self.clone().generate_layout(rng) {
+                Ok(puzzle) => {
+                    let quality = |p: &Puzzle| p.quality(&self.weights);
+                    if best.as_ref().is_none_or(|b| quality(&puzzle) > quality(b)) {
+                        best = Some(puzzle);
+                    }
+                }
+                Err(e @ WordSearchError::Cancelled { .. }) => return Err(e),
+                Err(e) => error = Some(e),
+            }
+        }
+        best.ok_or_else(|| error.unwrap())
+    }
+
+    /// Make one layout, retrying if it needs enough intersections, distractors that don't spell a word twice, no
+    /// extra copies of any word, a word going in every direction, or every word of a criss-cross puzzle connected.
+    fn generate_layout<R: Rng>(self, rng: &mut R) -> Result<Puzzle, WordSearchError> {
+        let retry =
+            self.min_intersections > 0 || self.distractors || self.exact || self.all_directions;
+        if !retry && !self.criss_cross {
+            return self.generate_once(rng);
+        }
+        let directions = self.usable_directions();
+        if self.all_directions && self.wordlist.len() < directions.len() {
+            return Err(WordSearchError::TooFewWordsForDirections {
+                words: self.wordlist.len(),
+                directions: directions.len(),
+            });
+        }
+        let (mut most, mut error) = (0, None);
+        for tries in 0..LAYOUT_RETRIES {
+            if self.timed_out() {
+                return Err(WordSearchError::LayoutTimedOut { tries });
+            }
+            // Crowding the words together can leave one without room, but another layout might work.
+            let puzzle = match self.clone().generate_once(rng) {
+                Ok(puzzle) => puzzle,
+                Err(e @ WordSearchError::Cancelled { .. }) => return Err(e),
+                Err(e) => {
+                    error = Some(e);
+                    continue;
+                }
+            };
+            if (self.distractors || self.exact) && !puzzle.duplicates().is_empty() {
+                continue;
+            }
+            let unused = |dir: &Direction| !puzzle.placements.iter().any(|p| p.direction == *dir);
+            if self.all_directions && directions.iter().any(unused) {
+                error = Some(WordSearchError::DirectionsUnused {
+                    tries: LAYOUT_RETRIES,
+                });
+                continue;
+            }
+            let found = puzzle.intersections();
+            if found >= self.min_intersections {
+                return Ok(puzzle);
+            }
+            most = max(most, found);
+        }
+        if self.min_intersections > 0 {
+            return Err(WordSearchError::TooFewIntersections {
+                wanted: self.min_intersections,
+                tries: LAYOUT_RETRIES,
+                most,
+            });
+        }
+        Err(error.unwrap_or(if self.distractors {
+            WordSearchError::DistractorsFailed {
+                tries: LAYOUT_RETRIES,
+            }
+        } else {
+            WordSearchError::ExtraCopies {
+                tries: LAYOUT_RETRIES,
+            }
+        }))
+    }
+
+    fn generate_once<R: Rng>(self, rng: &mut R) -> Result<Puzzle, WordSearchError> {
+        let original = self.wordlist.clone();
+        let mixed_case = self.mixed_case;
+        // place_word takes the words from the end of the list.
         let mut wordlist = self.wordlist.clone();
-        wordlist.shuffle(&mut rng);
+        wordlist.retain(|w| !self.placements.iter().any(|p| p.word == *w));
+        wordlist.reverse();
+        match self.order {
+            Order::LongestFirst => wordlist.sort_by_key(|w| length(w)),
+            Order::Input => (),
+            Order::Shuffle => wordlist.shuffle(rng),
+        }
+        if !self.optional.is_empty() {
+            // Sample the optional words by weight: each gets a key of u^(1/weight), and the highest keys go first,
+            // after all the words that have to be placed.
+            let (optional, required): (Vec<_>, Vec<_>) = wordlist
+                .into_iter()
+                .partition(|w| self.optional.contains_key(w));
+            let mut keyed: Vec<_> = optional
+                .into_iter()
+                .map(|w| (rng.gen::<f64>().powf(1.0 / self.optional[&w] as f64), w))
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            wordlist = keyed.into_iter().map(|(_, w)| w).chain(required).collect();
+        }
         let shuffled = Self { wordlist, ..self };
-        let grid = shuffled.place_word(&mut rng)?.grid;
-        let result = grid
-            .into_iter()
-            .map(|row| row.into_iter().map(|cell| cell.unwrap()).collect())
-            .collect();
-        Ok(result)
+        let Self {
+            mut grid,
+            placements,
+            attempts,
+            mut dropped,
+            ..
+        } = shuffled.place_word(rng)?;
+        if mixed_case {
+            for letter in grid.iter_mut().flatten().flatten() {
+                if rng.gen_bool(0.5) {
+                    *letter = lowercase(*letter);
+                }
+            }
+        }
+        // Report the placements in the same order as the word list we were given.
+        let mut placed: Vec<_> = placements.into_iter().zip(attempts).collect();
+        placed.sort_by_key(|(p, _)| original.iter().position(|w| *w == p.word));
+        let (placements, attempts) = placed.into_iter().unzip();
+        dropped.sort_by_key(|d| original.iter().position(|w| w == d));
+        Ok(Puzzle {
+            grid,
+            placements,
+            dropped,
+            attempts,
+        })
     }
 
-    /// Recursively place the word at the front of wordlist, or return an error if a placement can't be found after
-    /// retries.
-    fn place_word(self, rng: &mut ThreadRng) -> Result<Self, Error> {
+    /// Recursively place the word at the front of wordlist. If a word can't be placed, carry on with the rest, so
+    /// that the error can list every word that didn't fit and why. If dropping words is allowed, leave out the ones
+    /// that can't be placed instead.
+    fn place_word<R: Rng>(mut self, rng: &mut R) -> Result<Self, WordSearchError> {
+        if self.cancelled() {
+            let placed = self.placements.len();
+            return Err(WordSearchError::Cancelled {
+                placed,
+                total: placed + self.failures.len() + self.wordlist.len(),
+            });
+        }
+        if self.constrained {
+            self.order_by_constraint();
+        }
         let mut wordlist = self.wordlist.clone();
         match wordlist.pop() {
-            None => self.fill(&mut *rng),
-            Some(word) => {
-                let retry_limit = self.empty_count();
-                for _ in 0..retry_limit {
-                    let dir: Direction = rng.gen();
-                    let (xrange, yrange) = dir.ranges(word.len(), self.width, self.height);
-                    let x = rng.gen_range(xrange);
-                    let y = rng.gen_range(yrange);
-                    match self.try_word(&word, dir, x, y) {
-                        Err(_) => (),
-                        Ok(grid) => {
-                            return Self {
-                                grid,
-                                wordlist,
-                                ..self
-                            }
-                            .place_word(rng);
-                        }
+            None if !self.failures.is_empty() && !self.allow_drop => {
+                Err(WordSearchError::WordsNotPlaced {
+                    total: self.placements.len() + self.failures.len(),
+                    failures: self.failures,
+                })
+            }
+            None if self.fill && !self.criss_cross => self.add_distractors(rng).fill(rng),
+            None => Ok(self),
+            Some(word) => match self.find_place_or_any(&word, rng) {
+                Ok((placement, grid, tries)) => {
+                    let mut placements = self.placements.clone();
+                    placements.push(placement);
+                    let mut attempts = self.attempts.clone();
+                    attempts.push(tries);
+                    Self {
+                        grid,
+                        wordlist,
+                        placements,
+                        attempts,
+                        deferred: 0,
+                        ..self
+                    }
+                    .place_word(rng)
+                }
+                // Words placed later might give it something to cross, so try it again after them. Give up once
+                // every word left has been put off without any being placed.
+                Err(WordSearchError::NoCrossing { .. }) if self.deferred < wordlist.len() => {
+                    wordlist.insert(0, word);
+                    Self {
+                        wordlist,
+                        deferred: self.deferred + 1,
+                        ..self
                     }
+                    .place_word(rng)
                 }
-                Err(anyhow!(
-                    "Failed to place {} after {} retries",
-                    word,
-                    retry_limit
-                ))
+                Err(e) => {
+                    let mut failures = self.failures.clone();
+                    if !self.optional.contains_key(&word) {
+                        failures.push(e);
+                    }
+                    let mut dropped = self.dropped.clone();
+                    dropped.push(word);
+                    Self {
+                        wordlist,
+                        dropped,
+                        failures,
+                        ..self
+                    }
+                    .place_word(rng)
+                }
+            },
+        }
+    }
+
+    /// Find a place for the word. If none of the random places tried fit, it might still fit somewhere, and the words
+    /// placed after it might need to go where there's the least room. So look at every place it fits, and from then
+    /// on place the words with the fewest places first.
+    fn find_place_or_any<R: Rng>(
+        &mut self,
+        word: &str,
+        rng: &mut R,
+    ) -> Result<(Placement, Cells, usize), WordSearchError> {
+        match self.find_place(word, rng) {
+            Err(e @ WordSearchError::PlacementFailed { .. })
+                if !self.constrained && !self.criss_cross =>
+            {
+                self.constrained = true;
+                self.find_place(word, rng).map_err(|_| e)
             }
+            found => found,
         }
     }
 
-    /// Try to place the word into the grid. Return the new grid.
-    fn try_word(
-        &self,
+    /// Try random places for the word until one fits, and return it along with the new grid and how many places were
+    /// tried. If intersections are wanted, compare several places that fit and use the one that crosses the most
+    /// letters.
+    fn find_place<R: Rng>(
+        &mut self,
+        word: &str,
+        rng: &mut R,
+    ) -> Result<(Placement, Cells, usize), WordSearchError> {
+        let word_string = || word.to_string();
+        if self.timed_out() {
+            return Err(WordSearchError::TimedOut {
+                word: word_string(),
+            });
+        }
+        let directions: Vec<Direction> = self
+            .directions
+            .iter()
+            .filter(|dir| !self.criss_cross || matches!(dir, Direction::East | Direction::South))
+            .filter(|dir| dir.fits(length(word), self.width, self.height, self.margin))
+            .copied()
+            .collect();
+        if directions.is_empty() {
+            return Err(WordSearchError::WordTooLong {
+                word: word_string(),
+                width: self.width,
+                height: self.height,
+                margin: self.margin,
+            });
+        }
+        if self.criss_cross && !self.placements.is_empty() {
+            return self.find_crossing(word, &directions, rng);
+        }
+        // Try the directions no word goes in yet first, so they all get used.
+        if self.all_directions {
+            let unused: Vec<Direction> = directions
+                .iter()
+                .filter(|&&dir| !self.placements.iter().any(|p| p.direction == dir))
+                .copied()
+                .collect();
+            if !unused.is_empty() && unused.len() < directions.len() {
+                if let Ok(found) = self.find_among(word, &unused, rng) {
+                    return Ok(found);
+                }
+            }
+        }
+        self.find_among(word, &directions, rng)
+    }
+
+    /// Find a place for the word going in one of the directions: at random, or out of every place it fits once that
+    /// has failed.
+    fn find_among<R: Rng>(
+        &mut self,
+        word: &str,
+        directions: &[Direction],
+        rng: &mut R,
+    ) -> Result<(Placement, Cells, usize), WordSearchError> {
+        let word_string = || word.to_string();
+        if self.constrained {
+            return self.find_any_place(word, directions, rng);
+        }
+        let mut best: Option<(Placement, Cells, usize)> = None;
+        let (mut most_shared, mut candidates) = (0, 0);
+        for attempt in 1..=self.max_attempts {
+            if self.budget.get() == 0 {
+                if best.is_some() {
+                    break;
+                }
+                return Err(WordSearchError::BudgetExhausted {
+                    word: word_string(),
+                });
+            }
+            self.budget.set(self.budget.get() - 1);
+            let dir = *directions.choose(rng).unwrap();
+            let (xrange, yrange) = dir.ranges(length(word), self.width, self.height, self.margin);
+            let x = rng.gen_range(xrange);
+            let y = rng.gen_range(yrange);
+            if let Some(grid) = self.try_word(word, dir, x, y) {
+                let placement = Placement {
+                    word: word.to_string(),
+                    x,
+                    y,
+                    direction: dir,
+                };
+                if self.min_intersections == 0 {
+                    return Ok((placement, grid, attempt));
+                }
+                let shared = placement
+                    .cells()
+                    .filter(|&(x, y)| self.grid[y][x].is_some())
+                    .count();
+                if best.is_none() || shared > most_shared {
+                    most_shared = shared;
+                    best = Some((placement, grid, attempt));
+                }
+                candidates += 1;
+                if candidates == INTERSECTION_CANDIDATES {
+                    break;
+                }
+            }
+        }
+        if let Some(best) = best {
+            return Ok(best);
+        }
+        Err(WordSearchError::PlacementFailed {
+            word: word_string(),
+            attempts: self.max_attempts,
+        })
+    }
+
+    /// Find every place the word fits, and return a random one along with the new grid and how many places were
+    /// checked. If intersections are wanted, use one that crosses the most letters.
+    fn find_any_place<R: Rng>(
+        &mut self,
+        word: &str,
+        directions: &[Direction],
+        rng: &mut R,
+    ) -> Result<(Placement, Cells, usize), WordSearchError> {
+        if self.budget.get() == 0 {
+            return Err(WordSearchError::BudgetExhausted {
+                word: word.to_string(),
+            });
+        }
+        let places = self.places(word, directions);
+        let checked = self.checked_places(word, directions);
+        self.budget.set(self.budget.get().saturating_sub(checked));
+        let shared = |&(direction, x, y): &(Direction, usize, usize)| {
+            let placement = Placement {
+                word: word.to_string(),
+                x,
+                y,
+                direction,
+            };
+            placement
+                .cells()
+                .filter(|&(x, y)| self.grid[y][x].is_some())
+                .count()
+        };
+        let most = match self.min_intersections {
+            0 => 0,
+            _ => places.iter().map(shared).max().unwrap_or(0),
+        };
+        let best: Vec<_> = places.iter().filter(|p| shared(p) >= most).collect();
+        let Some(&&(direction, x, y)) = best.choose(rng) else {
+            return Err(WordSearchError::PlacementFailed {
+                word: word.to_string(),
+                attempts: checked,
+            });
+        };
+        let grid = self.try_word(word, direction, x, y).unwrap();
+        let placement = Placement {
+            word: word.to_string(),
+            x,
+            y,
+            direction,
+        };
+        Ok((placement, grid, checked))
+    }
+
+    /// Return every place the word fits in the grid as it is, going in one of the directions.
+    fn places(&self, word: &str, directions: &[Direction]) -> Vec<(Direction, usize, usize)> {
+        let mut places = vec![];
+        for &direction in directions {
+            let (xrange, yrange) =
+                direction.ranges(length(word), self.width, self.height, self.margin);
+            for y in yrange {
+                for x in xrange.clone() {
+                    if self.fits(word, direction, x, y) {
+                        places.push((direction, x, y));
+                    }
+                }
+            }
+        }
+        places
+    }
+
+    /// Return how many places there are to check for the word, going in one of the directions.
+    fn checked_places(&self, word: &str, directions: &[Direction]) -> usize {
+        directions
+            .iter()
+            .map(|dir| {
+                let (xrange, yrange) =
+                    dir.ranges(length(word), self.width, self.height, self.margin);
+                xrange.count() * yrange.count()
+            })
+            .sum()
+    }
+
+    /// Return the directions the shortest word fits in, in a grid this size.
+    fn usable_directions(&self) -> Vec<Direction> {
+        let shortest = self.wordlist.iter().map(|w| length(w)).min().unwrap_or(1);
+        self.directions
+            .iter()
+            .filter(|dir| dir.fits(shortest, self.width, self.height, self.margin))
+            .copied()
+            .collect()
+    }
+
+    /// Put the word with the fewest places it fits next in line, and the one with the most last, so the words that
+    /// are hardest to fit get the room they need. The words that can be left out still come after all the others.
+    fn order_by_constraint(&mut self) {
+        let directions: Vec<Direction> = self
+            .directions
+            .iter()
+            .filter(|dir| dir.fits(1, self.width, self.height, self.margin))
+            .copied()
+            .collect();
+        let mut wordlist = std::mem::take(&mut self.wordlist);
+        // The word placed next is the one at the end of the list.
+        wordlist.sort_by_cached_key(|word| match self.optional.contains_key(word) {
+            true => (false, Reverse(0)),
+            false => {
+                let directions: Vec<Direction> = directions
+                    .iter()
+                    .filter(|dir| dir.fits(length(word), self.width, self.height, self.margin))
+                    .copied()
+                    .collect();
+                (true, Reverse(self.places(word, &directions).len()))
+            }
+        });
+        self.wordlist = wordlist;
+    }
+
+    /// Find a place for the word in a criss-cross puzzle, where it crosses one of the words already in the grid. Try
+    /// every such place, in random order, and return the first that fits along with the new grid and how many places
+    /// were tried.
+    fn find_crossing<R: Rng>(
+        &mut self,
         word: &str,
-        dir: Direction,
-        x0: usize,
-        y0: usize,
-    ) -> Result<Vec<Vec<Option<char>>>, Error> {
+        directions: &[Direction],
+        rng: &mut R,
+    ) -> Result<(Placement, Cells, usize), WordSearchError> {
+        let letters: Vec<char> = word.chars().collect();
+        let mut starts = vec![];
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                for i in (0..letters.len()).filter(|&i| cell == Some(letters[i])) {
+                    for &dir in directions {
+                        let (dx, dy) = dir.next();
+                        let (xrange, yrange) =
+                            dir.ranges(letters.len(), self.width, self.height, self.margin);
+                        let x0 = x as isize - i as isize * dx;
+                        let y0 = y as isize - i as isize * dy;
+                        if xrange.contains(&(x0 as usize)) && yrange.contains(&(y0 as usize)) {
+                            starts.push((dir, x0 as usize, y0 as usize));
+                        }
+                    }
+                }
+            }
+        }
+        starts.shuffle(rng);
+        for (attempt, (direction, x, y)) in starts.into_iter().enumerate() {
+            if self.budget.get() == 0 {
+                return Err(WordSearchError::BudgetExhausted {
+                    word: word.to_string(),
+                });
+            }
+            self.budget.set(self.budget.get() - 1);
+            if let Some(grid) = self.try_word(word, direction, x, y) {
+                let placement = Placement {
+                    word: word.to_string(),
+                    x,
+                    y,
+                    direction,
+                };
+                return Ok((placement, grid, attempt + 1));
+            }
+        }
+        Err(WordSearchError::NoCrossing {
+            word: word.to_string(),
+        })
+    }
+
+    /// Try to place the word into the grid. Return the new grid, if it fits.
+    fn try_word(&self, word: &str, dir: Direction, x0: usize, y0: usize) -> Option<Cells> {
         // First check if we can insert it, to save copying the whole grid.
+        if !self.fits(word, dir, x0, y0) {
+            return None;
+        }
+
+        // It fits, so now actually place it.
+        let mut grid = self.grid.clone();
         let (mut x, mut y) = (x0, y0);
         for letter in word.chars() {
-            match self.grid[y][x] {
-                None => (),
-                Some(x) if x == letter => (),
-                _ => return Err(anyhow!("Doesn't fit.")),
-            }
+            grid[y][x] = Some(letter);
             let (dx, dy) = dir.next();
             x = (x as isize + dx) as usize;
             y = (y as isize + dy) as usize;
         }
 
-        // It fits, so now actually place it.
-        let mut grid = self.grid.clone();
+        Some(grid)
+    }
+
+    /// Return whether the word fits into the grid at this place, without running into other letters.
+    fn fits(&self, word: &str, dir: Direction, x0: usize, y0: usize) -> bool {
+        if self.criss_cross && !self.fits_crossword(length(word), dir, x0, y0) {
+            return false;
+        }
         let (mut x, mut y) = (x0, y0);
         for letter in word.chars() {
-            grid[y][x] = Some(letter);
+            match self.grid[y][x] {
+                None => (),
+                Some(x) if x == letter && self.overlap => (),
+                _ => return false,
+            }
             let (dx, dy) = dir.next();
             x = (x as isize + dx) as usize;
             y = (y as isize + dy) as usize;
         }
+        true
+    }
+
+    /// Return whether a word of length `len` follows the rules of a crossword at this place: it doesn't run into a
+    /// letter before or after it, and it has no letters beside it except where it crosses another word.
+    fn fits_crossword(&self, len: usize, dir: Direction, x0: usize, y0: usize) -> bool {
+        let cell = |x: isize, y: isize| {
+            let row = self.grid.get(usize::try_from(y).ok()?)?;
+            *row.get(usize::try_from(x).ok()?)?
+        };
+        let (dx, dy) = dir.next();
+        let (x0, y0, len) = (x0 as isize, y0 as isize, len as isize);
+        if cell(x0 - dx, y0 - dy).is_some() || cell(x0 + len * dx, y0 + len * dy).is_some() {
+            return false;
+        }
+        (0..len).all(|i| {
+            let (x, y) = (x0 + i * dx, y0 + i * dy);
+            cell(x, y).is_some()
+                || (cell(x + dy, y + dx).is_none() && cell(x - dy, y - dx).is_none())
+        })
+    }
 
-        Ok(grid)
+    /// Return whether the cancel flag has been set.
+    fn cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
     }
 
-    /// Finish the grid by filling in random letters in all the blank spaces.
-    fn fill(self, rng: &mut ThreadRng) -> Result<Self, Error> {
+    /// Return whether the deadline has passed.
+    fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// If distractors are wanted, hide all but the last two letters of each word of four or more letters in blank
+    /// cells. The cell after each one gets a letter that doesn't continue the word.
+    fn add_distractors<R: Rng>(mut self, rng: &mut R) -> Self {
+        if !self.distractors {
+            return self;
+        }
+        let (width, height) = (self.width as isize, self.height as isize);
+        for placement in self.placements.clone() {
+            let letters: Vec<char> = placement.word.chars().collect();
+            if letters.len() < 4 {
+                continue;
+            }
+            let (prefix, next) = (&letters[..letters.len() - 2], letters[letters.len() - 2]);
+            for _ in 0..DISTRACTOR_ATTEMPTS {
+                let dir = *self.directions.choose(rng).unwrap();
+                if !dir.fits(prefix.len(), self.width, self.height, 0) {
+                    continue;
+                }
+                let (xrange, yrange) = dir.ranges(prefix.len(), self.width, self.height, 0);
+                let (x, y) = (rng.gen_range(xrange), rng.gen_range(yrange));
+                let (dx, dy) = dir.next();
+                let cells: Vec<(isize, isize)> = (0..=prefix.len() as isize)
+                    .map(|i| (x as isize + dx * i, y as isize + dy * i))
+                    .collect();
+                if cells[..prefix.len()]
+                    .iter()
+                    .any(|&(cx, cy)| self.grid[cy as usize][cx as usize].is_some())
+                {
+                    continue;
+                }
+                let (bx, by) = cells[prefix.len()];
+                let blocker = if (0..width).contains(&bx) && (0..height).contains(&by) {
+                    match self.grid[by as usize][bx as usize] {
+                        Some(c) if c == next => continue,
+                        Some(_) => None,
+                        // Choose the filler's letter now, as long as it can come up with one that doesn't continue
+                        // the word.
+                        None => match (0..10)
+                            .map(|_| self.filler.letter(0, None, rng))
+                            .find(|&c| c != next)
+                        {
+                            Some(c) => Some(c),
+                            None => continue,
+                        },
+                    }
+                } else {
+                    None
+                };
+                for (&(cx, cy), &letter) in cells.iter().zip(prefix) {
+                    self.grid[cy as usize][cx as usize] = Some(letter);
+                }
+                if let Some(c) = blocker {
+                    self.grid[by as usize][bx as usize] = Some(c);
+                }
+                break;
+            }
+        }
+        self
+    }
+
+    /// Finish the grid by filling in all the blank spaces with letters from the filler.
+    fn fill<R: Rng>(self, rng: &mut R) -> Result<Self, WordSearchError> {
         let mut grid = self.grid.clone();
-        for row in grid.iter_mut() {
-            for cell in row.iter_mut() {
-                if cell.is_none() {
-                    let letter = rng.gen_range('A'..='Z');
-                    *cell = Some(letter);
+        let mut filled = vec![];
+        let mut index = 0;
+        for (y, row) in grid.iter_mut().enumerate() {
+            for x in 0..row.len() {
+                if row[x].is_none() {
+                    let previous = x.checked_sub(1).and_then(|x| row[x]);
+                    let kept = self.base.as_ref().and_then(|base| base[y][x]);
+                    if kept.is_none() {
+                        filled.push((x, y, index));
+                    }
+                    row[x] = kept.or_else(|| Some(self.filler.letter(index, previous, rng)));
+                    index += 1;
                 }
             }
         }
+        if let Some(share) = self.max_filler_share {
+            self.balance(&mut grid, &filled, share, rng);
+        }
         Ok(Self { grid, ..self })
     }
 
-    /// Return the approximate number of empty cells remaining.
-    fn empty_count(&self) -> usize {
-        self.grid
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .map(|cell| cell.map_or_else(|| 1, |_| 0))
-                    .sum::<usize>()
-            })
-            .sum()
+    /// Choose the letters of the filled-in cells again, in a random order, wherever their letter fills more than
+    /// `share` of them. The new letter comes from the filler if it gives one that isn't used that much already in a
+    /// few tries, or else from the other letters it filled in. Each cell is given as its position and its index for
+    /// the filler.
+    fn balance<R: Rng>(
+        &self,
+        grid: &mut Cells,
+        filled: &[(usize, usize, usize)],
+        share: f32,
+        rng: &mut R,
+    ) {
+        let most = ((share * filled.len() as f32).ceil() as usize).max(1);
+        let mut counts: BTreeMap<char, usize> = BTreeMap::new();
+        for &(x, y, _) in filled {
+            if let Some(letter) = grid[y][x] {
+                *counts.entry(letter).or_default() += 1;
+            }
+        }
+        let mut cells = filled.to_vec();
+        cells.shuffle(rng);
+        for (x, y, index) in cells {
+            let Some(letter) = grid[y][x] else { continue };
+            if counts[&letter] <= most {
+                continue;
+            }
+            let previous = x.checked_sub(1).and_then(|x| grid[y][x]);
+            let under = |c: &char| counts.get(c).is_none_or(|&n| n < most);
+            let redrawn = (0..BALANCE_TRIES)
+                .map(|_| self.filler.letter(index, previous, rng))
+                .find(under)
+                .or_else(|| {
+                    let letters: Vec<char> = counts.keys().copied().filter(under).collect();
+                    letters.choose(rng).copied()
+                });
+            if let Some(redrawn) = redrawn {
+                *counts.entry(letter).or_default() -= 1;
+                *counts.entry(redrawn).or_default() += 1;
+                grid[y][x] = Some(redrawn);
+            }
+        }
     }
 }
 
-#[derive(RandGen)]
-enum Direction {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
     East,
     Southeast,
     South,
@@ -152,9 +1395,36 @@ enum Direction {
     Northeast,
 }
 
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::East => "east",
+            Self::Southeast => "southeast",
+            Self::South => "south",
+            Self::Southwest => "southwest",
+            Self::West => "west",
+            Self::Northwest => "northwest",
+            Self::North => "north",
+            Self::Northeast => "northeast",
+        };
+        f.write_str(name)
+    }
+}
+
 impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Self::East,
+        Self::Southeast,
+        Self::South,
+        Self::Southwest,
+        Self::West,
+        Self::Northwest,
+        Self::North,
+        Self::Northeast,
+    ];
+
     /// Return the next position after the current one, in (dx, dy) form.
-    fn next(&self) -> (isize, isize) {
+    pub fn next(&self) -> (isize, isize) {
         match self {
             Self::East => (1, 0),
             Self::Southeast => (1, 1),
@@ -167,27 +1437,512 @@ impl Direction {
         }
     }
 
-    /// Return the allowable starting positions for a word of length len.
+    /// Return whether a word of length len fits in the grid in this direction, at least `margin` cells from the
+    /// edges.
+    fn fits(&self, len: usize, width: usize, height: usize, margin: usize) -> bool {
+        let (dx, dy) = self.next();
+        let fits = |d: isize, size: usize| {
+            let inner = size.saturating_sub(2 * margin);
+            inner >= if d == 0 { 1 } else { len }
+        };
+        fits(dx, width) && fits(dy, height)
+    }
+
+    /// Return the allowable starting positions for a word of length len, at least `margin` cells from the edges.
     fn ranges(
         &self,
         len: usize,
         width: usize,
         height: usize,
+        margin: usize,
     ) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
         let (dx, dy) = self.next();
-        let (xmin, xmax) = if dx < 0 {
-            (len - 1, width - 1)
-        } else {
-            (0, width - len)
-        };
-        let (ymin, ymax) = if dy < 0 {
-            (len - 1, height - 1)
-        } else {
-            (0, height - len)
+        let span = |d: isize, size: usize| {
+            let last = size - 1 - margin;
+            match d {
+                -1 => (margin + len - 1, last),
+                1 => (margin, last + 1 - len),
+                _ => (margin, last),
+            }
         };
+        let (xmin, xmax) = span(dx, width);
+        let (ymin, ymax) = span(dy, height);
         (
             RangeInclusive::new(xmin, xmax),
             RangeInclusive::new(ymin, ymax),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::error::WordSearchError;
+    use crate::filler::Frequency;
+
+    use super::{
+        cell_name, column_label, parse_cell, Direction, Grid, Mirror, Placement, Puzzle,
+        QualityWeights,
+    };
+
+    #[test]
+    fn test_find() {
+        let grid = ["CAT", "XOX", "XXG"]
+            .iter()
+            .map(|row| row.chars().map(Some).collect())
+            .collect();
+        let puzzle = Puzzle {
+            grid,
+            placements: vec![],
+            dropped: vec![],
+            attempts: vec![],
+        };
+
+        let cat = puzzle.find("CAT").unwrap();
+        assert_eq!((0, 0, Direction::East), (cat.x, cat.y, cat.direction));
+        let god = puzzle.find("GOC").unwrap();
+        assert_eq!((2, 2, Direction::Northwest), (god.x, god.y, god.direction));
+        assert_eq!((0, 0), god.end());
+        assert!(puzzle.find("DOG").is_none());
+    }
+
+    #[test]
+    fn test_crossings() {
+        let grid = ["CAT", "XOX", "XXG"]
+            .iter()
+            .map(|row| row.chars().map(Some).collect())
+            .collect();
+        let mut puzzle = Puzzle {
+            grid,
+            placements: vec![],
+            dropped: vec![],
+            attempts: vec![],
+        };
+        puzzle.placements = ["CAT", "GOC", "TOX"]
+            .iter()
+            .map(|word| puzzle.find(word).unwrap())
+            .collect();
+        let words =
+            |p| -> Vec<String> { puzzle.crossings(p).iter().map(|p| p.word.clone()).collect() };
+        assert_eq!(["GOC", "TOX"].to_vec(), words(&puzzle.placements[0]));
+        assert_eq!(["CAT", "TOX"].to_vec(), words(&puzzle.placements[1]));
+        assert_eq!(3, puzzle.intersections());
+    }
+
+    #[test]
+    fn test_rotate_and_mirror() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let words = ["CAT", "DOG", "BIRD", "FISH"].map(String::from).to_vec();
+        let puzzle = Grid::new(words, Some(6), Some(4))
+            .generate(&mut rng)
+            .unwrap();
+        let in_place = |puzzle: &Puzzle| {
+            puzzle.placements.iter().all(|p| {
+                p.word
+                    .chars()
+                    .zip(p.cells())
+                    .all(|(letter, (x, y))| puzzle.grid[y][x] == Some(letter))
+            })
+        };
+        for turns in 1..4 {
+            let turned = puzzle.rotate(turns);
+            assert!(in_place(&turned));
+            assert_eq!(puzzle.grid, turned.rotate(4 - turns).grid);
+        }
+        assert_eq!(
+            (4, 6),
+            (puzzle.rotate(1).grid[0].len(), puzzle.rotate(1).grid.len())
+        );
+        for mirror in [Mirror::Horizontal, Mirror::Vertical] {
+            let flipped = puzzle.mirror(mirror);
+            assert!(in_place(&flipped));
+            assert_eq!(puzzle.grid, flipped.mirror(mirror).grid);
+        }
+    }
+
+    #[test]
+    fn test_parse_cell() {
+        for (x, y) in [(0, 0), (2, 6), (25, 9), (26, 0), (701, 99), (702, 3)] {
+            assert_eq!(Some((x, y)), parse_cell(&cell_name(x, y)));
+        }
+        assert_eq!(Some((2, 6)), parse_cell("c7"));
+        assert_eq!(None, parse_cell("C0"));
+        assert_eq!(None, parse_cell("7"));
+        assert_eq!(None, parse_cell("C7X"));
+    }
+
+    #[test]
+    fn test_cell_name() {
+        assert_eq!("A", column_label(0));
+        assert_eq!("Z", column_label(25));
+        assert_eq!("AA", column_label(26));
+        assert_eq!("BA", column_label(52));
+        assert_eq!("C7", cell_name(2, 6));
+    }
+
+    #[test]
+    fn test_smallest() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = vec!["CAT".to_string(), "DOG".to_string()];
+        let puzzle = Grid::new(words, None, None)
+            .smallest(1.0, 100, &mut rng)
+            .unwrap();
+        assert_eq!(3, puzzle.grid.len());
+        assert_eq!(3, puzzle.grid[0].len());
+        assert_eq!(2, puzzle.placements.len());
+    }
+
+    #[test]
+    fn test_default_shape() {
+        let words = ["ELEPHANT", "GIRAFFE", "TIGER"].map(String::from).to_vec();
+        let size = Grid::default_size(&words, 0.5);
+        assert_eq!((size, size), Grid::default_shape(&words, 0.5, 1.0));
+        let (width, height) = Grid::default_shape(&words, 0.5, 2.0);
+        assert!(width > 2 * height - 2 && width * height >= 40);
+    }
+
+    #[test]
+    fn test_optional() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["AAA", "BBB", "CCC", "DDD", "EEE"]
+            .map(String::from)
+            .to_vec();
+        let optional = words[2..].iter().map(|w| (w.clone(), 1.0)).collect();
+        let puzzle = Grid::new(words.clone(), Some(3), Some(3))
+            .with_directions(&[Direction::East])
+            .with_optional(optional)
+            .generate(&mut rng)
+            .unwrap();
+        assert_eq!(3, puzzle.placements.len());
+        assert_eq!(2, puzzle.dropped.len());
+        assert!(["AAA", "BBB"].iter().all(|w| puzzle.find(w).is_some()));
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["AAA", "BBB", "CCC", "DDD", "EEE"]
+            .map(String::from)
+            .to_vec();
+        let error = Grid::new(words, Some(3), Some(3))
+            .with_directions(&[Direction::East])
+            .generate(&mut rng)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("Couldn't place 2 of 5 words:"));
+        let WordSearchError::WordsNotPlaced { failures, .. } = error else {
+            panic!("{:?}", error);
+        };
+        assert!(matches!(
+            failures[0],
+            WordSearchError::PlacementFailed { attempts: 1000, .. }
+        ));
+    }
+
+    #[test]
+    fn test_budget() {
+        // Each of the candidates takes at least one try, and they all take them from the same budget.
+        let mut rng = StdRng::seed_from_u64(0);
+        let grid = Grid::new(vec!["CAT".to_string()], Some(5), Some(5))
+            .with_max_attempts(100, 3)
+            .with_candidates(5, QualityWeights::default());
+        let budget = grid.budget.clone();
+        assert!(grid.generate(&mut rng).is_ok());
+        assert_eq!(0, budget.get());
+    }
+
+    #[test]
+    fn test_constrained() {
+        // One random try each is hardly ever enough, but every word still fits somewhere.
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let puzzle = Grid::new(
+                ["CAT", "DOG", "EMU"].map(String::from).to_vec(),
+                Some(3),
+                Some(3),
+            )
+            .with_directions(&[Direction::East])
+            .with_max_attempts(1, 1000)
+            .with_overlap(false)
+            .generate(&mut rng)
+            .unwrap();
+            assert_eq!(puzzle.placements.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_all_directions() {
+        let words = ["CAT", "DOG", "EMU", "FOX", "GNU", "OWL", "YAK", "ELK"]
+            .map(String::from)
+            .to_vec();
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let puzzle = Grid::new(words.clone(), Some(8), Some(8))
+                .with_all_directions(true)
+                .generate(&mut rng)
+                .unwrap();
+            for direction in Direction::ALL {
+                assert!(puzzle.placements.iter().any(|p| p.direction == direction));
+            }
+        }
+        let mut rng = StdRng::seed_from_u64(0);
+        let error = Grid::new(words[..7].to_vec(), Some(8), Some(8))
+            .with_all_directions(true)
+            .generate(&mut rng)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            WordSearchError::TooFewWordsForDirections {
+                words: 7,
+                directions: 8
+            }
+        ));
+    }
+
+    #[test]
+    fn test_empty_word() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let error = Grid::new(["CAT", ""].map(String::from).to_vec(), None, None)
+            .generate(&mut rng)
+            .err()
+            .unwrap();
+        assert!(matches!(error, WordSearchError::EmptyWord));
+
+        let error = Grid::new(vec!["👩\u{200D}💻".to_string()], None, None)
+            .generate(&mut rng)
+            .err()
+            .unwrap();
+        assert!(matches!(error, WordSearchError::EmojiSequence { .. }));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let cancel = Arc::new(AtomicBool::new(true));
+        let error = Grid::new(["CAT", "DOG"].map(String::from).to_vec(), None, None)
+            .with_cancel(cancel)
+            .generate(&mut rng)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            WordSearchError::Cancelled {
+                placed: 0,
+                total: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_layout_timed_out() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["CAT", "DOG"].map(String::from).to_vec();
+        let grid = Grid::new(words, None, None).with_deadline(Some(Instant::now()));
+        for grid in [
+            grid.clone().with_min_intersections(1),
+            grid.with_distractors(true),
+        ] {
+            let error = grid.generate(&mut rng).err().unwrap();
+            assert!(matches!(
+                error,
+                WordSearchError::LayoutTimedOut { tries: 0 }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_base() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let base = Grid::new(["CAT", "DOG"].map(String::from).to_vec(), Some(8), Some(8))
+            .generate(&mut rng)
+            .unwrap();
+        let puzzle = Grid::new(["CAT", "DOG", "EMU"].map(String::from).to_vec(), None, None)
+            .with_base(&base)
+            .generate(&mut rng)
+            .unwrap();
+        assert_eq!(base.placements[..], puzzle.placements[..2]);
+        assert_eq!("EMU", puzzle.placements[2].word);
+        let emu: Vec<_> = puzzle.placements[2].cells().collect();
+        for (y, row) in base.grid.iter().enumerate() {
+            for (x, letter) in row.iter().enumerate() {
+                if !emu.contains(&(x, y)) {
+                    assert_eq!(*letter, puzzle.grid[y][x]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_inner_margin() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["CAT", "DOG"].map(String::from).to_vec();
+        for _ in 0..20 {
+            let puzzle = Grid::new(words.clone(), Some(6), Some(6))
+                .with_inner_margin(1)
+                .generate(&mut rng)
+                .unwrap();
+            for (x, y) in puzzle.placements.iter().flat_map(|p| p.cells()) {
+                assert!((1..=4).contains(&x) && (1..=4).contains(&y));
+            }
+        }
+        let error = Grid::new(words, Some(4), Some(4))
+            .with_inner_margin(1)
+            .generate(&mut rng)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("inside its 1-cell margin"));
+    }
+
+    #[test]
+    fn test_exact() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = vec![String::from("CAT"); 12];
+        let puzzle = Grid::new(words, Some(8), Some(8))
+            .with_overlap(false)
+            .with_exact(true)
+            .generate(&mut rng)
+            .unwrap();
+        assert_eq!(puzzle.placements.len(), 12);
+        assert_eq!(puzzle.find_all("CAT").len(), 12);
+        assert_eq!(puzzle.intersections(), 0);
+    }
+
+    #[test]
+    fn test_max_filler_share() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let filler = Frequency::new(vec!['X', 'Y', 'Z'], &[98.0, 1.0, 1.0]).unwrap();
+        let puzzle = Grid::new(vec![String::from("CAT")], Some(6), Some(6))
+            .with_filler(Rc::new(filler))
+            .with_max_filler_share(Some(0.4))
+            .generate(&mut rng)
+            .unwrap();
+        let xs = puzzle
+            .grid
+            .iter()
+            .flatten()
+            .filter(|&&c| c == Some('X'))
+            .count();
+        // 33 cells are filled in, and 40% of them is 13.2, which rounds up.
+        assert_eq!(xs, 14);
+    }
+
+    #[test]
+    fn test_distractors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["ELEPHANT", "GIRAFFE"].map(String::from).to_vec();
+        for _ in 0..10 {
+            let puzzle = Grid::new(words.clone(), Some(10), Some(10))
+                .with_distractors(true)
+                .generate(&mut rng)
+                .unwrap();
+            assert!(puzzle.duplicates().is_empty());
+            assert!(puzzle.find_all("ELEPHA").len() > 1);
+        }
+    }
+
+    #[test]
+    fn test_quality() {
+        let placements = vec![
+            Placement {
+                word: "CAT".to_string(),
+                x: 0,
+                y: 0,
+                direction: Direction::East,
+            },
+            Placement {
+                word: "COT".to_string(),
+                x: 0,
+                y: 0,
+                direction: Direction::South,
+            },
+        ];
+        let puzzle = Puzzle {
+            grid: ["CAT", "OXX", "TXX"]
+                .iter()
+                .map(|row| row.chars().map(Some).collect())
+                .collect(),
+            placements,
+            dropped: vec![],
+            attempts: vec![],
+        };
+        // 5 of 9 cells, 1 shared letter for 2 words, and the two words in different directions.
+        let quality = puzzle.quality(&QualityWeights::default());
+        assert!((quality - (5.0 / 9.0 + 0.5 + 1.0)).abs() < 1e-6);
+        let weights = QualityWeights {
+            density: 2.0,
+            intersections: 0.0,
+            direction_balance: 1.0,
+        };
+        assert!((puzzle.quality(&weights) - (10.0 / 9.0 + 1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_criss_cross() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["ELEPHANT", "GIRAFFE", "TIGER", "HIPPO", "ZEBRA"]
+            .map(String::from)
+            .to_vec();
+        let puzzle = Grid::new(words, Some(12), Some(12))
+            .with_criss_cross(true)
+            .generate(&mut rng)
+            .unwrap();
+        let cells: Vec<_> = puzzle.placements.iter().flat_map(|p| p.cells()).collect();
+        for (y, row) in puzzle.grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                assert_eq!(cells.contains(&(x, y)), cell.is_some());
+            }
+        }
+        for placement in &puzzle.placements {
+            assert!([Direction::East, Direction::South].contains(&placement.direction));
+            assert!(!puzzle.crossings(placement).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_no_overlap() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["CAT", "COW", "CUB"].map(String::from).to_vec();
+        for _ in 0..20 {
+            let puzzle = Grid::new(words.clone(), Some(4), Some(4))
+                .with_overlap(false)
+                .generate(&mut rng)
+                .unwrap();
+            for p in &puzzle.placements {
+                assert!(puzzle.crossings(p).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_mixed_case() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let words = ["ELEPHANT", "GIRAFFE", "TIGER"].map(String::from).to_vec();
+        let puzzle = Grid::new(words.clone(), Some(10), Some(10))
+            .with_mixed_case(true)
+            .generate(&mut rng)
+            .unwrap();
+        let letters: Vec<char> = puzzle.grid.iter().flatten().flatten().copied().collect();
+        assert!(letters.iter().any(char::is_ascii_lowercase));
+        assert!(letters.iter().any(char::is_ascii_uppercase));
+        for word in &words {
+            assert_eq!(
+                puzzle.find(word).map(|p| p.cells().collect::<Vec<_>>()),
+                puzzle
+                    .placements
+                    .iter()
+                    .find(|p| p.word == *word)
+                    .map(|p| p.cells().collect())
+            );
+        }
+    }
+}
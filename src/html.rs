@@ -0,0 +1,234 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use anyhow::Error;
+use image::Rgb;
+
+use crate::config::{Args, Length};
+use crate::grid::Puzzle;
+use crate::page::escape;
+use crate::render;
+use crate::wordlist::Entry;
+
+/// The script that makes an --interactive page playable: dragging across the grid selects a line of letters, and if
+/// they spell a word in the key, forwards or backwards, they stay marked and the word is struck off. The timer stops
+/// when the last word is found.
+const SCRIPT: &str = r##"(() => {
+  const table = document.getElementById("grid");
+  const words = [...document.querySelectorAll("#key li[data-word]")];
+  const timer = document.getElementById("timer");
+  const start = Date.now();
+  const tick = setInterval(() => {
+    const s = Math.floor((Date.now() - start) / 1000);
+    timer.textContent = Math.floor(s / 60) + ":" + String(s % 60).padStart(2, "0");
+  }, 250);
+  let anchor = null;
+  let selected = [];
+  const cellAt = (e) => {
+    const el = document.elementFromPoint(e.clientX, e.clientY);
+    return el && el.closest("#grid td");
+  };
+  const select = (td) => {
+    selected.forEach((c) => c.classList.remove("selected"));
+    selected = [];
+    if (!anchor || !td) return;
+    const [x0, y0] = [anchor.cellIndex, anchor.parentElement.rowIndex];
+    const [x1, y1] = [td.cellIndex, td.parentElement.rowIndex];
+    const [dx, dy] = [x1 - x0, y1 - y0];
+    // Words only run across, down, or diagonally.
+    if (dx && dy && Math.abs(dx) !== Math.abs(dy)) return;
+    for (let i = 0; i <= Math.max(Math.abs(dx), Math.abs(dy)); i++) {
+      selected.push(table.rows[y0 + Math.sign(dy) * i].cells[x0 + Math.sign(dx) * i]);
+    }
+    selected.forEach((c) => c.classList.add("selected"));
+  };
+  table.addEventListener("pointerdown", (e) => {
+    anchor = cellAt(e);
+    select(anchor);
+    e.preventDefault();
+  });
+  document.addEventListener("pointermove", (e) => {
+    if (anchor) select(cellAt(e) || selected[selected.length - 1]);
+  });
+  document.addEventListener("pointerup", () => {
+    const letters = selected.map((c) => c.textContent).join("").toUpperCase();
+    const backwards = [...letters].reverse().join("");
+    const word = words.find((w) => !w.classList.contains("found")
+      && (w.dataset.word === letters || w.dataset.word === backwards));
+    if (word) {
+      word.classList.add("found");
+      selected.forEach((c) => c.classList.add("found"));
+      if (words.every((w) => w.classList.contains("found"))) {
+        clearInterval(tick);
+        timer.classList.add("done");
+      }
+    }
+    anchor = null;
+    select(null);
+  });
+})();
+"##;
+
+/// Write the puzzle as a web page, with the grid as a table and the key as a list. With `solution`, the letters of
+/// the answers are highlighted. With --interactive, the page can be played in a browser; it's a single file, with
+/// nothing to load from anywhere else.
+pub fn write_html<W: Write>(
+    mut out: W,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), Error> {
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(
+        html,
+        r#"<meta name="viewport" content="width=device-width, initial-scale=1">"#
+    )?;
+    writeln!(
+        html,
+        "<title>{}</title>",
+        escape(args.title.as_deref().unwrap_or("Word search"))
+    )?;
+    writeln!(html, "<style>")?;
+    writeln!(
+        html,
+        "body {{ font-family: {}; }}",
+        args.typeface().css_family()
+    )?;
+    writeln!(
+        html,
+        "#grid {{ border-collapse: collapse; font-size: 1.5em; user-select: none; touch-action: none; }}"
+    )?;
+    writeln!(
+        html,
+        "#grid td {{ width: 1.5em; height: 1.5em; text-align: center; }}"
+    )?;
+    if args.high_contrast() {
+        writeln!(
+            html,
+            "body {{ color: black; background: white; font-weight: bold; }}"
+        )?;
+    }
+    writeln!(
+        html,
+        "#grid td.box {{ border: {}px solid black; }}",
+        if args.high_contrast() { 2 } else { 1 }
+    )?;
+    writeln!(
+        html,
+        "#grid td.answer, #grid td.found {{ background: {}; }}",
+        rgba(args.mark_color(), 0.3)
+    )?;
+    writeln!(
+        html,
+        "#grid td.selected {{ background: {}; }}",
+        rgba(args.mark_color(), 0.5)
+    )?;
+    writeln!(html, "#key {{ list-style: none; padding: 0; columns: 3; }}")?;
+    if let Some(gap) = args.key_gap {
+        writeln!(html, "#key {{ margin-top: {}; }}", css_length(gap))?;
+    }
+    writeln!(html, "#key li.found {{ text-decoration: line-through; }}")?;
+    writeln!(html, "#timer.done {{ font-weight: bold; }}")?;
+    writeln!(html, "</style>\n</head>\n<body>")?;
+
+    if let Some(title) = &args.title {
+        writeln!(html, "<h1>{}</h1>", escape(title))?;
+    }
+    if args.worksheet_header || student.is_some() {
+        let (name, date) = render::header_text(student);
+        writeln!(
+            html,
+            "<p>{}<span style=\"float: right\">{}</span></p>",
+            escape(&name),
+            escape(date)
+        )?;
+    }
+    if args.interactive {
+        writeln!(html, "<p>Time: <span id=\"timer\">0:00</span></p>")?;
+    }
+
+    let letter_colors = render::letter_colors(&puzzle.grid, args)?;
+    writeln!(html, "<table id=\"grid\">")?;
+    for (y, line) in puzzle.grid.iter().enumerate() {
+        write!(html, "<tr>")?;
+        for (x, letter) in line.iter().enumerate() {
+            let answer = solution
+                && puzzle
+                    .placements
+                    .iter()
+                    .any(|p| p.cells().any(|c| c == (x, y)));
+            let (class, text) = match letter {
+                // A criss-cross puzzle has empty boxes for the solver to write the letters in.
+                Some(_) if args.criss_cross && !solution => ("box", String::new()),
+                Some(letter) if args.criss_cross => ("box", letter.to_string()),
+                Some(letter) if answer => ("answer", letter.to_string()),
+                Some(letter) => ("", letter.to_string()),
+                None => ("", String::new()),
+            };
+            let style = match &letter_colors {
+                Some(colors) if !text.is_empty() => {
+                    format!(" style=\"color: {}\"", rgba(colors[y][x], 1.0))
+                }
+                _ => String::new(),
+            };
+            match class {
+                "" => write!(html, "<td{style}>{}</td>", escape(&text))?,
+                _ => write!(html, "<td class=\"{class}\"{style}>{}</td>", escape(&text))?,
+            }
+        }
+        writeln!(html, "</tr>")?;
+    }
+    writeln!(html, "</table>")?;
+
+    writeln!(html, "<ul id=\"key\">")?;
+    let mut category = None;
+    for entry in wordlist {
+        if entry.category != category {
+            category = entry.category.clone();
+            if let Some(heading) = &category {
+                writeln!(html, "<li><strong>{}</strong></li>", escape(heading))?;
+            }
+        }
+        writeln!(
+            html,
+            "<li data-word=\"{}\">{}</li>",
+            escape(&entry.grid_form()),
+            escape(&entry.word)
+        )?;
+    }
+    writeln!(html, "</ul>")?;
+
+    if args.score_box {
+        writeln!(
+            html,
+            "<p>{}</p>",
+            escape(&render::score_text(wordlist.len()))
+        )?;
+    }
+    if let Some(footer) = args.footer() {
+        writeln!(html, "<p><small>{}</small></p>", escape(&footer))?;
+    }
+    if args.interactive {
+        writeln!(html, "<script>\n{SCRIPT}</script>")?;
+    }
+    writeln!(html, "</body>\n</html>")?;
+    out.write_all(html.as_bytes())?;
+    Ok(())
+}
+
+fn rgba(color: Rgb<u8>, alpha: f32) -> String {
+    let Rgb([r, g, b]) = color;
+    format!("rgba({}, {}, {}, {})", r, g, b, alpha)
+}
+
+fn css_length(length: Length) -> String {
+    match length {
+        Length::Pixels(pixels) => format!("{}px", pixels),
+        Length::Millimeters(mm) => format!("{}mm", mm),
+        Length::Inches(inches) => format!("{}in", inches),
+    }
+}
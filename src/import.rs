@@ -0,0 +1,120 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{anyhow, Error};
+use serde_json::Value;
+
+use crate::grid::{self, Puzzle};
+use crate::wordlist::Entry;
+
+/// Read a wordsearch puzzle in ipuz format. Return the list of words to find, and the puzzle. Words that can't be
+/// found in the grid are listed in the puzzle's `dropped`, in the form they'd take in the grid.
+pub fn read_ipuz(filename: &Path) -> Result<(Vec<String>, Puzzle), Error> {
+    let rdr = BufReader::new(File::open(filename)?);
+    let ipuz: Value = serde_json::from_reader(rdr)?;
+
+    let is_wordsearch = ipuz["kind"].as_array().is_some_and(|kinds| {
+        kinds.iter().any(|k| {
+            k.as_str()
+                .is_some_and(|k| k.starts_with("http://ipuz.org/wordsearch"))
+        })
+    });
+    if !is_wordsearch {
+        return Err(anyhow!("Not an ipuz wordsearch puzzle: {:?}", filename));
+    }
+
+    let grid = ipuz["puzzle"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Missing puzzle grid in {:?}", filename))?
+        .iter()
+        .map(|row| {
+            row.as_array()
+                .ok_or_else(|| anyhow!("Puzzle rows must be arrays"))?
+                .iter()
+                .map(ipuz_cell)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if grid.is_empty() || grid[0].is_empty() || grid.iter().any(|row| row.len() != grid[0].len()) {
+        return Err(anyhow!("Puzzle grid must be a non-empty rectangle"));
+    }
+
+    let words = ipuz["solution"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Missing solution word list in {:?}", filename))?
+        .iter()
+        .map(|w| {
+            w.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Solution words must be strings: {}", w))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if words.is_empty() {
+        return Err(anyhow!("Empty word list: {:?}", filename));
+    }
+
+    let mut puzzle = Puzzle {
+        grid,
+        placements: vec![],
+        dropped: vec![],
+        attempts: vec![],
+    };
+    for word in &words {
+        let word = Entry::new(word.clone()).grid_form();
+        match puzzle.find(&word) {
+            Some(placement) => puzzle.placements.push(placement),
+            None => puzzle.dropped.push(word),
+        }
+    }
+    Ok((words, puzzle))
+}
+
+/// A grid cell is either a bare string, or an object with the letter in its "cell" field. A null cell is empty.
+fn ipuz_cell(cell: &Value) -> Result<Option<char>, Error> {
+    let s = match cell {
+        Value::Null => return Ok(None),
+        Value::Object(o) => o.get("cell").and_then(Value::as_str),
+        _ => cell.as_str(),
+    };
+    let mut letters = s.into_iter().flat_map(str::chars);
+    match (letters.next(), letters.next()) {
+        (Some(letter), None) => Ok(Some(letter)),
+        _ => Err(anyhow!("Grid cells must hold a single letter: {}", cell)),
+    }
+}
+
+/// Read a hand-made grid from a text file, one row per line. Whitespace between letters is ignored, and a dot is an
+/// empty cell. Return the puzzle, with placements for whichever of the words could be found in it.
+pub fn read_text_grid(filename: &Path, words: &[String]) -> Result<Puzzle, Error> {
+    let rdr = BufReader::new(File::open(filename)?);
+    let mut grid = vec![];
+    for line in rdr.lines() {
+        let row: Vec<Option<char>> = line?
+            .to_uppercase()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| (c != grid::EMPTY).then_some(c))
+            .collect();
+        if !row.is_empty() {
+            grid.push(row);
+        }
+    }
+    if grid.is_empty() || grid.iter().any(|row| row.len() != grid[0].len()) {
+        return Err(anyhow!(
+            "Grid must be a non-empty rectangle of letters: {:?}",
+            filename
+        ));
+    }
+
+    let mut puzzle = Puzzle {
+        grid,
+        placements: vec![],
+        dropped: vec![],
+        attempts: vec![],
+    };
+    puzzle.placements = words.iter().filter_map(|w| puzzle.find(w)).collect();
+    Ok(puzzle)
+}
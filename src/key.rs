@@ -0,0 +1,326 @@
+use std::cmp::Reverse;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use icu_collator::{options::CollatorOptions, Collator};
+use icu_locale_core::Locale;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::config::{KeyStyle, SortKey};
+use crate::error::WordSearchError;
+use crate::grid::Puzzle;
+use crate::render;
+use crate::wordlist::Entry;
+
+/// How many times to shuffle a word's letters, looking for an order that doesn't spell the word.
+const SCRAMBLE_TRIES: usize = 10;
+
+/// One line of the key: a word, the heading over a category, or a blank line between categories.
+pub struct Line<'a> {
+    pub text: &'a str,
+    pub heading: bool,
+}
+
+/// Return the words in the order they should be listed in the key. Without a sort key they stay in the order they
+/// were given. Words are alphabetized by the rules of the language, if one is given. Words of the same length keep
+/// their order. Categories stay in the order they were given, with their words sorted within them.
+pub fn sort(
+    words: &[Entry],
+    sort_key: Option<SortKey>,
+    lang: Option<&Locale>,
+) -> Result<Vec<Entry>, WordSearchError> {
+    let mut sorted = words.to_vec();
+    match sort_key {
+        None => (),
+        Some(SortKey::Alphabetical) => {
+            let locale = lang.cloned().unwrap_or(Locale::UNKNOWN);
+            let collator = Collator::try_new((&locale).into(), CollatorOptions::default())
+                .map_err(|e| {
+                    WordSearchError::InvalidInput(format!(
+                        "Can't alphabetize words in {}: {}",
+                        locale, e
+                    ))
+                })?;
+            sorted.sort_by(|a, b| collator.compare(&a.word, &b.word));
+        }
+        Some(SortKey::Length) => {
+            sorted.sort_by_key(|e| Reverse(e.grid_form().chars().count()));
+        }
+    }
+    sorted.sort_by_key(|e| words.iter().position(|w| w.category == e.category));
+    Ok(sorted)
+}
+
+/// Return the words as the key shows them to the solver. Words with a clue show the clue instead, and bilingual keys
+/// show each word in capitals, as it's hidden, with its translation after it. Scrambled words are shuffled the same
+/// way every time the same puzzle is drawn, so every copy of it matches.
+pub fn hints(words: &[Entry], style: KeyStyle, puzzle: &Puzzle) -> Vec<Entry> {
+    let mut hasher = DefaultHasher::new();
+    puzzle.grid.hash(&mut hasher);
+    let mut rng = StdRng::seed_from_u64(hasher.finish());
+    words
+        .iter()
+        .map(|entry| {
+            let mut entry = entry.clone();
+            match (style, &entry.clue) {
+                (_, Some(clue)) => entry.word = clue.clone(),
+                (KeyStyle::Words, _) => (),
+                (KeyStyle::Scrambled, _) => entry.word = scramble(&entry.grid_form(), &mut rng),
+                (KeyStyle::Blanks, _) => entry.word = blanks(&entry.grid_form()),
+                (KeyStyle::Bilingual, _) => {
+                    if let Some(translation) = &entry.translation {
+                        entry.word = format!("{} — {}", entry.word.to_uppercase(), translation);
+                    }
+                }
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Shuffle the letters of a word, so that it's spelled differently if it can be.
+fn scramble(word: &str, rng: &mut StdRng) -> String {
+    let mut letters: Vec<char> = word.chars().collect();
+    for _ in 0..SCRAMBLE_TRIES {
+        letters.shuffle(rng);
+        if !letters.iter().copied().eq(word.chars()) {
+            break;
+        }
+    }
+    letters.into_iter().collect()
+}
+
+/// Show a word as its first letter and a blank for each letter after it.
+fn blanks(word: &str) -> String {
+    let mut letters = word.chars();
+    let first = letters.next().map(String::from).unwrap_or_default();
+    letters.fold(first, |hint, _| hint + " _")
+}
+
+/// Arrange the words of the key into columns. If the words have categories, they're grouped under a heading for
+/// each, and whole groups are spread across the columns so the longest column is as short as possible.
+pub fn columns(words: &[Entry], num_columns: usize) -> Vec<Vec<Line<'_>>> {
+    let mut columns: Vec<Vec<Line>> = (0..num_columns).map(|_| vec![]).collect();
+    if words.iter().all(|e| e.category.is_none()) {
+        let cells = render::column_iter(num_columns as u32, 1, num_columns as u32, words.len());
+        for ((column, _), entry) in cells.zip(words) {
+            columns[column as usize].push(Line {
+                text: &entry.word,
+                heading: false,
+            });
+        }
+        return columns;
+    }
+
+    let mut groups: Vec<Vec<Line>> = vec![];
+    let mut category = None;
+    for entry in words {
+        if groups.is_empty() || entry.category != category {
+            category = entry.category.clone();
+            groups.push(vec![]);
+            if let Some(heading) = &entry.category {
+                groups.last_mut().unwrap().push(Line {
+                    text: heading,
+                    heading: true,
+                });
+            }
+        }
+        groups.last_mut().unwrap().push(Line {
+            text: &entry.word,
+            heading: false,
+        });
+    }
+
+    // Find the shortest column height that all the groups fit into.
+    let sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+    let mut height = sizes.iter().copied().max().unwrap_or(0);
+    while pack(&sizes, height)
+        .last()
+        .is_some_and(|&c| c >= num_columns)
+    {
+        height += 1;
+    }
+    for (group, column) in groups.into_iter().zip(pack(&sizes, height)) {
+        if !columns[column].is_empty() {
+            columns[column].push(Line {
+                text: "",
+                heading: false,
+            });
+        }
+        columns[column].extend(group);
+    }
+    columns
+}
+
+/// Assign each group of lines to a column, filling each column up to `height` lines, with a blank line between
+/// groups, before starting the next.
+fn pack(sizes: &[usize], height: usize) -> Vec<usize> {
+    let (mut column, mut filled) = (0, 0);
+    sizes
+        .iter()
+        .map(|&size| {
+            if filled > 0 && filled + 1 + size > height {
+                column += 1;
+                filled = 0;
+            }
+            filled += if filled > 0 { size + 1 } else { size };
+            column
+        })
+        .collect()
+}
+
+/// Return how many lines the longest column of the key has.
+pub fn rows(words: &[Entry], num_columns: usize) -> usize {
+    columns(words, num_columns)
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Split the words into pages: as many as fit in `first_rows` lines of the key on the first page, then `rows` lines
+/// on each page after that.
+pub fn paginate(
+    words: &[Entry],
+    num_columns: usize,
+    first_rows: usize,
+    rows: usize,
+) -> Vec<&[Entry]> {
+    let mut pages = vec![];
+    let mut rest = words;
+    let mut limit = first_rows;
+    while !rest.is_empty() {
+        let mut n = 0;
+        while n < rest.len() && self::rows(&rest[..n + 1], num_columns) <= limit {
+            n += 1;
+        }
+        // The first page might have no room for the key, but every page after it takes at least one word.
+        if n == 0 && !pages.is_empty() {
+            n = 1;
+        }
+        pages.push(&rest[..n]);
+        rest = &rest[n..];
+        limit = rows;
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use icu_locale_core::Locale;
+
+    use super::{columns, hints, paginate, sort};
+    use crate::config::{KeyStyle, SortKey};
+    use crate::grid::Puzzle;
+    use crate::wordlist::Entry;
+
+    fn entries(words: &[&str]) -> Vec<Entry> {
+        words.iter().map(|w| Entry::new(w.to_string())).collect()
+    }
+
+    fn words(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|e| e.word.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort() {
+        let check = |list: &[&str], lang: &str, expected: &[&str]| {
+            let lang: Locale = lang.parse().unwrap();
+            let sorted = sort(&entries(list), Some(SortKey::Alphabetical), Some(&lang)).unwrap();
+            assert_eq!(expected, words(&sorted));
+        };
+        check(&["Pferd", "Öl", "Ost"], "de", &["Öl", "Ost", "Pferd"]);
+        check(
+            &["chata", "hrad", "cesta"],
+            "cs",
+            &["cesta", "hrad", "chata"],
+        );
+
+        let list = entries(&["ox", "cat", "New York", "dog"]);
+        let sorted = sort(&list, Some(SortKey::Length), None).unwrap();
+        assert_eq!(["New York", "cat", "dog", "ox"].to_vec(), words(&sorted));
+    }
+
+    #[test]
+    fn test_hints() {
+        let puzzle = Puzzle {
+            grid: vec![vec![Some('A')]],
+            placements: vec![],
+            dropped: vec![],
+            attempts: vec![],
+        };
+        let list = entries(&["Elephant", "T-Rex"]);
+        let scrambled = hints(&list, KeyStyle::Scrambled, &puzzle);
+        for (entry, hint) in list.iter().zip(&scrambled) {
+            let sorted = |s: &str| {
+                let mut letters: Vec<char> = s.chars().collect();
+                letters.sort();
+                letters
+            };
+            assert_ne!(entry.grid_form(), hint.word);
+            assert_eq!(sorted(&entry.grid_form()), sorted(&hint.word));
+            assert_eq!(entry.grid_form(), hint.grid_form());
+        }
+        assert_eq!(
+            words(&scrambled),
+            words(&hints(&list, KeyStyle::Scrambled, &puzzle))
+        );
+        assert_eq!(words(&list), words(&hints(&list, KeyStyle::Words, &puzzle)));
+        assert_eq!(
+            vec!["E _ _ _ _ _ _ _", "T _ _ _"],
+            words(&hints(&list, KeyStyle::Blanks, &puzzle))
+        );
+        let translated = vec![list[0]
+            .clone()
+            .with_translation(Some("elefante".to_string()))];
+        assert_eq!(
+            vec!["ELEPHANT — elefante"],
+            words(&hints(&translated, KeyStyle::Bilingual, &puzzle))
+        );
+        assert_eq!(
+            words(&list),
+            words(&hints(&list, KeyStyle::Bilingual, &puzzle))
+        );
+        let clued = vec![list[0].clone().with_clue(Some("Has a trunk".to_string()))];
+        assert_eq!(
+            vec!["Has a trunk"],
+            words(&hints(&clued, KeyStyle::Blanks, &puzzle))
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut list = entries(&["cat", "dog", "owl", "ant", "bee", "elk"]);
+        for (entry, category) in list.iter_mut().zip(["M", "M", "B", "I", "I", "M"]) {
+            entry.category = Some(category.to_string());
+        }
+        let sorted = sort(&list, None, None).unwrap();
+        let texts: Vec<Vec<&str>> = columns(&sorted, 2)
+            .iter()
+            .map(|column| column.iter().map(|line| line.text).collect())
+            .collect();
+        assert_eq!(
+            vec![
+                vec!["M", "cat", "dog", "elk"],
+                vec!["B", "owl", "", "I", "ant", "bee"],
+            ],
+            texts
+        );
+    }
+
+    #[test]
+    fn test_paginate() {
+        let list = entries(&["a", "b", "c", "d", "e", "f", "g"]);
+        let pages: Vec<Vec<&str>> = paginate(&list, 2, 1, 2)
+            .iter()
+            .map(|page| words(page))
+            .collect();
+        assert_eq!(
+            vec![vec!["a", "b"], vec!["c", "d", "e", "f"], vec!["g"]],
+            pages
+        );
+        assert_eq!(1, paginate(&list, 2, 4, 2).len());
+    }
+}
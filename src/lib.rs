@@ -0,0 +1,34 @@
+//! Generate wordsearch puzzles.
+//!
+//! Laying out the grid needs only the core modules. Drawing it, and the command line options, need the "render"
+//! feature, which is on by default.
+//!
+//! The placement engine, `grid` along with the `filler` and `error` modules it uses, does no I/O and needs no crate
+//! but rand, so it can be embedded in places like WASM workers and plugins. It only reads the clock when given a
+//! deadline.
+//!
+//! Everything in the library that can fail returns an `error::WordSearchError`, so callers can tell what went wrong.
+
+pub mod checksum;
+#[cfg(feature = "render")]
+pub mod code;
+#[cfg(feature = "render")]
+pub mod config;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filler;
+pub mod grid;
+#[cfg(feature = "render")]
+pub mod key;
+#[cfg(feature = "render")]
+pub mod page;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod script;
+pub mod solver;
+#[cfg(feature = "render")]
+pub mod typeset;
+pub mod wordlist;
@@ -1,206 +1,1064 @@
 use std::{
-    cmp::{max, min, Ordering},
+    collections::HashMap,
+    env,
     fs::File,
-    io::{BufRead, BufReader},
-    path::PathBuf,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::anyhow;
 use anyhow::Error;
 use clap::Parser;
-use config::Args;
-use grid::Grid;
-use image::{ImageBuffer, Rgb, RgbImage};
-use imageproc::drawing;
-use rusttype::{Font, Scale};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use wordsearch::code::Code;
+use wordsearch::config::{Args, Command, Emit, Fit, Preview};
+use wordsearch::error::WordSearchError;
+use wordsearch::grid::{self, Direction, Grid, Puzzle, QualityWeights};
+use wordsearch::wordlist::{Entry, WordList};
+use wordsearch::{checksum, config, error, key, page, render, script, solver, typeset, wordlist};
 
-mod config;
-mod grid;
+mod edit;
+mod export;
+mod html;
+mod import;
+mod office;
+mod photo;
+mod project;
+mod svg;
 
-/// How much to pad the horizontal space allocated to each character in the grid.
-const PADDING: f32 = 1.3;
+/// How many layouts to try at each size with --min-size, before deciding the words don't fit.
+const MIN_SIZE_ATTEMPTS: usize = 200;
+
+/// How many times --fit grow makes the page bigger, in case the things on it grow with it.
+const FIT_ATTEMPTS: usize = 4;
+
+/// Pages with more pixels than this are written as PNGs a band of rows at a time, instead of all at once, to keep the
+/// memory they take bounded.
+const STREAM_PIXELS: u64 = 40_000_000;
+
+/// The output name for writing the image to standard output.
+const STDOUT: &str = "-";
+
+/// How often --watch checks whether the files have changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Set by Ctrl-C, to stop making puzzles.
+static CANCEL: LazyLock<Arc<AtomicBool>> = LazyLock::new(Default::default);
 
 fn main() -> Result<(), Error> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // The first Ctrl-C stops cleanly and says how far it got; a second one quits right away.
+    let cancel = CANCEL.clone();
+    ctrlc::set_handler(move || {
+        if cancel.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    })?;
+    if let Some(path) = &args.project {
+        let project = project::read_project(path)?;
+        let seed = project.seed;
+        args = project::merge_args(project.args, env::args_os().collect())?;
+        args.seed = args.seed.or(seed);
+    }
+    let mut directions = Direction::ALL.to_vec();
+    if let Some(code) = args.from_code.clone() {
+        code.apply(&mut args);
+        directions = code.directions;
+    }
+    let seed = args.seed.unwrap_or_else(random_seed);
+    if to_stdout(&args) {
+        check_stdout(&args)?;
+    }
+    if args.watch {
+        return watch(&args, seed, &directions);
+    }
+    make(args, seed, &directions)
+}
 
-    let words = read_wordlist(&args.wordlist)?;
+/// Return whether the image is to be written to standard output, with --output -.
+fn to_stdout(args: &Args) -> bool {
+    args.output.as_deref() == Some(Path::new(STDOUT))
+}
 
-    let grid = make_grid(&words, args.grid_width, args.grid_height)?;
+/// Make sure the options make a single image, with nothing else printed to standard output, since that's where the
+/// image goes.
+fn check_stdout(args: &Args) -> Result<(), Error> {
+    let more_files = args.teachers_edition
+        || args.roster.is_some()
+        || !args.tiers.is_empty()
+        || args.solution_text
+        || args.emit.len() != 1
+        || !args.formats.is_empty()
+        || !args.reveal.is_empty()
+        || args.reveal_count.is_some()
+        || args.watch;
+    if more_files {
+        return Err(anyhow!(
+            "Only one image can be written to standard output; leave out --teachers-edition, --roster, --tiers, \
+             --solution-text, --watch, --formats, --reveal, and more than one --emit"
+        ));
+    }
+    if args.explain || args.min_size || args.open {
+        return Err(anyhow!(
+            "--explain, --min-size, and --open can't be used with --output -"
+        ));
+    }
+    Ok(())
+}
 
-    let image = make_image(&words, grid, args.image_width, args.image_height)?;
+/// Keep random seeds to 32 bits, so the codes for them stay short.
+fn random_seed() -> u64 {
+    rand::thread_rng().gen::<u32>() as u64
+}
 
-    let filename = args.output.unwrap_or_else(|| {
-        let mut n = args.wordlist.clone();
-        n.set_extension("png");
-        n
-    });
-    image.save(filename)?;
+/// Make the puzzles the user asked for, and save them.
+fn make(mut args: Args, seed: u64, directions: &[Direction]) -> Result<(), Error> {
+    let deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+    let list_title = match &args.title {
+        Some(_) => None,
+        None => args.list_title()?,
+    };
+    args.title = args.title.or(list_title.clone());
+
+    if let Some(roster) = &args.roster {
+        return make_roster(&args, roster, seed, directions, deadline);
+    }
+    if !args.tiers.is_empty() {
+        return make_tiers(&args, seed, deadline);
+    }
+
+    let mut code = None;
+    let (words, puzzle, input) = match (&args.command, &args.project) {
+        (Some(Command::Edit { project }), _) => return edit::edit(project),
+        (Some(Command::CheckAnswer { checksum, answers }), _) => {
+            return check_answer(checksum, answers)
+        }
+        (Some(Command::SolveImage { photo, words }), _) => return solve_image(photo, words, &args),
+        (Some(Command::VerifyDir { dir, jobs }), _) => return verify_dir(dir, *jobs),
+        (Some(Command::Render { input }), _) => {
+            let (words, puzzle) = read_puzzle(input)?;
+            verify_placements(&words, &puzzle)?;
+            (words, puzzle, input)
+        }
+        (None, Some(path)) => {
+            let project = project::read_project(path)?;
+            (project.words, project.puzzle, path)
+        }
+        (None, None) => {
+            let words = keep_words(args.read_words()?);
+            match &args.grid {
+                Some(grid) => {
+                    let puzzle = import::read_text_grid(grid, &grid_words(&words))?;
+                    if args.verify {
+                        verify_placements(&words, &puzzle)?;
+                    }
+                    (words, puzzle, grid)
+                }
+                None => {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let base = match &args.base {
+                        Some(base) => Some(import::read_ipuz(base)?.1),
+                        None => None,
+                    };
+                    let grid = new_grid(
+                        &args,
+                        &words,
+                        args.hidden_words(&words),
+                        base.as_ref().map(|b| b.grid[0].len()).or(args.grid_width),
+                        base.as_ref().map(|b| b.grid.len()).or(args.grid_height),
+                        deadline,
+                    )?
+                    .with_directions(directions);
+                    let puzzle = if args.min_size {
+                        let aspect = match (args.grid_width, args.grid_height) {
+                            (Some(w), Some(h)) => w as f32 / h as f32,
+                            _ => {
+                                let (w, h) = default_shape(
+                                    &args,
+                                    &words,
+                                    &args.hidden_words(&words),
+                                    args.density(),
+                                )?;
+                                w as f32 / h as f32
+                            }
+                        };
+                        let puzzle = grid.smallest(aspect, MIN_SIZE_ATTEMPTS, &mut rng)?;
+                        print_layout(&puzzle);
+                        puzzle
+                    } else if let Some(base) = &base {
+                        build_on(grid, base, &mut rng)?
+                    } else {
+                        grid.generate(&mut rng)?
+                    };
+                    // The code makes the puzzle as it was generated, before it's turned or flipped.
+                    code = Some(Code::new(&args, seed, &puzzle, directions));
+                    let puzzle = args.orient(puzzle);
+                    if args.explain {
+                        explain(&puzzle, &args.optimize);
+                    }
+                    (drop_words(&words, &puzzle), puzzle, &args.wordlist)
+                }
+            }
+        }
+    };
+
+    if args.preview.is_some() {
+        return preview(&words, &puzzle, &args, None);
+    }
+    if let Some(path) = &args.save_project {
+        let mut argv: Vec<String> = env::args().skip(1).collect();
+        // The project keeps its own copy of the words, so it needs the title they came with too.
+        if let Some(title) = list_title {
+            argv.extend(["--title".to_string(), title]);
+        }
+        project::write_project(File::create(path)?, &argv, Some(seed), &words, &puzzle)?;
+    }
+    if let Some(path) = &args.answers_csv {
+        export::write_answers_csv(File::create(path)?, &puzzle.placements)?;
+    }
+    if let Some(path) = &args.answers_list {
+        export::write_answers_list(File::create(path)?, &puzzle.placements)?;
+    }
+    if let Some(path) = &args.ipuz {
+        export::write_ipuz(File::create(path)?, &puzzle)?;
+    }
+
+    let input = input.clone();
+    if args.show_code {
+        args.code = code.map(|code| code.to_string());
+    }
+    let answers = args.teachers_edition.then_some("teacher");
+    save_images(&words, &puzzle, &args, None, &input, None, answers)?;
+    if args.open {
+        open_output(&args, &input, None)?;
+    }
 
     Ok(())
 }
 
-fn read_wordlist(filename: &PathBuf) -> Result<Vec<String>, Error> {
-    let file = File::open(filename)?;
-    let rdr = BufReader::new(file);
-    let lines = rdr.lines().collect::<Result<Vec<_>, _>>()?;
-    if lines.is_empty() {
-        return Err(anyhow!("Empty word list: {:?}", filename));
+/// Add the new words to the puzzle from --base, keeping the rest of it the way it was. If they don't fit, make a new
+/// puzzle the same size instead.
+fn build_on(grid: Grid, base: &Puzzle, rng: &mut StdRng) -> Result<Puzzle, Error> {
+    match grid.clone().with_base(base).generate(rng) {
+        Ok(puzzle) => Ok(puzzle),
+        Err(e @ WordSearchError::Cancelled { .. }) => Err(e.into()),
+        Err(e) => {
+            eprintln!("Couldn't keep the earlier layout, so made a new one: {}", e);
+            Ok(grid.generate(rng)?)
+        }
     }
-    Ok(lines)
 }
 
-fn make_grid(
-    words: &[String],
+/// Make the puzzles, and then make them again each time one of the files they're made from changes, until Ctrl-C.
+/// The seed stays the same unless --reseed is given.
+fn watch(args: &Args, mut seed: u64, directions: &[Direction]) -> Result<(), Error> {
+    let mut files = vec![];
+    if args.words.is_empty() {
+        files.push(args.wordlist.clone());
+    }
+    files.extend(args.grid.clone());
+    files.extend(args.roster.clone());
+    if let Some(Command::Render { input }) = &args.command {
+        files = vec![input.clone()];
+    }
+    let modified = || -> Vec<_> {
+        files
+            .iter()
+            .map(|f| f.metadata().and_then(|m| m.modified()).ok())
+            .collect()
+    };
+    let names: Vec<_> = files.iter().map(|f| f.to_string_lossy()).collect();
+    loop {
+        let before = modified();
+        match make(args.clone(), seed, directions) {
+            Ok(()) => println!("Made the puzzle with seed {}", seed),
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+        println!(
+            "Watching {} for changes. Press Ctrl-C to stop.",
+            names.join(", ")
+        );
+        while modified() == before {
+            if CANCEL.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            thread::sleep(WATCH_INTERVAL);
+        }
+        if args.reseed {
+            seed = random_seed();
+        }
+    }
+}
+
+/// Make a puzzle for each student in the roster, each with a different seed and with the student's name in the
+/// header.
+fn make_roster(
+    args: &Args,
+    roster: &Path,
+    seed: u64,
+    directions: &[Direction],
+    deadline: Option<Instant>,
+) -> Result<(), Error> {
+    let students = wordlist::read_lines(roster, args.strict)?;
+    let words = keep_words(args.read_words()?);
+    for (i, student) in students.iter().enumerate() {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+        let mut caps_words = grid_words(&words);
+        if args.roster_bonus {
+            caps_words.push(wordlist::grid_form(student));
+        }
+        let grid = new_grid(
+            args,
+            &words,
+            caps_words,
+            args.grid_width,
+            args.grid_height,
+            deadline,
+        )?
+        .with_directions(directions);
+        let puzzle = grid.generate(&mut rng)?;
+        let args = &with_code(args, seed.wrapping_add(i as u64), &puzzle, directions);
+        let puzzle = args.orient(puzzle);
+        if args.explain {
+            println!("{}:", student);
+            explain(&puzzle, &args.optimize);
+        }
+        let suffix: String = student
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let answers = args.teachers_edition.then(|| format!("{}-teacher", suffix));
+        save_images(
+            &drop_words(&words, &puzzle),
+            &puzzle,
+            args,
+            Some(student),
+            &args.wordlist,
+            Some(&suffix),
+            answers.as_deref(),
+        )?;
+        if args.open && i == 0 {
+            open_output(args, &args.wordlist, Some(&suffix))?;
+        }
+    }
+    Ok(())
+}
+
+/// Make a version of the puzzle for each difficulty tier, each with its own solution.
+fn make_tiers(args: &Args, seed: u64, deadline: Option<Instant>) -> Result<(), Error> {
+    let words = keep_words(args.read_words()?);
+    let caps_words = grid_words(&words);
+    for (i, tier) in args.tiers.iter().enumerate() {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (width, height) = match (args.grid_width, args.grid_height) {
+            (None, None) => default_shape(args, &words, &caps_words, tier.density())?,
+            (width, height) => {
+                let size = Grid::default_size(&caps_words, tier.density());
+                (width.unwrap_or(size), height.unwrap_or(size))
+            }
+        };
+        let grid = new_grid(
+            args,
+            &words,
+            caps_words.clone(),
+            Some(width),
+            Some(height),
+            deadline,
+        )?
+        .with_directions(tier.directions());
+        let puzzle = grid.generate(&mut rng)?;
+        let args = &with_code(args, seed, &puzzle, tier.directions());
+        let puzzle = args.orient(puzzle);
+        let name = tier.to_string();
+        if args.explain {
+            println!("{}:", name);
+            explain(&puzzle, &args.optimize);
+        }
+        save_images(
+            &drop_words(&words, &puzzle),
+            &puzzle,
+            args,
+            None,
+            &args.wordlist,
+            Some(&name),
+            Some(&format!("{}-solution", name)),
+        )?;
+        if args.open && i == 0 {
+            open_output(args, &args.wordlist, Some(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Return the options to draw the puzzle with: the same ones, plus the puzzle's code if it's to be shown.
+fn with_code(args: &Args, seed: u64, puzzle: &Puzzle, directions: &[Direction]) -> Args {
+    let mut args = args.clone();
+    if args.show_code {
+        args.code = Some(Code::new(&args, seed, puzzle, directions).to_string());
+    }
+    args
+}
+
+/// Set up a grid for the words, with the options the user asked for, stopping at the deadline or on Ctrl-C. Without a
+/// width or height, the grid is shaped to fill the page around the key, which lists the `key` entries.
+fn new_grid(
+    args: &Args,
+    key: &[Entry],
+    words: Vec<String>,
     width: Option<usize>,
     height: Option<usize>,
-) -> Result<Vec<Vec<char>>, Error> {
-    let legal: String = ('A'..='Z').collect();
-    let caps_words = words
+    deadline: Option<Instant>,
+) -> Result<Grid, Error> {
+    let (width, height) = match (width, height) {
+        (None, None) => {
+            let (width, height) = default_shape(args, key, &words, args.density())?;
+            (Some(width), Some(height))
+        }
+        size => size,
+    };
+    Ok(args
+        .new_grid(words, width, height)?
+        .with_optional(optional_words(key))
+        .with_deadline(deadline)
+        .with_cancel(CANCEL.clone()))
+}
+
+/// Return the words that can be left out if they don't all fit, by their form in the grid, with their weights.
+fn optional_words(words: &[Entry]) -> HashMap<String, f32> {
+    words
         .iter()
-        .map(|w| {
-            w.to_uppercase()
-                .chars()
-                .filter(|c| legal.contains(*c))
-                .collect()
-        })
-        .collect();
-    let grid = Grid::new(caps_words, width, height);
-    grid.generate()
+        .filter_map(|e| Some((e.grid_form(), e.weight?)))
+        .collect()
 }
 
-fn make_image(
-    wordlist: &Vec<String>,
-    grid: Vec<Vec<char>>,
-    width: u32,
-    height: u32,
-) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, Error> {
-    let mut image = RgbImage::new(width, height);
+/// Return the width and height of a grid where the words fill about `density` of the cells, shaped like the room the
+/// grid gets on the page.
+fn default_shape(
+    args: &Args,
+    key: &[Entry],
+    words: &[String],
+    density: f32,
+) -> Result<(usize, usize), Error> {
+    let cells = Grid::default_size(words, density).pow(2);
+    let aspect = render::grid_aspect(key, args, cells)?;
+    Ok(Grid::default_shape(words, density, aspect))
+}
 
-    for x in 0..width {
-        for y in 0..height {
-            *image.get_pixel_mut(x, y) = image::Rgb([255, 255, 255]);
-        }
+/// Return the words of the list, and tell the user about any that were left out for having no letters to hide.
+fn keep_words(list: WordList) -> Vec<Entry> {
+    for entry in &list.left_out {
+        eprintln!("Leaving out {:?}, which has no letters to hide", entry.word);
+    }
+    list.entries
+}
+
+/// Return the words that made it into the puzzle, and tell the user about any that were left out.
+fn drop_words(words: &[Entry], puzzle: &Puzzle) -> Vec<Entry> {
+    if puzzle.dropped.is_empty() {
+        return words.to_vec();
     }
+    let (kept, dropped): (Vec<_>, Vec<_>) = words
+        .iter()
+        .zip(grid_words(words))
+        .partition(|(_, w)| !puzzle.dropped.contains(w));
+    let dropped: Vec<_> = dropped.into_iter().map(|(w, _)| w.word.as_str()).collect();
+    eprintln!("Left out words that didn't fit: {}", dropped.join(", "));
+    kept.into_iter().map(|(w, _)| w.clone()).collect()
+}
 
-    let font = include_bytes!("../FreeSans.ttf") as &[u8];
-    let font = Font::try_from_bytes(font).ok_or(anyhow!("Couldn't parse built-in font data"))?;
+/// Writes a whole puzzle to a file: the grid, the key, and, if the flag is set, the answers.
+type WriteDocument = fn(File, &[Entry], &Puzzle, &Args, Option<&str>, bool) -> Result<(), Error>;
 
-    let desired_stride = min(width / grid[0].len() as u32, height / grid.len() as u32);
-    let text_height = compute_text_height(&font, desired_stride as i32)?;
-    let scale = Scale {
-        x: text_height,
-        y: text_height,
+/// Render and save each of the artifacts the user asked for. If `answers` is given, also save a copy of the page
+/// with the answers marked, with that suffix on its file name.
+fn save_images(
+    words: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    input: &Path,
+    suffix: Option<&str>,
+    answers: Option<&str>,
+) -> Result<(), Error> {
+    if args.preview.is_some() {
+        return preview(words, puzzle, args, suffix);
+    }
+    if args.show_checksum && args.checksum.is_none() {
+        let mut args = args.clone();
+        args.checksum = Some(checksum::checksum(&checksum::answers(&puzzle.placements)));
+        return save_images(words, puzzle, &args, student, input, suffix, answers);
+    }
+    // Each format is saved from the same puzzle and options, so they're all laid out the same way.
+    if !args.formats.is_empty() {
+        let output = output_path(args, input, None);
+        for format in &args.formats {
+            let mut args = args.clone();
+            args.formats.clear();
+            args.output = Some(output.with_extension(format));
+            if format == "ipuz" {
+                export::write_ipuz(File::create(output_path(&args, input, suffix))?, puzzle)?;
+                continue;
+            }
+            save_images(words, puzzle, &args, student, input, suffix, answers)?;
+        }
+        return Ok(());
+    }
+    let answer_words = &key::sort(words, args.sort_key, args.lang.as_ref())?;
+    // The answers list the real words, even when the puzzle's key hints at them.
+    let words = &key::hints(answer_words, args.key, puzzle);
+    let hint = match suffix {
+        Some(suffix) => format!("{}-hint", suffix),
+        None => "hint".to_string(),
+    };
+    let revealed = reveal(answer_words, puzzle, args)?;
+    // The copies with answers marked: the teacher's edition, with all of them, and the hint, with only some.
+    let answer_copies: Vec<(&str, &Puzzle)> = answers
+        .map(|answers| (answers, puzzle))
+        .into_iter()
+        .chain(revealed.as_ref().map(|revealed| (hint.as_str(), revealed)))
+        .collect();
+    if args.solution_text {
+        let name = match suffix {
+            Some(suffix) => format!("{}-solution", suffix),
+            None => "solution".to_string(),
+        };
+        let path = output_path(args, input, Some(&name)).with_extension("txt");
+        export::write_solution_text(File::create(path)?, puzzle)?;
+    }
+    let extension = output_path(args, input, None)
+        .extension()
+        .map(|ext| ext.to_ascii_lowercase());
+    let is_svg = extension.as_ref().is_some_and(|ext| ext == "svg");
+    let is_html = extension.as_ref().is_some_and(|ext| ext == "html");
+    if args.interactive && !is_html {
+        return Err(anyhow!(
+            "--interactive needs an output file ending in .html"
+        ));
+    }
+    if args.interactive && args.criss_cross {
+        return Err(anyhow!("A criss-cross puzzle can't be made --interactive"));
+    }
+    // Documents are written whole, with the grid and the key together.
+    let write_document = match extension.as_ref().and_then(|ext| ext.to_str()) {
+        Some("html") => Some(html::write_html::<File> as WriteDocument),
+        Some("docx") => Some(office::write_docx::<File> as WriteDocument),
+        Some("odt") => Some(office::write_odt::<File> as WriteDocument),
+        Some("xlsx") => Some(office::write_xlsx::<File> as WriteDocument),
+        _ => None,
     };
+    if (args.bleed.is_some() || args.crop_marks) && (is_svg || write_document.is_some()) {
+        return Err(anyhow!(
+            "--bleed and --crop-marks only work with PNG or TIFF images"
+        ));
+    }
+    if let Some(write_document) = write_document {
+        if args.emit.iter().any(|emit| !matches!(emit, Emit::Page)) {
+            return Err(anyhow!(
+                "{} output only supports --emit page",
+                extension
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_uppercase()
+            ));
+        }
+        let file = File::create(output_path(args, input, suffix))?;
+        write_document(file, words, puzzle, args, student, false)?;
+        for &(answers, puzzle) in &answer_copies {
+            let file = File::create(output_path(args, input, Some(answers)))?;
+            write_document(file, answer_words, puzzle, args, student, true)?;
+        }
+        return Ok(());
+    }
+    let args = &fit_page(words, puzzle, args, student)?;
+    if is_svg {
+        if args.emit.iter().any(|emit| !matches!(emit, Emit::Page)) {
+            return Err(anyhow!("SVG output only supports --emit page"));
+        }
+        let file = File::create(output_path(args, input, suffix))?;
+        svg::write_svg(file, words, puzzle, args, student, false)?;
+        for (i, page) in svg::continued_pages(words, puzzle, args, student)?
+            .iter()
+            .enumerate()
+        {
+            std::fs::write(output_path(args, input, Some(&continued(suffix, i))), page)?;
+        }
+        for &(answers, puzzle) in &answer_copies {
+            let file = File::create(output_path(args, input, Some(answers)))?;
+            svg::write_svg(file, answer_words, puzzle, args, student, true)?;
+        }
+        return Ok(());
+    }
 
-    // color of the text
-    let (red, green, blue) = (0, 0, 0);
+    // rusttype can't draw color emoji, but browsers and office programs can.
+    if puzzle
+        .grid
+        .iter()
+        .flatten()
+        .flatten()
+        .any(|&c| script::is_emoji(c))
+    {
+        return Err(anyhow!(
+            "Emoji can't be drawn in images; save the puzzle as SVG, HTML, DOCX, ODT, or XLSX instead"
+        ));
+    }
+    if to_stdout(args) {
+        return write_stdout(words, puzzle, args, student);
+    }
+    let (width, height) = render::sheet_size(args);
+    let stream = extension.as_ref().is_some_and(|ext| ext == "png")
+        && width as u64 * height as u64 > STREAM_PIXELS;
+    for emit in &args.emit {
+        if let (Emit::Page, true) = (emit, stream) {
+            let file = BufWriter::new(File::create(output_path(args, input, suffix))?);
+            render::write_png(file, words, puzzle, args, student, false)?;
+            render::write_continued_pngs(words, puzzle, args, student, |i| {
+                let path = output_path(args, input, Some(&continued(suffix, i)));
+                Ok(BufWriter::new(File::create(path)?))
+            })?;
+            continue;
+        }
+        let image = match emit {
+            Emit::Page => render::make_image(words, puzzle, args, student, false)?,
+            Emit::Grid => render::make_grid_image(words, puzzle, args, false)?,
+            Emit::Key => render::make_key_image(words, puzzle, args)?,
+            Emit::Solution => render::make_grid_image(words, puzzle, args, true)?,
+        };
+        let name = match (emit, suffix) {
+            (Emit::Page, _) => suffix.map(str::to_string),
+            (_, Some(suffix)) => Some(format!("{}-{}", suffix, emit)),
+            (_, None) => Some(emit.to_string()),
+        };
+        image.save(output_path(args, input, name.as_deref()))?;
+        if let Emit::Page = emit {
+            let pages = render::make_continued_images(words, puzzle, args, student)?;
+            for (i, page) in pages.iter().enumerate() {
+                page.save(output_path(args, input, Some(&continued(suffix, i))))?;
+            }
+        }
+    }
+    for &(answers, puzzle) in &answer_copies {
+        let path = output_path(args, input, Some(answers));
+        if stream {
+            render::write_png(
+                BufWriter::new(File::create(path)?),
+                answer_words,
+                puzzle,
+                args,
+                student,
+                true,
+            )?;
+        } else {
+            render::make_image(answer_words, puzzle, args, student, true)?.save(path)?;
+        }
+    }
+    Ok(())
+}
 
-    let (text_width, text_height) = drawing::text_size(scale, &font, "M");
-    let grid_stride = max((text_width as f32 * PADDING) as i32, text_height);
+/// Return a copy of the puzzle with only the words chosen by --reveal or --reveal-count in it, to be marked as a hint.
+/// --reveal-count takes the first words of the key, so a bigger count reveals the same words and more.
+fn reveal(words: &[Entry], puzzle: &Puzzle, args: &Args) -> Result<Option<Puzzle>, Error> {
+    let chosen: Vec<String> = match args.reveal_count {
+        Some(count) => words.iter().map(Entry::grid_form).take(count).collect(),
+        None if args.reveal.is_empty() => return Ok(None),
+        None => args.reveal.iter().map(|w| wordlist::grid_form(w)).collect(),
+    };
+    let placements = chosen
+        .iter()
+        .map(|word| {
+            puzzle
+                .placements
+                .iter()
+                .find(|p| p.word == *word)
+                .cloned()
+                .ok_or_else(|| anyhow!("Can't reveal {}, which isn't in the puzzle", word))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Some(Puzzle {
+        grid: puzzle.grid.clone(),
+        placements,
+        dropped: vec![],
+        attempts: vec![],
+    }))
+}
 
-    for (y, line) in grid.iter().enumerate() {
-        for (x, letter) in line.iter().map(char::to_string).enumerate() {
-            let (let_width, _) = drawing::text_size(scale, &font, &letter);
-            drawing::draw_text_mut(
-                &mut image,
-                Rgb([red, green, blue]),
-                x as i32 * grid_stride + (grid_stride - let_width) / 2,
-                y as i32 * grid_stride,
-                scale,
-                &font,
-                &letter,
-            );
+/// Make sure the page is big enough for the puzzle. If it isn't, make it bigger with --fit grow, or else say how big
+/// it needs to be.
+fn fit_page(
+    words: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+) -> Result<Args, Error> {
+    let mut args = args.clone();
+    for _ in 0..FIT_ATTEMPTS {
+        let (width, height) = render::needed_size(words, puzzle, &args, student)?;
+        if width <= args.image_width && height <= args.image_height {
+            break;
+        }
+        if args.fit == Fit::Fail {
+            return Err(anyhow!(
+                "The puzzle doesn't fit on a {}x{} page; it needs at least {}x{}. Use a bigger --image-width and \
+                 --image-height, or --fit grow",
+                args.image_width,
+                args.image_height,
+                width.max(args.image_width),
+                height.max(args.image_height)
+            ));
         }
+        args.image_width = args.image_width.max(width);
+        args.image_height = args.image_height.max(height);
     }
+    Ok(args)
+}
+
+/// Print the puzzle in the terminal for --preview, under the name it would have been saved with if it's one of
+/// several.
+fn preview(
+    words: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    suffix: Option<&str>,
+) -> Result<(), Error> {
+    let words = &key::sort(words, args.sort_key, args.lang.as_ref())?;
+    if let Some(suffix) = suffix {
+        println!("{}:", suffix);
+    }
+    let highlight = (args.preview == Some(Preview::Answers)).then_some(args.mark_color());
+    let hints = key::hints(words, args.key, puzzle);
+    let words = if highlight.is_some() { words } else { &hints };
+    export::write_preview(std::io::stdout(), words, puzzle, highlight)?;
+    println!();
+    Ok(())
+}
+
+/// Write the one image asked for to standard output, as a PNG.
+fn write_stdout(
+    words: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+) -> Result<(), Error> {
+    let out = BufWriter::new(io::stdout().lock());
+    let emit = args.emit.first().copied().unwrap_or(Emit::Page);
+    let image = match emit {
+        Emit::Page => {
+            if render::page_count(words, puzzle, args, student)? > 1 {
+                return Err(anyhow!(
+                    "The key continues onto more pages, which can't be written to standard output; make the page \
+                     bigger or the key smaller"
+                ));
+            }
+            return Ok(render::write_png(out, words, puzzle, args, student, false)?);
+        }
+        Emit::Grid => render::make_grid_image(words, puzzle, args, false)?,
+        Emit::Key => render::make_key_image(words, puzzle, args)?,
+        Emit::Solution => render::make_grid_image(words, puzzle, args, true)?,
+    };
+    let (width, height) = image.dimensions();
+    PngEncoder::new(out).write_image(&image, width, height, ColorType::Rgb8)?;
+    Ok(())
+}
 
-    // Now make the key: the list of words hidden in the puzzle.
-    let scale = Scale {
-        x: text_height as f32 * 0.8,
-        y: text_height as f32 * 0.8,
+/// Open the first file saved for the puzzle in the system's default viewer, for --open.
+fn open_output(args: &Args, input: &Path, suffix: Option<&str>) -> Result<(), Error> {
+    let name = match (args.emit.first(), suffix) {
+        (Some(Emit::Page) | None, _) => suffix.map(str::to_string),
+        (Some(emit), Some(suffix)) => Some(format!("{}-{}", suffix, emit)),
+        (Some(emit), None) => Some(emit.to_string()),
     };
-    let (_, key_stride) = drawing::text_size(scale, &font, "M");
-    let key_y0 = grid.len() as i32 * grid_stride + key_stride;
-    for ((x, y), word) in column_iter(width, key_stride as u32, 3, wordlist.len()).zip(wordlist) {
-        drawing::draw_text_mut(
-            &mut image,
-            Rgb([red, green, blue]),
-            x,
-            y + key_y0,
-            scale,
-            &font,
-            word,
-        );
+    let mut path = output_path(args, input, name.as_deref());
+    if let Some(format) = args.formats.first() {
+        path.set_extension(format);
     }
+    #[cfg(target_os = "macos")]
+    let mut command = process::Command::new("open");
+    #[cfg(windows)]
+    let mut command = process::Command::new("explorer");
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = process::Command::new("xdg-open");
+    command.arg(&path).spawn().map_err(|e| {
+        let viewer = command.get_program().to_string_lossy();
+        anyhow!("Couldn't open {:?} with {}: {}", path, viewer, e)
+    })?;
+    Ok(())
+}
 
-    Ok(image)
+/// Return the suffix for the file name of the `i`th page the key continues onto, like "continued" or
+/// "continued-2", after the page's own suffix if it has one.
+fn continued(suffix: Option<&str>, i: usize) -> String {
+    let name = match i {
+        0 => "continued".to_string(),
+        _ => format!("continued-{}", i + 1),
+    };
+    match suffix {
+        Some(suffix) => format!("{}-{}", suffix, name),
+        None => name,
+    }
 }
 
-/// We can't get font metrics, so we do a binary search to find an appropriate
-/// text height.
-fn compute_text_height(font: &Font, desired_stride: i32) -> Result<f32, Error> {
-    let (mut min, mut max) = (1.0, 300.0);
-    while max - min > 1.0 {
-        let guess = (min + max) / 2.0;
-        let scale = Scale { x: guess, y: guess };
-        let (w, h) = drawing::text_size(scale, font, "M");
-        let stride = core::cmp::max((w as f32 * PADDING) as i32, h);
-        match stride.cmp(&desired_stride) {
-            Ordering::Less => min = guess,
-            Ordering::Greater => max = guess,
-            Ordering::Equal => return Ok(guess),
+/// Return the path of the output file: either the one the user asked for or one named after the input file, with the
+/// suffix (if any) added to the file name, like "words-suffix.png".
+fn output_path(args: &Args, input: &Path, suffix: Option<&str>) -> PathBuf {
+    let mut path = args.output.clone().unwrap_or_else(|| {
+        let mut n = input.to_path_buf();
+        n.set_extension("png");
+        n
+    });
+    if let Some(suffix) = suffix {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut name = format!("{}-{}", stem, suffix);
+        if let Some(ext) = path.extension() {
+            name = format!("{}.{}", name, ext.to_string_lossy());
         }
+        path.set_file_name(name);
     }
-    Err(anyhow!("unable to find a font size"))
+    path
 }
 
-/// Return an iterator of (X, Y) coordinates in the specified number of columns.
-fn column_iter(
-    image_width: u32,
-    y_stride: u32,
-    num_columns: u32,
-    length: usize,
-) -> impl Iterator<Item = (i32, i32)> {
-    let mut result = vec![];
-    let col_width = image_width / num_columns;
-    for column in 0..num_columns {
-        let mut num_rows = length as u32 / num_columns;
-        if length as u32 % num_columns > column {
-            num_rows += 1;
-        }
-        for row in 0..num_rows {
-            result.push(((column * col_width) as i32, (row * y_stride) as i32));
-        }
+/// Convert the words to the form they take in the grid.
+fn grid_words(words: &[Entry]) -> Vec<String> {
+    words.iter().map(Entry::grid_form).collect()
+}
+
+/// Print the size of the grid and its letters.
+fn print_layout(puzzle: &Puzzle) {
+    println!("{}x{}", puzzle.grid[0].len(), puzzle.grid.len());
+    for row in &puzzle.grid {
+        let row: String = row.iter().map(|c| c.unwrap_or(grid::EMPTY)).collect();
+        println!("{}", row);
     }
-    result.into_iter()
 }
 
-#[cfg(test)]
-mod tests {
-    use anyhow::Error;
+/// Print how each word was placed: where it is, how many places were tried before it fit, and which other words it
+/// crosses. Then print the layout's quality score, with the weights given by --optimize, and about how long it takes
+/// to solve.
+fn explain(puzzle: &Puzzle, weights: &QualityWeights) {
+    for (placement, attempts) in puzzle.placements.iter().zip(&puzzle.attempts) {
+        let (end_x, end_y) = placement.end();
+        let crossings: Vec<_> = puzzle
+            .crossings(placement)
+            .iter()
+            .map(|p| p.word.as_str())
+            .collect();
+        let crossings = match crossings.is_empty() {
+            true => "no other words".to_string(),
+            false => crossings.join(", "),
+        };
+        println!(
+            "{}: {} → {} ({}), after {} {}, crossing {}",
+            placement.word,
+            grid::cell_name(placement.x, placement.y),
+            grid::cell_name(end_x, end_y),
+            placement.direction,
+            attempts,
+            if *attempts == 1 { "try" } else { "tries" },
+            crossings
+        );
+    }
+    for word in &puzzle.dropped {
+        println!("{}: left out", word);
+    }
+    explain_table(puzzle);
+    println!("Quality: {:.2}", puzzle.quality(weights));
+    let solve_time = solver::solve_time(puzzle).as_secs();
+    println!(
+        "Estimated solve time: {}m {:02}s",
+        solve_time / 60,
+        solve_time % 60
+    );
+}
 
-    use crate::column_iter;
+/// Print a table of how many other words each word crosses and which way it reads, then how many words read each
+/// way, so words that cross nothing or all read the same way stand out.
+fn explain_table(puzzle: &Puzzle) {
+    let width = puzzle
+        .placements
+        .iter()
+        .map(|p| p.word.chars().count())
+        .chain(["Word".len()])
+        .max()
+        .unwrap_or(0);
+    println!();
+    println!("{:width$}  Crosses  Direction", "Word");
+    for placement in &puzzle.placements {
+        println!(
+            "{:width$}  {:>7}  {}",
+            placement.word,
+            puzzle.crossings(placement).len(),
+            placement.direction
+        );
+    }
+    let isolated = puzzle
+        .placements
+        .iter()
+        .filter(|p| puzzle.crossings(p).is_empty())
+        .count();
+    println!(
+        "{} of {} words cross no other word",
+        isolated,
+        puzzle.placements.len()
+    );
+    let directions: Vec<_> = Direction::ALL
+        .iter()
+        .filter_map(|&d| {
+            let count = puzzle
+                .placements
+                .iter()
+                .filter(|p| p.direction == d)
+                .count();
+            (count > 0).then(|| format!("{} {}", d, count))
+        })
+        .collect();
+    println!("Directions: {}", directions.join(", "));
+    println!();
+}
 
-    #[test]
-    fn test_column_iter() -> Result<(), Error> {
-        let expecteds = vec![(0, 0), (33, 0), (66, 0)];
-        for len in 0..=expecteds.len() {
-            let observed: Vec<_> = column_iter(100, 10, 3, len).collect();
-            let expected = expecteds[0..len].to_vec();
-            assert_eq!(expected, observed);
-        }
+/// Check the solver's answers against the checksum printed on their puzzle.
+fn check_answer(expected: &str, answers: &Path) -> Result<(), Error> {
+    let answers = checksum::parse_answers(&std::fs::read_to_string(answers)?)?;
+    if !checksum::matches(expected, &checksum::checksum(&answers)) {
+        return Err(anyhow!(
+            "Not yet: some of those {} answers are wrong, or some words are still missing",
+            answers.len()
+        ));
+    }
+    println!("All {} answers are right!", answers.len());
+    Ok(())
+}
 
-        let observed: Vec<_> = column_iter(100, 10, 3, 4).collect();
-        let expected = vec![(0, 0), (0, 10), (33, 0), (66, 0)];
-        assert_eq!(expected, observed);
+/// Read the puzzle in the photo, look for the words in it, and save a copy of the photo with them marked.
+fn solve_image(photo: &Path, words: &Path, args: &Args) -> Result<(), Error> {
+    let words = keep_words(wordlist::read_list(words, args.strict)?);
+    let mut image = image::open(photo)?.to_rgb8();
+    let scan = photo::read_photo(&image, &grid_words(&words), args)?;
+    print_layout(&scan.puzzle);
+    let missing: Vec<_> = words
+        .iter()
+        .zip(grid_words(&words))
+        .filter(|(_, w)| !scan.puzzle.placements.iter().any(|p| p.word == *w))
+        .map(|(w, _)| w.word.as_str())
+        .collect();
+    println!(
+        "Found {} of {} words",
+        words.len() - missing.len(),
+        words.len()
+    );
+    if !missing.is_empty() {
+        eprintln!("Couldn't find: {}", missing.join(", "));
+    }
+    render::mark_image(&mut image, &scan.marks(), args)?;
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None => output_path(args, photo, Some("solved")),
+    };
+    image.save(&output)?;
+    println!("Saved the solution to {:?}", output);
+    Ok(())
+}
 
-        let observed: Vec<_> = column_iter(100, 10, 3, 5).collect();
-        let expected = vec![(0, 0), (0, 10), (33, 0), (33, 10), (66, 0)];
-        assert_eq!(expected, observed);
+/// The extensions of the puzzle files verify-dir checks.
+const PUZZLE_EXTENSIONS: [&str; 3] = ["ipuz", "json", "wsproj"];
 
-        let observed: Vec<_> = column_iter(100, 10, 3, 6).collect();
-        let expected = vec![(0, 0), (0, 10), (33, 0), (33, 10), (66, 0), (66, 10)];
-        assert_eq!(expected, observed);
+/// Check every puzzle file in the directory, a few at a time, and report which ones passed. Fail if any didn't.
+fn verify_dir(dir: &Path, jobs: Option<usize>) -> Result<(), Error> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if path.is_file() && PUZZLE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        return Err(anyhow!("No puzzles in {:?}", dir));
+    }
+    files.sort();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
+    let results: Vec<_> = pool.install(|| files.par_iter().map(|f| verify_puzzle(f)).collect());
+    let mut failed = 0;
+    for (file, result) in files.iter().zip(results) {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        match result {
+            Ok(()) => println!("PASS {}", name),
+            Err(e) => {
+                println!("FAIL {}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    println!("{} of {} puzzles passed", files.len() - failed, files.len());
+    if failed > 0 {
+        return Err(anyhow!("{} of {} puzzles failed", failed, files.len()));
+    }
+    Ok(())
+}
+
+/// Load one puzzle, and make sure each of its words is hidden in the grid, and can't be found more often than it
+/// was hidden.
+fn verify_puzzle(file: &Path) -> Result<(), Error> {
+    let (words, puzzle) = read_puzzle(file)?;
+    let (mut missing, mut repeated) = (vec![], vec![]);
+    for (entry, word) in words.iter().zip(grid_words(&words)) {
+        let hidden = puzzle.placements.iter().filter(|p| p.word == word).count();
+        match puzzle.find_all(&word).len() {
+            0 => missing.push(entry.word.as_str()),
+            found if found > hidden.max(1) => repeated.push(entry.word.as_str()),
+            _ => (),
+        }
+    }
+    let mut problems = vec![];
+    if !missing.is_empty() {
+        problems.push(format!("not in the grid: {}", missing.join(", ")));
+    }
+    if !repeated.is_empty() {
+        problems.push(format!("found more than once: {}", repeated.join(", ")));
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!("{}", problems.join("; ")));
+    }
+    Ok(())
+}
 
-        Ok(())
+/// Read a puzzle saved with --save-project (.wsproj), or else in ipuz format. Return its words and the puzzle.
+fn read_puzzle(file: &Path) -> Result<(Vec<Entry>, Puzzle), Error> {
+    match file.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("wsproj") => {
+            let project = project::read_project(file)?;
+            Ok((project.words, project.puzzle))
+        }
+        _ => {
+            let (words, puzzle) = import::read_ipuz(file)?;
+            Ok((words.into_iter().map(Entry::new).collect(), puzzle))
+        }
     }
 }
+
+/// Make sure every word in the list was found in the puzzle.
+fn verify_placements(words: &[Entry], puzzle: &Puzzle) -> Result<(), Error> {
+    let missing: Vec<_> = words
+        .iter()
+        .zip(grid_words(words))
+        .filter(|(_, w)| !puzzle.placements.iter().any(|p| p.word == *w))
+        .map(|(w, _)| w.word.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!("Words not found in grid: {}", missing.join(", ")));
+    }
+    Ok(())
+}
@@ -0,0 +1,573 @@
+use std::fmt::Write as _;
+use std::io::{Seek, Write};
+
+use anyhow::Error;
+use image::Rgb;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config::Args;
+use crate::grid::Puzzle;
+use crate::page::{escape, hex};
+use crate::render;
+use crate::wordlist::Entry;
+
+/// The widest the grid can be, in twentieths of a point: the width of a letter-size page inside one-inch margins.
+const GRID_WIDTH: usize = 9360;
+
+/// The largest a cell of the grid can be, in twentieths of a point: about a centimeter.
+const MAX_CELL: usize = 567;
+
+/// How many columns the key is set in.
+const KEY_COLUMNS: usize = 3;
+
+/// How tall a row of the grid is in a spreadsheet, in points.
+const SHEET_ROW_HEIGHT: f32 = 24.0;
+
+/// How wide a column of the grid is in a spreadsheet, in widths of a digit of the default font, to make the cells
+/// square: a digit is 7 pixels wide, with 5 more for padding, and a point is 4/3 of a pixel.
+const SHEET_COLUMN_WIDTH: f32 = (SHEET_ROW_HEIGHT * 4.0 / 3.0 - 5.0) / 7.0;
+
+/// How a cell of the grid looks.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    /// One of the letters of an answer, in a solution.
+    Answer,
+    /// A box for the solver to write a letter in, in a criss-cross puzzle.
+    Box,
+}
+
+/// The text and style of each cell of the grid, by row.
+fn cells(puzzle: &Puzzle, args: &Args, solution: bool) -> Vec<Vec<(String, Style)>> {
+    puzzle
+        .grid
+        .iter()
+        .enumerate()
+        .map(|(y, line)| {
+            line.iter()
+                .enumerate()
+                .map(|(x, letter)| {
+                    let answer = solution
+                        && puzzle
+                            .placements
+                            .iter()
+                            .any(|p| p.cells().any(|c| c == (x, y)));
+                    match letter {
+                        Some(_) if args.criss_cross && !solution => (String::new(), Style::Box),
+                        Some(letter) if args.criss_cross => (letter.to_string(), Style::Box),
+                        Some(letter) if answer => (letter.to_string(), Style::Answer),
+                        Some(letter) => (letter.to_string(), Style::Plain),
+                        None => (String::new(), Style::Plain),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The size of a cell of the grid, in twentieths of a point, so the grid fits across the page.
+fn cell_size(puzzle: &Puzzle) -> usize {
+    let columns = puzzle.grid[0].len().max(puzzle.grid.len());
+    (GRID_WIDTH / columns).min(MAX_CELL)
+}
+
+/// Write the puzzle as a Word document, with the grid as a table and the key in columns below it, so it can be
+/// changed in an office suite. With `solution`, the letters of the answers are shaded.
+pub fn write_docx<W: Write + Seek>(
+    out: W,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), Error> {
+    let mut doc = String::new();
+    writeln!(
+        doc,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(
+        doc,
+        r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>"#
+    )?;
+
+    if let Some(title) = &args.title {
+        writeln!(
+            doc,
+            r#"<w:p><w:pPr><w:jc w:val="center"/></w:pPr><w:r><w:rPr><w:b/></w:rPr><w:t xml:space="preserve">{}</w:t></w:r></w:p>"#,
+            escape(title)
+        )?;
+    }
+    if args.worksheet_header || student.is_some() {
+        let (name, date) = render::header_text(student);
+        writeln!(
+            doc,
+            r#"<w:p><w:pPr><w:tabs><w:tab w:val="right" w:pos="{GRID_WIDTH}"/></w:tabs></w:pPr><w:r><w:t xml:space="preserve">{}</w:t></w:r><w:r><w:tab/><w:t xml:space="preserve">{}</w:t></w:r></w:p>"#,
+            escape(&name),
+            escape(date)
+        )?;
+    }
+
+    let cell = cell_size(puzzle);
+    // Font sizes are in half points, and a letter takes up a little more than half its cell.
+    let font_size = cell * 6 / 100;
+    writeln!(
+        doc,
+        r#"<w:tbl><w:tblPr><w:jc w:val="center"/><w:tblLayout w:type="fixed"/><w:tblCellMar><w:left w:w="0" w:type="dxa"/><w:right w:w="0" w:type="dxa"/></w:tblCellMar></w:tblPr><w:tblGrid>"#
+    )?;
+    for _ in &puzzle.grid[0] {
+        write!(doc, r#"<w:gridCol w:w="{cell}"/>"#)?;
+    }
+    writeln!(doc, "</w:tblGrid>")?;
+    for line in cells(puzzle, args, solution) {
+        write!(
+            doc,
+            r#"<w:tr><w:trPr><w:trHeight w:val="{cell}" w:hRule="exact"/></w:trPr>"#
+        )?;
+        for (text, style) in line {
+            write!(
+                doc,
+                r#"<w:tc><w:tcPr><w:tcW w:w="{cell}" w:type="dxa"/><w:vAlign w:val="center"/>"#
+            )?;
+            match style {
+                Style::Plain => (),
+                Style::Answer => write!(
+                    doc,
+                    r#"<w:shd w:val="clear" w:color="auto" w:fill="{}"/>"#,
+                    hex(tint(args.mark_color()))
+                )?,
+                Style::Box => write!(
+                    doc,
+                    r#"<w:tcBorders><w:top w:val="single" w:sz="4"/><w:left w:val="single" w:sz="4"/><w:bottom w:val="single" w:sz="4"/><w:right w:val="single" w:sz="4"/></w:tcBorders>"#
+                )?,
+            }
+            writeln!(
+                doc,
+                r#"</w:tcPr><w:p><w:pPr><w:spacing w:before="0" w:after="0"/><w:jc w:val="center"/></w:pPr><w:r><w:rPr><w:sz w:val="{font_size}"/></w:rPr><w:t>{}</w:t></w:r></w:p></w:tc>"#,
+                escape(&text)
+            )?;
+        }
+        writeln!(doc, "</w:tr>")?;
+    }
+    writeln!(doc, "</w:tbl>")?;
+
+    // The grid ends a one-column section, and the key is in a section of its own, with columns.
+    writeln!(
+        doc,
+        r#"<w:p><w:pPr><w:sectPr><w:type w:val="continuous"/><w:pgSz w:w="12240" w:h="15840"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="720" w:footer="720" w:gutter="0"/></w:sectPr></w:pPr></w:p>"#
+    )?;
+    let mut category = None;
+    for entry in wordlist {
+        if entry.category != category {
+            category = entry.category.clone();
+            if let Some(heading) = &category {
+                writeln!(
+                    doc,
+                    r#"<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>{}</w:t></w:r></w:p>"#,
+                    escape(heading)
+                )?;
+            }
+        }
+        writeln!(
+            doc,
+            r#"<w:p><w:r><w:t>{}</w:t></w:r></w:p>"#,
+            escape(&entry.word)
+        )?;
+    }
+    for line in footer(wordlist, args) {
+        writeln!(
+            doc,
+            r#"<w:p><w:r><w:t xml:space="preserve">{}</w:t></w:r></w:p>"#,
+            escape(&line)
+        )?;
+    }
+    writeln!(
+        doc,
+        r#"<w:sectPr><w:type w:val="continuous"/><w:pgSz w:w="12240" w:h="15840"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="720" w:footer="720" w:gutter="0"/><w:cols w:num="{KEY_COLUMNS}" w:space="720"/></w:sectPr>"#
+    )?;
+    writeln!(doc, "</w:body></w:document>")?;
+
+    let mut zip = ZipWriter::new(out);
+    let options = SimpleFileOptions::default();
+    zip.start_file("[Content_Types].xml", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/></Types>
+"#,
+    )?;
+    zip.start_file("_rels/.rels", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>
+"#,
+    )?;
+    zip.start_file("word/document.xml", options)?;
+    zip.write_all(doc.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Write the puzzle as an OpenDocument text document, laid out the same way as the Word document.
+pub fn write_odt<W: Write + Seek>(
+    out: W,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), Error> {
+    // Sizes are given in points, from twentieths of a point.
+    let cell = cell_size(puzzle) as f32 / 20.0;
+    let mut doc = String::new();
+    writeln!(doc, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        doc,
+        r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2">"#
+    )?;
+    writeln!(doc, "<office:automatic-styles>")?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Header" style:family="paragraph"><style:paragraph-properties><style:tab-stops><style:tab-stop style:position="{}pt" style:type="right"/></style:tab-stops></style:paragraph-properties></style:style>"#,
+        GRID_WIDTH / 20
+    )?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Title" style:family="paragraph"><style:paragraph-properties fo:text-align="center"/><style:text-properties fo:font-weight="bold"/></style:style>"#
+    )?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Grid" style:family="table"><style:table-properties style:width="{}pt" table:align="center"/></style:style>"#,
+        cell * puzzle.grid[0].len() as f32
+    )?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Column" style:family="table-column"><style:table-column-properties style:column-width="{cell}pt"/></style:style>"#
+    )?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Row" style:family="table-row"><style:table-row-properties style:row-height="{cell}pt"/></style:style>"#
+    )?;
+    for (name, properties) in [
+        ("Plain", String::new()),
+        (
+            "Answer",
+            format!(
+                r#" fo:background-color="{}""#,
+                css_hex(tint(args.mark_color()))
+            ),
+        ),
+        ("Box", r#" fo:border="0.5pt solid #000000""#.to_string()),
+    ] {
+        writeln!(
+            doc,
+            r#"<style:style style:name="{name}" style:family="table-cell"><style:table-cell-properties style:vertical-align="middle" fo:padding="0pt"{properties}/></style:style>"#
+        )?;
+    }
+    writeln!(
+        doc,
+        r#"<style:style style:name="Letter" style:family="paragraph"><style:paragraph-properties fo:text-align="center"/><style:text-properties fo:font-size="{}pt"/></style:style>"#,
+        cell * 0.6
+    )?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Key" style:family="section"><style:section-properties><style:columns fo:column-count="{KEY_COLUMNS}" fo:column-gap="36pt"/></style:section-properties></style:style>"#
+    )?;
+    writeln!(
+        doc,
+        r#"<style:style style:name="Heading" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>"#
+    )?;
+    writeln!(doc, "</office:automatic-styles>")?;
+    writeln!(doc, "<office:body><office:text>")?;
+
+    if let Some(title) = &args.title {
+        writeln!(
+            doc,
+            r#"<text:p text:style-name="Title">{}</text:p>"#,
+            escape(title)
+        )?;
+    }
+    if args.worksheet_header || student.is_some() {
+        let (name, date) = render::header_text(student);
+        writeln!(
+            doc,
+            r#"<text:p text:style-name="Header">{}<text:tab/>{}</text:p>"#,
+            escape(&name),
+            escape(date)
+        )?;
+    }
+
+    writeln!(
+        doc,
+        r#"<table:table table:name="Grid" table:style-name="Grid"><table:table-column table:style-name="Column" table:number-columns-repeated="{}"/>"#,
+        puzzle.grid[0].len()
+    )?;
+    for line in cells(puzzle, args, solution) {
+        write!(doc, r#"<table:table-row table:style-name="Row">"#)?;
+        for (text, style) in line {
+            let style = match style {
+                Style::Plain => "Plain",
+                Style::Answer => "Answer",
+                Style::Box => "Box",
+            };
+            write!(
+                doc,
+                r#"<table:table-cell table:style-name="{style}" office:value-type="string"><text:p text:style-name="Letter">{}</text:p></table:table-cell>"#,
+                escape(&text)
+            )?;
+        }
+        writeln!(doc, "</table:table-row>")?;
+    }
+    writeln!(doc, "</table:table>")?;
+
+    writeln!(
+        doc,
+        r#"<text:section text:style-name="Key" text:name="Key">"#
+    )?;
+    let mut category = None;
+    for entry in wordlist {
+        if entry.category != category {
+            category = entry.category.clone();
+            if let Some(heading) = &category {
+                writeln!(
+                    doc,
+                    r#"<text:p><text:span text:style-name="Heading">{}</text:span></text:p>"#,
+                    escape(heading)
+                )?;
+            }
+        }
+        writeln!(doc, "<text:p>{}</text:p>", escape(&entry.word))?;
+    }
+    writeln!(doc, "</text:section>")?;
+    for line in footer(wordlist, args) {
+        writeln!(doc, "<text:p>{}</text:p>", escape(&line))?;
+    }
+    writeln!(
+        doc,
+        "</office:text></office:body></office:document-content>"
+    )?;
+
+    let mut zip = ZipWriter::new(out);
+    // The type has to come first, and uncompressed, so the file can be recognized from its first bytes.
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/vnd.oasis.opendocument.text")?;
+    let options = SimpleFileOptions::default();
+    zip.start_file("META-INF/manifest.xml", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2"><manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.text"/><manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/></manifest:manifest>
+"#,
+    )?;
+    zip.start_file("content.xml", options)?;
+    zip.write_all(doc.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Write the puzzle as an Excel workbook, with one letter to a cell of square columns on the first sheet, and the
+/// key on a second sheet. With `solution`, the letters of the answers are shaded.
+pub fn write_xlsx<W: Write + Seek>(
+    out: W,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), Error> {
+    let width = puzzle.grid[0].len();
+    let mut grid = String::new();
+    writeln!(
+        grid,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(
+        grid,
+        r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#
+    )?;
+    writeln!(
+        grid,
+        r#"<sheetViews><sheetView workbookViewId="0" showGridLines="0"/></sheetViews>"#
+    )?;
+    writeln!(
+        grid,
+        r#"<sheetFormatPr defaultRowHeight="{SHEET_ROW_HEIGHT}" customHeight="1"/><cols><col min="1" max="{width}" width="{SHEET_COLUMN_WIDTH:.2}" customWidth="1"/></cols><sheetData>"#
+    )?;
+    for (y, line) in cells(puzzle, args, solution).into_iter().enumerate() {
+        write!(
+            grid,
+            r#"<row r="{}" ht="{SHEET_ROW_HEIGHT}" customHeight="1">"#,
+            y + 1
+        )?;
+        for (x, (text, style)) in line.into_iter().enumerate() {
+            // The styles are numbered in the order they're listed in styles.xml.
+            let style = match style {
+                Style::Plain => 1,
+                Style::Answer => 2,
+                Style::Box => 3,
+            };
+            write!(
+                grid,
+                r#"<c r="{}{}" s="{style}" t="inlineStr"><is><t>{}</t></is></c>"#,
+                column_name(x),
+                y + 1,
+                escape(&text)
+            )?;
+        }
+        writeln!(grid, "</row>")?;
+    }
+    writeln!(grid, "</sheetData>")?;
+    writeln!(grid, r#"<printOptions horizontalCentered="1"/>"#)?;
+    // The name and date go in the page header, on the left and the right, with the title in the middle in bold.
+    let mut header = String::new();
+    if args.worksheet_header || student.is_some() {
+        let (name, date) = render::header_text(student);
+        header += &format!("&L{}&R{}", name.replace('&', "&&"), date);
+    }
+    if let Some(title) = &args.title {
+        header += &format!("&C&B{}", title.replace('&', "&&"));
+    }
+    if !header.is_empty() {
+        writeln!(
+            grid,
+            "<headerFooter><oddHeader>{}</oddHeader></headerFooter>",
+            escape(&header)
+        )?;
+    }
+    writeln!(grid, "</worksheet>")?;
+
+    let mut key = String::new();
+    writeln!(
+        key,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(
+        key,
+        r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><cols><col min="1" max="1" width="30" customWidth="1"/></cols><sheetData>"#
+    )?;
+    let mut lines = vec![];
+    let mut category = None;
+    for entry in wordlist {
+        if entry.category.as_deref() != category {
+            category = entry.category.as_deref();
+            if let Some(heading) = category {
+                lines.push((heading, 4));
+            }
+        }
+        lines.push((entry.word.as_str(), 0));
+    }
+    let footer = footer(wordlist, args);
+    lines.extend(footer.iter().map(|line| (line.as_str(), 0)));
+    for (y, (text, style)) in lines.into_iter().enumerate() {
+        writeln!(
+            key,
+            r#"<row r="{row}"><c r="A{row}" s="{style}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c></row>"#,
+            escape(text),
+            row = y + 1
+        )?;
+    }
+    writeln!(key, "</sheetData></worksheet>")?;
+
+    let mut styles = String::new();
+    writeln!(
+        styles,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(
+        styles,
+        r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#
+    )?;
+    writeln!(
+        styles,
+        r#"<fonts count="3"><font><sz val="11"/><name val="Calibri"/></font><font><sz val="{}"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts>"#,
+        SHEET_ROW_HEIGHT * 0.6
+    )?;
+    writeln!(
+        styles,
+        r#"<fills count="3"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill><fill><patternFill patternType="solid"><fgColor rgb="FF{}"/></patternFill></fill></fills>"#,
+        hex(tint(args.mark_color()))
+    )?;
+    writeln!(
+        styles,
+        r#"<borders count="2"><border/><border><left style="thin"/><right style="thin"/><top style="thin"/><bottom style="thin"/></border></borders>"#
+    )?;
+    writeln!(
+        styles,
+        r#"<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>"#
+    )?;
+    let centered = r#"<alignment horizontal="center" vertical="center"/>"#;
+    writeln!(
+        styles,
+        r#"<cellXfs count="5"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1" applyAlignment="1">{centered}</xf><xf numFmtId="0" fontId="1" fillId="2" borderId="0" xfId="0" applyFont="1" applyFill="1" applyAlignment="1">{centered}</xf><xf numFmtId="0" fontId="1" fillId="0" borderId="1" xfId="0" applyFont="1" applyBorder="1" applyAlignment="1">{centered}</xf><xf numFmtId="0" fontId="2" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs>"#
+    )?;
+    writeln!(styles, "</styleSheet>")?;
+
+    let mut zip = ZipWriter::new(out);
+    let options = SimpleFileOptions::default();
+    zip.start_file("[Content_Types].xml", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/worksheets/sheet2.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>
+"#,
+    )?;
+    zip.start_file("_rels/.rels", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>
+"#,
+    )?;
+    zip.start_file("xl/workbook.xml", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Puzzle" sheetId="1" r:id="rId1"/><sheet name="Words" sheetId="2" r:id="rId2"/></sheets></workbook>
+"#,
+    )?;
+    zip.start_file("xl/_rels/workbook.xml.rels", options)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet2.xml"/><Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>
+"#,
+    )?;
+    zip.start_file("xl/worksheets/sheet1.xml", options)?;
+    zip.write_all(grid.as_bytes())?;
+    zip.start_file("xl/worksheets/sheet2.xml", options)?;
+    zip.write_all(key.as_bytes())?;
+    zip.start_file("xl/styles.xml", options)?;
+    zip.write_all(styles.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Return the name a spreadsheet gives a column, counting from 0: A to Z, then AA, AB, and so on.
+fn column_name(mut x: usize) -> String {
+    let mut name = vec![];
+    loop {
+        name.push(b'A' + (x % 26) as u8);
+        if x < 26 {
+            break;
+        }
+        x = x / 26 - 1;
+    }
+    name.iter().rev().map(|&c| c as char).collect()
+}
+
+/// The lines under the key: the score box, and the puzzle's code and answer checksum, if they're wanted.
+fn footer(wordlist: &[Entry], args: &Args) -> Vec<String> {
+    let mut lines = vec![];
+    if args.score_box {
+        lines.push(render::score_text(wordlist.len()));
+    }
+    lines.extend(args.footer());
+    lines
+}
+
+/// Lighten the mark color, so the letters can still be read on it.
+fn tint(color: Rgb<u8>) -> Rgb<u8> {
+    Rgb(color.0.map(|c| 255 - (255 - c) / 3))
+}
+
+fn css_hex(color: Rgb<u8>) -> String {
+    format!("#{}", hex(color))
+}
@@ -0,0 +1,541 @@
+//! What goes on a page, and where. The layout is worked out here once, and each kind of image implements [`Renderer`]
+//! to draw the pieces, so they all put everything in the same place.
+
+use image::Rgb;
+use rusttype::Scale;
+
+use crate::config::{Args, CellShading, FrameStyle, MarkStyle};
+use crate::error::WordSearchError;
+use crate::grid::{Placement, Puzzle};
+use crate::key::{self, Line};
+use crate::render::{self, FramePart, Layout, TEXT};
+use crate::typeset::{self, Font};
+use crate::wordlist::Entry;
+
+/// The color of shaded cells.
+const SHADING: Rgb<u8> = Rgb([235, 235, 235]);
+
+/// The color of shaded cells with --theme high-contrast.
+const DARK_SHADING: Rgb<u8> = Rgb([200, 200, 200]);
+
+/// Where a line of text goes across from its `x`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    /// It starts there.
+    Left,
+    /// It's centered in a space this wide that starts there.
+    Center(i32),
+    /// It ends there.
+    Right,
+}
+
+/// A line of text, with the top of its line at `y`.
+pub struct Text<'a> {
+    pub x: i32,
+    pub y: i32,
+    pub align: Align,
+    pub scale: Scale,
+    pub text: &'a str,
+    pub bold: bool,
+    /// The color to draw the text in, if it isn't the usual black.
+    pub color: Option<Rgb<u8>>,
+}
+
+/// A rectangle, filled or outlined, with its edges on the lines from (x, y) to (x + width, y + height).
+pub struct Rectangle {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub fill: Option<Rgb<u8>>,
+    pub stroke: Option<Rgb<u8>>,
+    /// How thick the outline is, in pixels.
+    pub line_width: i32,
+}
+
+/// Where to mark an answer: the middles of its first and last letters, and of every letter.
+pub struct Mark {
+    pub start: (f32, f32),
+    pub end: (f32, f32),
+    pub letters: Vec<(f32, f32)>,
+    /// The size of a cell in the grid, which the marks are drawn in proportion to.
+    pub stride: f32,
+}
+
+/// A part of the page that some kinds of image keep together, so it can be found or hidden.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Group {
+    Grid,
+    Coordinates,
+    /// The answers, which are only shown in a solution.
+    Solution {
+        shown: bool,
+    },
+    Key,
+    Frame,
+}
+
+/// A kind of image that pages can be drawn as.
+pub trait Renderer {
+    fn text(&mut self, text: &Text) -> Result<(), WordSearchError>;
+    fn rectangle(&mut self, rectangle: &Rectangle) -> Result<(), WordSearchError>;
+    fn frame_part(&mut self, part: &FramePart) -> Result<(), WordSearchError>;
+    /// Mark where the answers are. They're all marked at once, so where marks overlap they can be blended together.
+    fn marks(
+        &mut self,
+        marks: &[Mark],
+        style: MarkStyle,
+        color: Rgb<u8>,
+        opacity: f32,
+    ) -> Result<(), WordSearchError>;
+    fn begin(&mut self, group: Group) -> Result<(), WordSearchError>;
+    fn end(&mut self, group: Group) -> Result<(), WordSearchError>;
+}
+
+/// Draw the whole page: the grid with the key below it. If `solution` is set, the answers are shown.
+pub fn draw_page<R: Renderer>(
+    renderer: &mut R,
+    font: &Font,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), WordSearchError> {
+    let (width, height) = (args.image_width, args.image_height);
+    let mut top = 0;
+    if let Some(title) = &args.title {
+        top = draw_title(renderer, font, title, width, height)?;
+    }
+    if args.worksheet_header || student.is_some() {
+        top += draw_worksheet_header(
+            renderer,
+            font,
+            student,
+            top,
+            width,
+            height,
+            args.high_contrast(),
+        )?;
+    }
+
+    let layout = Layout::new(font, wordlist, puzzle, args, top)?;
+    draw_grid(renderer, font, puzzle, &layout, args, solution)?;
+    let key_top = layout.key_top(puzzle.grid.len());
+    let pages = render::key_pages(wordlist, &layout, args);
+    draw_key(renderer, pages[0], &layout, key_top, width)?;
+
+    if args.score_box {
+        draw_score_box(renderer, font, &layout, wordlist.len(), width, height)?;
+    }
+    if let Some(footer) = args.footer() {
+        draw_code(renderer, font, &layout, &footer, height)?;
+    }
+    Ok(())
+}
+
+/// Draw a page the key continues onto.
+pub fn draw_continued_page<R: Renderer>(
+    renderer: &mut R,
+    page: &[Entry],
+    layout: &Layout,
+    width: u32,
+) -> Result<(), WordSearchError> {
+    renderer.text(&Text {
+        x: 0,
+        y: 0,
+        align: Align::Left,
+        scale: layout.key_scale,
+        text: render::CONTINUED,
+        bold: layout.bold,
+        color: None,
+    })?;
+    draw_key(renderer, page, layout, 2 * layout.key_stride, width)
+}
+
+/// Draw the grid of letters, with any shading behind it, a frame around it, and its coordinate labels. The answers
+/// are shown if `solution` is set.
+pub fn draw_grid<R: Renderer>(
+    renderer: &mut R,
+    font: &Font,
+    puzzle: &Puzzle,
+    layout: &Layout,
+    args: &Args,
+    solution: bool,
+) -> Result<(), WordSearchError> {
+    let grid = &puzzle.grid;
+    let (top, left, stride) = (layout.top, layout.left, layout.grid_stride);
+
+    if let Some(shading) = args.cell_shading {
+        for (y, line) in grid.iter().enumerate() {
+            for x in 0..line.len() {
+                let shaded = match shading {
+                    CellShading::Checkerboard => (x + y) % 2 == 1,
+                    CellShading::Rows => y % 2 == 1,
+                };
+                if shaded {
+                    renderer.rectangle(&Rectangle {
+                        x: left + x as i32 * stride,
+                        y: top + y as i32 * stride,
+                        width: stride,
+                        height: stride,
+                        fill: Some(match args.high_contrast() {
+                            true => DARK_SHADING,
+                            false => SHADING,
+                        }),
+                        stroke: None,
+                        line_width: layout.line_width,
+                    })?;
+                }
+            }
+        }
+    }
+
+    let letters: Vec<Cell> = grid
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.iter().enumerate().filter_map(move |(x, letter)| {
+                let color = layout.letter_colors.as_ref().map(|colors| colors[y][x]);
+                let (x, y) = (left + x as i32 * stride, top + y as i32 * stride);
+                letter.map(|letter| (x, y, letter.to_string(), color))
+            })
+        })
+        .collect();
+    renderer.begin(Group::Grid)?;
+    for cell in &letters {
+        if args.criss_cross {
+            // An empty box for the solver to write the letter in. Neighboring boxes share their edges.
+            renderer.rectangle(&Rectangle {
+                x: cell.0,
+                y: cell.1,
+                width: stride,
+                height: stride,
+                fill: None,
+                stroke: Some(TEXT),
+                line_width: layout.line_width,
+            })?;
+        } else {
+            renderer.text(&letter(cell, layout))?;
+        }
+    }
+    renderer.end(Group::Grid)?;
+
+    if let (Some(style), Some(rect)) = (args.frame, layout.grid_frame(grid)) {
+        draw_frame(renderer, style, rect, args.frame_thickness as i32)?;
+    }
+
+    if layout.coordinates {
+        let label_top = (stride / 2 - render::letter_middle(font, layout.key_scale)).max(0);
+        renderer.begin(Group::Coordinates)?;
+        for (x, y, label) in render::coordinate_labels(grid, layout) {
+            renderer.text(&Text {
+                x,
+                y: y + label_top,
+                align: Align::Center(stride),
+                scale: layout.key_scale,
+                text: &label,
+                bold: layout.bold,
+                color: None,
+            })?;
+        }
+        renderer.end(Group::Coordinates)?;
+    }
+
+    let group = Group::Solution { shown: solution };
+    renderer.begin(group)?;
+    if args.criss_cross {
+        // The answers to a criss-cross puzzle are the letters in the boxes.
+        for cell in &letters {
+            renderer.text(&letter(cell, layout))?;
+        }
+    } else {
+        let marks: Vec<Mark> = puzzle.placements.iter().map(|p| mark(p, layout)).collect();
+        renderer.marks(
+            &marks,
+            args.mark_style,
+            args.mark_color(),
+            args.mark_opacity(),
+        )?;
+    }
+    renderer.end(group)
+}
+
+/// A letter of the grid: where the top left corner of its cell is, and its color, if it has one.
+type Cell = (i32, i32, String, Option<Rgb<u8>>);
+
+/// Return a letter of the grid, centered in its cell.
+fn letter<'a>((x, y, letter, color): &'a Cell, layout: &Layout) -> Text<'a> {
+    Text {
+        x: *x,
+        y: y + layout.letter_top,
+        align: Align::Center(layout.grid_stride),
+        scale: layout.scale,
+        text: letter,
+        bold: layout.bold,
+        color: *color,
+    }
+}
+
+/// Return where to mark a word in the grid.
+fn mark(placement: &Placement, layout: &Layout) -> Mark {
+    let stride = layout.grid_stride;
+    let center = |(x, y): (usize, usize)| {
+        (
+            (layout.left + x as i32 * stride + stride / 2) as f32,
+            (layout.top + y as i32 * stride + layout.middle) as f32,
+        )
+    };
+    Mark {
+        start: center((placement.x, placement.y)),
+        end: center(placement.end()),
+        letters: placement.cells().map(center).collect(),
+        stride: stride as f32,
+    }
+}
+
+/// Draw the key: the list of words hidden in the puzzle, in columns across the page starting at `key_top`, with any
+/// category headings in bold.
+pub fn draw_key<R: Renderer>(
+    renderer: &mut R,
+    wordlist: &[Entry],
+    layout: &Layout,
+    key_top: i32,
+    width: u32,
+) -> Result<(), WordSearchError> {
+    let col_width = (width / layout.key_columns as u32) as i32;
+    renderer.begin(Group::Key)?;
+    for (column, lines) in key::columns(wordlist, layout.key_columns)
+        .iter()
+        .enumerate()
+    {
+        for (row, Line { text, heading }) in lines.iter().enumerate() {
+            renderer.text(&Text {
+                x: column as i32 * col_width,
+                y: key_top + row as i32 * layout.key_stride,
+                align: Align::Left,
+                scale: layout.key_scale,
+                text,
+                bold: *heading || layout.bold,
+                color: None,
+            })?;
+        }
+    }
+    renderer.end(Group::Key)
+}
+
+/// Draw a frame in this style, with its outside edge on the rectangle at (x, y) with this width and height.
+pub fn draw_frame<R: Renderer>(
+    renderer: &mut R,
+    style: FrameStyle,
+    rect: (i32, i32, i32, i32),
+    thickness: i32,
+) -> Result<(), WordSearchError> {
+    renderer.begin(Group::Frame)?;
+    for part in render::frame_parts(style, rect, thickness) {
+        renderer.frame_part(&part)?;
+    }
+    renderer.end(Group::Frame)
+}
+
+/// Draw the title in bold across the top of the page, and return how much room it takes.
+fn draw_title<R: Renderer>(
+    renderer: &mut R,
+    font: &Font,
+    title: &str,
+    width: u32,
+    height: u32,
+) -> Result<i32, WordSearchError> {
+    let scale = render::header_scale(height);
+    renderer.text(&Text {
+        x: 0,
+        y: 0,
+        align: Align::Center(width as i32),
+        scale,
+        text: title,
+        bold: true,
+        color: None,
+    })?;
+    Ok(render::header_height(font, scale))
+}
+
+/// Draw the lines for the solver's name and the date at `top`, and return how much room they take.
+fn draw_worksheet_header<R: Renderer>(
+    renderer: &mut R,
+    font: &Font,
+    student: Option<&str>,
+    top: i32,
+    width: u32,
+    height: u32,
+    bold: bool,
+) -> Result<i32, WordSearchError> {
+    let scale = render::header_scale(height);
+    let (name, date) = render::header_text(student);
+    for (x, align, text) in [
+        (0, Align::Left, name.as_str()),
+        (width as i32, Align::Right, date),
+    ] {
+        renderer.text(&Text {
+            x,
+            y: top,
+            align,
+            scale,
+            text,
+            bold,
+            color: None,
+        })?;
+    }
+    Ok(render::header_height(font, scale))
+}
+
+/// Draw a box in the bottom right corner of the page for the solver to fill in their time and how many words they
+/// found.
+fn draw_score_box<R: Renderer>(
+    renderer: &mut R,
+    font: &Font,
+    layout: &Layout,
+    num_words: usize,
+    width: u32,
+    height: u32,
+) -> Result<(), WordSearchError> {
+    let scale = layout.key_scale;
+    let text = render::score_text(num_words);
+    let (text_width, text_height) = typeset::size(scale, font, &text);
+    let margin = text_height / 2;
+    let (box_width, box_height) = (text_width + 2 * margin, text_height + 2 * margin);
+    let x0 = width as i32 - box_width - margin;
+    let y0 = height as i32 - box_height - margin;
+    renderer.rectangle(&Rectangle {
+        x: x0,
+        y: y0,
+        width: box_width,
+        height: box_height,
+        fill: None,
+        stroke: Some(TEXT),
+        line_width: layout.line_width,
+    })?;
+    renderer.text(&Text {
+        x: x0 + margin,
+        y: y0 + margin,
+        align: Align::Left,
+        scale,
+        text: &text,
+        bold: layout.bold,
+        color: None,
+    })
+}
+
+/// Write the puzzle's code and answer checksum in the bottom left corner of the page, in the same place as the score
+/// box on the other side.
+fn draw_code<R: Renderer>(
+    renderer: &mut R,
+    font: &Font,
+    layout: &Layout,
+    code: &str,
+    height: u32,
+) -> Result<(), WordSearchError> {
+    let scale = layout.key_scale;
+    let (_, text_height) = typeset::size(scale, font, code);
+    renderer.text(&Text {
+        x: text_height,
+        y: height as i32 - 2 * text_height,
+        align: Align::Left,
+        scale,
+        text: code,
+        bold: layout.bold,
+        color: None,
+    })
+}
+
+/// Return the text with the characters that mean something in XML and HTML written as entities, for the image and
+/// document formats written as markup.
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Return the color as six hex digits, without the leading `#`.
+pub fn hex(color: Rgb<u8>) -> String {
+    let Rgb([r, g, b]) = color;
+    format!("{:02X}{:02X}{:02X}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+    use clap::Parser;
+    use image::Rgb;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::config::{Args, MarkStyle};
+    use crate::error::WordSearchError;
+    use crate::grid::Grid;
+    use crate::render::{self, FramePart, Layout};
+
+    use super::{draw_grid, Align, Group, Mark, Rectangle, Renderer, Text};
+
+    /// Remembers the letters drawn, and the groups they're in.
+    #[derive(Default)]
+    struct Recorder {
+        letters: Vec<(i32, i32, String, Option<Group>)>,
+        marks: usize,
+        group: Option<Group>,
+    }
+
+    impl Renderer for Recorder {
+        fn text(&mut self, text: &Text) -> Result<(), WordSearchError> {
+            assert!(matches!(text.align, Align::Center(_)));
+            let letter = text.text.to_string();
+            self.letters.push((text.x, text.y, letter, self.group));
+            Ok(())
+        }
+        fn rectangle(&mut self, _: &Rectangle) -> Result<(), WordSearchError> {
+            Ok(())
+        }
+        fn frame_part(&mut self, _: &FramePart) -> Result<(), WordSearchError> {
+            Ok(())
+        }
+        fn marks(
+            &mut self,
+            marks: &[Mark],
+            _: MarkStyle,
+            _: Rgb<u8>,
+            _: f32,
+        ) -> Result<(), WordSearchError> {
+            self.marks += marks.len();
+            Ok(())
+        }
+        fn begin(&mut self, group: Group) -> Result<(), WordSearchError> {
+            self.group = Some(group);
+            Ok(())
+        }
+        fn end(&mut self, _: Group) -> Result<(), WordSearchError> {
+            self.group = None;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_draw_grid() -> Result<(), Error> {
+        let words = vec!["BISHOP".to_string(), "CASTLE".to_string()];
+        let puzzle = Grid::new(words, Some(8), Some(8)).generate(&mut StdRng::seed_from_u64(1))?;
+        let args = Args::try_parse_from(["wordsearch"])?;
+        let font = render::load_font(&args)?;
+        let layout = Layout::new(&font, &[], &puzzle, &args, 0)?;
+        let mut recorder = Recorder::default();
+        draw_grid(&mut recorder, &font, &puzzle, &layout, &args, true)?;
+
+        assert_eq!(64, recorder.letters.len());
+        assert!(recorder.letters.iter().all(|l| l.3 == Some(Group::Grid)));
+        let (x, y, _, _) = &recorder.letters[9];
+        assert_eq!(
+            (layout.grid_stride, layout.grid_stride + layout.letter_top),
+            (*x, *y)
+        );
+        assert_eq!(2, recorder.marks);
+        Ok(())
+    }
+}
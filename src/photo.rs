@@ -0,0 +1,486 @@
+//! Reading a puzzle from a photo or scan of a printed page, to solve it. This is experimental: the grid is found by
+//! looking for rows of evenly spaced letters, and each letter is read by comparing it with the uppercase letters of
+//! the built-in font, so it works best on pages printed by this program and photographed straight on.
+
+use anyhow::{anyhow, Error};
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, RgbImage};
+use imageproc::contrast::otsu_level;
+use imageproc::region_labelling::{connected_components, Connectivity};
+use rusttype::Scale;
+use wordsearch::config::Args;
+use wordsearch::grid::{Cells, Direction, Placement, Puzzle};
+use wordsearch::page::Mark;
+use wordsearch::{render, typeset};
+
+/// The size, in pixels on a side, that letters are scaled to before they're compared.
+const SAMPLE: u32 = 20;
+
+/// How tall the font's letters are drawn, to compare the letters in the photo with.
+const TEMPLATE_SIZE: f32 = 96.0;
+
+/// How much a difference in shape, wide or narrow, counts against a match, compared to a difference in pixels.
+const ASPECT_WEIGHT: f32 = 20.0;
+
+/// The lightest shade of gray that counts as ink, however light the paper is. Yellow letters are about this dark,
+/// and highlighted answers are lighter.
+const LIGHTEST_INK: u8 = 170;
+
+/// How much less like a letter in the photo another letter can look than the likeliest one, and still be how the
+/// letter reads if that spells out a word.
+const CLOSE: f32 = 1.5;
+
+/// The most ways to read each letter in the photo, when looking for words.
+const MAX_READINGS: usize = 3;
+
+/// How far a letter can be from where it would be if the letters were evenly spaced, as a fraction of the spacing.
+const TOLERANCE: f32 = 0.3;
+
+/// The box around the dark pixels of a letter in the photo, or a part of one. The right and bottom edges are inside
+/// it.
+#[derive(Clone, Copy, Debug)]
+struct Glyph {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+}
+
+impl Glyph {
+    fn width(&self) -> u32 {
+        self.right - self.left + 1
+    }
+
+    fn height(&self) -> u32 {
+        self.bottom - self.top + 1
+    }
+
+    fn center(&self) -> (f32, f32) {
+        (
+            (self.left + self.right) as f32 / 2.0,
+            (self.top + self.bottom) as f32 / 2.0,
+        )
+    }
+
+    /// Grow the box to take in the other one too.
+    fn merge(&mut self, other: &Glyph) {
+        self.left = self.left.min(other.left);
+        self.top = self.top.min(other.top);
+        self.right = self.right.max(other.right);
+        self.bottom = self.bottom.max(other.bottom);
+    }
+}
+
+/// A puzzle read from a photo, and where the middles of its columns and rows are in the photo.
+pub struct Scan {
+    pub puzzle: Puzzle,
+    columns: Vec<f32>,
+    rows: Vec<f32>,
+}
+
+impl Scan {
+    /// Return where to mark each word found in the photo.
+    pub fn marks(&self) -> Vec<Mark> {
+        let stride =
+            (spacing(self.columns.iter().copied()) + spacing(self.rows.iter().copied())) / 2.0;
+        let center = |(x, y): (usize, usize)| (self.columns[x], self.rows[y]);
+        self.puzzle
+            .placements
+            .iter()
+            .map(|p| Mark {
+                start: center((p.x, p.y)),
+                end: center(p.end()),
+                letters: p.cells().map(center).collect(),
+                stride,
+            })
+            .collect()
+    }
+}
+
+/// Read the grid of a puzzle from a photo of it, and look for the words in it.
+pub fn read_photo(image: &RgbImage, words: &[String], args: &Args) -> Result<Scan, Error> {
+    let gray = imageops::grayscale(image);
+    let ink = ink(&gray);
+    let rows = find_grid(&glyphs(&ink))
+        .ok_or_else(|| anyhow!("Couldn't find a grid of letters in the photo"))?;
+    let templates = templates(&render::load_font(args)?, words);
+    let readings: Vec<Vec<Vec<char>>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|glyph| classify(&sample(&gray, glyph), &templates))
+                .collect()
+        })
+        .collect();
+    let grid: Cells = readings
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.first().copied()).collect())
+        .collect();
+    let columns = (0..rows[0].len())
+        .map(|x| rows.iter().map(|row| row[x].center().0).sum::<f32>() / rows.len() as f32)
+        .collect();
+    let middles = rows.iter().map(|row| middle(row)).collect();
+    let mut puzzle = Puzzle {
+        grid,
+        placements: vec![],
+        dropped: vec![],
+        attempts: vec![],
+    };
+    puzzle.placements = words
+        .iter()
+        .filter_map(|w| puzzle.find(w).or_else(|| find_likely(&readings, w)))
+        .collect();
+    // Where a word was found by a less likely reading of its letters, that's how they read.
+    for placement in &puzzle.placements {
+        for ((x, y), letter) in placement.cells().zip(placement.word.chars()) {
+            puzzle.grid[y][x] = Some(letter);
+        }
+    }
+    Ok(Scan {
+        puzzle,
+        columns,
+        rows: middles,
+    })
+}
+
+/// Return a mask of the dark pixels of the image: the ink, as opposed to the paper. Light shading, like highlighted
+/// answers, doesn't count.
+fn ink(gray: &GrayImage) -> GrayImage {
+    let level = otsu_level(gray).min(LIGHTEST_INK);
+    GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        Luma([if gray.get_pixel(x, y)[0] < level {
+            255
+        } else {
+            0
+        }])
+    })
+}
+
+/// Find the letters in the mask: each patch of ink, with any small patches just above or below it, like the dot of
+/// an "i", counted as part of it. Specks, and anything much bigger than a letter, like the lines of a frame, are left
+/// out.
+fn glyphs(ink: &GrayImage) -> Vec<Glyph> {
+    let labels = connected_components(ink, Connectivity::Eight, Luma([0]));
+    let mut parts: Vec<Option<Glyph>> = vec![];
+    for (x, y, label) in labels.enumerate_pixels() {
+        let label = label[0] as usize;
+        if label == 0 {
+            continue;
+        }
+        if parts.len() < label {
+            parts.resize(label, None);
+        }
+        let part = Glyph {
+            left: x,
+            top: y,
+            right: x,
+            bottom: y,
+        };
+        match &mut parts[label - 1] {
+            Some(glyph) => glyph.merge(&part),
+            empty => *empty = Some(part),
+        }
+    }
+    let mut parts: Vec<Glyph> = parts.into_iter().flatten().collect();
+    parts.sort_by_key(|g| std::cmp::Reverse(g.height()));
+    let mut glyphs: Vec<Glyph> = vec![];
+    for part in parts {
+        let (x, _) = part.center();
+        let attached = glyphs.iter_mut().find(|g| {
+            let gap = part
+                .top
+                .saturating_sub(g.bottom)
+                .max(g.top.saturating_sub(part.bottom));
+            part.height() * 2 < g.height()
+                && (g.left as f32..=g.right as f32).contains(&x)
+                && gap * 2 < g.height()
+        });
+        match attached {
+            Some(glyph) => glyph.merge(&part),
+            None => glyphs.push(part),
+        }
+    }
+    let typical = typical_height(&glyphs) as u32;
+    glyphs.retain(|g| g.height() * 4 >= typical && g.height().max(g.width()) <= typical * 4);
+    glyphs
+}
+
+/// Find the grid among the letters: the most letters in a block of evenly spaced rows of evenly spaced letters, of
+/// about the same size, lined up in columns. Letters beside the grid that aren't in every row, like row numbers, are
+/// left out. Return its rows, from top to bottom.
+fn find_grid(glyphs: &[Glyph]) -> Option<Vec<Vec<Glyph>>> {
+    let runs: Vec<Vec<Glyph>> = lines(glyphs)
+        .iter()
+        .map(|line| evenly_spaced(line))
+        .collect();
+    let mut best: Option<Vec<Vec<Glyph>>> = None;
+    for (start, first) in runs.iter().enumerate() {
+        if first.len() < 2 {
+            continue;
+        }
+        let pitch = spacing(first.iter().map(|g| g.center().0));
+        let height = typical_height(first);
+        let mut rows = vec![first.clone()];
+        for row in &runs[start + 1..] {
+            // Follow each column from the row above, so they can drift a little in a tilted photo.
+            let below: Vec<Option<Glyph>> = rows[rows.len() - 1]
+                .iter()
+                .map(|above| {
+                    row.iter()
+                        .find(|g| (g.center().0 - above.center().0).abs() <= pitch * TOLERANCE)
+                        .copied()
+                })
+                .collect();
+            let kept: Vec<usize> = (0..below.len()).filter(|&x| below[x].is_some()).collect();
+            let contiguous = kept
+                .last()
+                .zip(kept.first())
+                .is_some_and(|(last, first)| last - first + 1 == kept.len());
+            let same_size = (typical_height(row) - height).abs() <= height * TOLERANCE;
+            let gap = middle(row) - middle(&rows[rows.len() - 1]);
+            let evenly_spaced = rows.len() < 2 || {
+                let row_pitch = middle(&rows[1]) - middle(&rows[0]);
+                (gap - row_pitch).abs() <= row_pitch * TOLERANCE
+            };
+            if kept.len() < 2 || !contiguous || !same_size || !evenly_spaced {
+                break;
+            }
+            for above in &mut rows {
+                *above = kept.iter().map(|&x| above[x]).collect();
+            }
+            rows.push(below.into_iter().flatten().collect());
+            let size = |rows: &[Vec<Glyph>]| rows.len() * rows[0].len();
+            if best.as_deref().is_none_or(|best| size(&rows) > size(best)) {
+                best = Some(rows.clone());
+            }
+        }
+    }
+    best
+}
+
+/// Return the height of most of the letters.
+fn typical_height(line: &[Glyph]) -> f32 {
+    let mut heights: Vec<u32> = line.iter().map(Glyph::height).collect();
+    heights.sort();
+    heights
+        .get(heights.len() / 2)
+        .map_or(0.0, |&height| height as f32)
+}
+
+/// Return how far down the middle of the line is.
+fn middle(line: &[Glyph]) -> f32 {
+    line.iter().map(|g| g.center().1).sum::<f32>() / line.len() as f32
+}
+
+/// Sort the letters into lines of text, from top to bottom, each from left to right.
+fn lines(glyphs: &[Glyph]) -> Vec<Vec<Glyph>> {
+    let mut glyphs = glyphs.to_vec();
+    glyphs.sort_by(|a, b| a.center().1.total_cmp(&b.center().1));
+    let mut lines: Vec<Vec<Glyph>> = vec![];
+    for glyph in glyphs {
+        let same_line = lines.last().is_some_and(|line| {
+            let height = line.iter().map(|g| g.height()).max().unwrap_or(0);
+            (glyph.center().1 - middle(line)).abs() * 2.0 < height as f32
+        });
+        match lines.last_mut() {
+            Some(line) if same_line => line.push(glyph),
+            _ => lines.push(vec![glyph]),
+        }
+    }
+    for line in &mut lines {
+        line.sort_by_key(|g| g.left);
+    }
+    lines
+}
+
+/// Return the longest stretch of the line where the letters are evenly spaced, like a row of the grid and unlike the
+/// words of the key, leaving out anything beside the grid, like row numbers.
+fn evenly_spaced(line: &[Glyph]) -> Vec<Glyph> {
+    let mut best: &[Glyph] = &line[..line.len().min(1)];
+    for start in 0..line.len().saturating_sub(1) {
+        let pitch = line[start + 1].center().0 - line[start].center().0;
+        let mut end = start + 2;
+        while end < line.len()
+            && (line[end].center().0 - line[end - 1].center().0 - pitch).abs() <= pitch * TOLERANCE
+        {
+            end += 1;
+        }
+        if end - start > best.len() {
+            best = &line[start..end];
+        }
+    }
+    best.to_vec()
+}
+
+/// Return the average distance between neighboring values.
+fn spacing(middles: impl Iterator<Item = f32>) -> f32 {
+    let middles: Vec<f32> = middles.collect();
+    match &middles[..] {
+        [first, .., last] => (last - first) / (middles.len() - 1) as f32,
+        _ => 0.0,
+    }
+}
+
+/// A letter, scaled to [`SAMPLE`] pixels on a side, with how wide it was for its height.
+struct Sample {
+    pixels: Vec<f32>,
+    aspect: f32,
+}
+
+/// Scale a letter to the size letters are compared at, in a square around it so narrow letters stay narrow, and
+/// stretch its shades of gray from the paper to the darkest ink, so letters compare the same however light or blurry
+/// the photo is.
+fn sample(gray: &GrayImage, glyph: &Glyph) -> Sample {
+    let side = glyph.width().max(glyph.height());
+    let (x, y) = glyph.center();
+    let (left, top) = (x - side as f32 / 2.0, y - side as f32 / 2.0);
+    let square = GrayImage::from_fn(side, side, |dx, dy| {
+        let (x, y) = ((left + dx as f32).round(), (top + dy as f32).round());
+        match (x >= 0.0 && y >= 0.0).then(|| gray.get_pixel_checked(x as u32, y as u32)) {
+            Some(Some(pixel)) => *pixel,
+            _ => Luma([255]),
+        }
+    });
+    let scaled = imageops::blur(
+        &imageops::resize(&square, SAMPLE, SAMPLE, FilterType::Triangle),
+        1.0,
+    );
+    let darkest = scaled.pixels().map(|p| p[0]).min().unwrap_or(0) as f32;
+    let lightest = scaled.pixels().map(|p| p[0]).max().unwrap_or(255) as f32;
+    Sample {
+        pixels: scaled
+            .pixels()
+            .map(|p| (lightest - p[0] as f32) / (lightest - darkest).max(1.0))
+            .collect(),
+        aspect: glyph.width() as f32 / glyph.height() as f32,
+    }
+}
+
+/// Draw each letter the photo might have in it, in the font, to compare the photo's letters with: the letters A to
+/// Z, and any others in the words.
+fn templates(font: &typeset::Font, words: &[String]) -> Vec<(char, Sample)> {
+    let mut letters: Vec<char> = ('A'..='Z').collect();
+    for letter in words.iter().flat_map(|w| w.chars()) {
+        if letter.is_alphanumeric() && !letters.contains(&letter) {
+            letters.push(letter);
+        }
+    }
+    let scale = Scale::uniform(TEMPLATE_SIZE);
+    letters
+        .into_iter()
+        .filter_map(|letter| {
+            let (width, height) = typeset::size(scale, font, &letter.to_string());
+            let margin = TEMPLATE_SIZE as i32 / 2;
+            let (width, height) = ((width + 2 * margin) as u32, (height + 2 * margin) as u32);
+            let mut image = RgbImage::from_pixel(width, height, image::Rgb([255, 255, 255]));
+            typeset::draw(
+                &mut image,
+                render::TEXT,
+                margin,
+                margin,
+                scale,
+                font,
+                &letter.to_string(),
+            );
+            let gray = imageops::grayscale(&image);
+            let glyph = glyphs(&ink(&gray)).into_iter().reduce(|mut a, b| {
+                a.merge(&b);
+                a
+            })?;
+            Some((letter, sample(&gray, &glyph)))
+        })
+        .collect()
+}
+
+/// Return the letters that look most like the sample, the likeliest first: the ones that look almost as much like it
+/// as the likeliest one does.
+fn classify(sample: &Sample, templates: &[(char, Sample)]) -> Vec<char> {
+    let distance = |template: &Sample| {
+        let pixels: f32 = sample
+            .pixels
+            .iter()
+            .zip(&template.pixels)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        let shape = (sample.aspect.ln() - template.aspect.ln()).powi(2);
+        pixels + ASPECT_WEIGHT * shape
+    };
+    let mut distances: Vec<(f32, char)> = templates
+        .iter()
+        .map(|(letter, t)| (distance(t), *letter))
+        .collect();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let best = distances.first().map_or(0.0, |d| d.0);
+    distances
+        .into_iter()
+        .take_while(|&(distance, _)| distance <= best * CLOSE)
+        .take(MAX_READINGS)
+        .map(|(_, letter)| letter)
+        .collect()
+}
+
+/// Look for a word that isn't in the likeliest reading of the grid, where each of its letters is one of the ways
+/// that cell might read, for letters that are hard to tell apart in a blurry photo, like O and Q.
+fn find_likely(readings: &[Vec<Vec<char>>], word: &str) -> Option<Placement> {
+    let letters: Vec<char> = word.chars().collect();
+    let (width, height) = (readings[0].len() as isize, readings.len() as isize);
+    for y in 0..height {
+        for x in 0..width {
+            for direction in Direction::ALL {
+                let (dx, dy) = direction.next();
+                let matches = letters.iter().enumerate().all(|(i, letter)| {
+                    let (cx, cy) = (x + dx * i as isize, y + dy * i as isize);
+                    (0..width).contains(&cx)
+                        && (0..height).contains(&cy)
+                        && readings[cy as usize][cx as usize].contains(letter)
+                });
+                if matches {
+                    return Some(Placement {
+                        word: word.to_string(),
+                        x: x as usize,
+                        y: y as usize,
+                        direction,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use wordsearch::config::Args;
+    use wordsearch::grid::Grid;
+    use wordsearch::render;
+    use wordsearch::wordlist::Entry;
+
+    use super::read_photo;
+
+    #[test]
+    fn test_read_photo() -> Result<(), Error> {
+        let words: Vec<String> = ["ELEPHANT", "GIRAFFE", "LION", "ZEBRA"]
+            .map(String::from)
+            .to_vec();
+        let puzzle =
+            Grid::new(words.clone(), Some(9), Some(9)).generate(&mut StdRng::seed_from_u64(0))?;
+        let entries: Vec<Entry> = words.iter().cloned().map(Entry::new).collect();
+        let args = Args::parse_from([
+            "wordsearch",
+            "--image-width",
+            "600",
+            "--image-height",
+            "800",
+        ]);
+        let image = render::make_image(&entries, &puzzle, &args, None, false)?;
+        let scan = read_photo(&image, &words, &args)?;
+        assert_eq!(scan.puzzle.grid, puzzle.grid);
+        assert_eq!(scan.puzzle.placements.len(), words.len());
+        assert_eq!(scan.marks().len(), words.len());
+        Ok(())
+    }
+}
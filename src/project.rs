@@ -0,0 +1,238 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Error};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, Parser};
+use serde_json::{json, Value};
+
+use crate::config::Args;
+
+use crate::grid::{self, Direction, Placement, Puzzle};
+use crate::wordlist::Entry;
+
+/// Everything needed to draw a puzzle again later: the options it was made with, its seed, its word list, and its
+/// layout.
+pub struct Project {
+    /// The command line the puzzle was made with, not counting the program's name.
+    pub args: Vec<String>,
+    pub seed: Option<u64>,
+    pub words: Vec<Entry>,
+    pub puzzle: Puzzle,
+}
+
+/// Write a project as JSON: the command line the puzzle was made with, not counting the program's name, and the
+/// puzzle.
+pub fn write_project<W: Write>(
+    out: W,
+    args: &[String],
+    seed: Option<u64>,
+    words: &[Entry],
+    puzzle: &Puzzle,
+) -> Result<(), Error> {
+    let words: Vec<Value> = words
+        .iter()
+        .map(|e| {
+            json!({
+                "word": e.word,
+                "category": e.category,
+                "weight": e.weight,
+                "clue": e.clue,
+                "translation": e.translation,
+            })
+        })
+        .collect();
+    let grid: Vec<String> = puzzle
+        .grid
+        .iter()
+        .map(|row| row.iter().map(|c| c.unwrap_or(grid::EMPTY)).collect())
+        .collect();
+    let placements: Vec<Value> = puzzle
+        .placements
+        .iter()
+        .map(|p| json!({"word": p.word, "x": p.x, "y": p.y, "direction": p.direction.to_string()}))
+        .collect();
+    let json = json!({
+        "version": 1,
+        "args": args,
+        "seed": seed,
+        "words": words,
+        "grid": grid,
+        "placements": placements,
+    });
+    serde_json::to_writer_pretty(out, &json)?;
+    Ok(())
+}
+
+/// Combine the options a project was made with and the ones given now, which take precedence. `current` is the whole
+/// command line, starting with the program's name. The project's --save-project is left out, so drawing a project
+/// doesn't overwrite it.
+pub fn merge_args(saved: Vec<String>, current: Vec<OsString>) -> Result<Args, Error> {
+    let command = Args::command();
+    let matches = command.clone().try_get_matches_from(&current)?;
+    let given = |id: &str| {
+        id == "save_project" || matches.value_source(id) == Some(ValueSource::CommandLine)
+    };
+    let mut merged: Vec<OsString> = current.iter().take(1).cloned().collect();
+    let mut tokens = saved.into_iter();
+    while let Some(token) = tokens.next() {
+        // Find the option, and whether its value is in the same token, like --seed=7 or -c12, or the next one.
+        let arg = command.get_arguments().find_map(|arg| {
+            if let Some(rest) = token.strip_prefix("--") {
+                let (name, value) = rest.split_once('=').unzip();
+                (arg.get_long() == Some(name.unwrap_or(rest))).then_some((arg, value.is_some()))
+            } else {
+                let mut chars = token.chars();
+                let short = chars.next() == Some('-') && chars.next() == arg.get_short();
+                (short && arg.get_short().is_some()).then_some((arg, token.len() > 2))
+            }
+        });
+        match arg {
+            Some((arg, attached)) => {
+                let value = match arg.get_action().takes_values() && !attached {
+                    true => tokens.next(),
+                    false => None,
+                };
+                if !given(arg.get_id().as_str()) {
+                    merged.push(token.into());
+                    merged.extend(value.map(OsString::from));
+                }
+            }
+            None => merged.push(token.into()),
+        }
+    }
+    merged.extend(current.into_iter().skip(1));
+    Ok(Args::try_parse_from(merged)?)
+}
+
+/// Read a project written by `write_project`. The seed is missing if the puzzle wasn't generated.
+pub fn read_project(filename: &Path) -> Result<Project, Error> {
+    let json: Value = serde_json::from_reader(BufReader::new(File::open(filename)?))?;
+    let invalid = |what: &str| anyhow!("Bad {} in project file {:?}", what, filename);
+    if json["version"] != 1 {
+        return Err(invalid("version"));
+    }
+    let strings = |value: &Value, what: &str| -> Result<Vec<String>, Error> {
+        value
+            .as_array()
+            .ok_or_else(|| invalid(what))?
+            .iter()
+            .map(|s| s.as_str().map(str::to_string).ok_or_else(|| invalid(what)))
+            .collect()
+    };
+
+    let args = strings(&json["args"], "options")?;
+    let words = json["words"]
+        .as_array()
+        .ok_or_else(|| invalid("words"))?
+        .iter()
+        .map(|e| {
+            let word = e["word"].as_str().ok_or_else(|| invalid("words"))?;
+            Ok(Entry::new(word.to_string())
+                .with_category(e["category"].as_str().map(str::to_string))
+                .with_weight(e["weight"].as_f64().map(|w| w as f32))
+                .with_clue(e["clue"].as_str().map(str::to_string))
+                .with_translation(e["translation"].as_str().map(str::to_string)))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let grid: Vec<Vec<Option<char>>> = strings(&json["grid"], "grid")?
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|c| (c != grid::EMPTY).then_some(c))
+                .collect()
+        })
+        .collect();
+    if grid.is_empty() || grid.iter().any(|row| row.len() != grid[0].len()) {
+        return Err(invalid("grid"));
+    }
+    let placements = json["placements"]
+        .as_array()
+        .ok_or_else(|| invalid("placements"))?
+        .iter()
+        .map(|p| {
+            let direction = Direction::ALL
+                .into_iter()
+                .find(|d| p["direction"].as_str() == Some(&d.to_string()))
+                .ok_or_else(|| invalid("direction"))?;
+            let coordinate = |key| {
+                p[key]
+                    .as_u64()
+                    .map(|n| n as usize)
+                    .ok_or_else(|| invalid("placements"))
+            };
+            Ok(Placement {
+                word: p["word"]
+                    .as_str()
+                    .ok_or_else(|| invalid("placements"))?
+                    .to_string(),
+                x: coordinate("x")?,
+                y: coordinate("y")?,
+                direction,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let puzzle = Puzzle {
+        grid,
+        placements,
+        dropped: vec![],
+        attempts: vec![],
+    };
+    let in_place = |p: &Placement| {
+        let (dx, dy) = p.direction.next();
+        p.word.chars().enumerate().all(|(i, letter)| {
+            let x = p.x.checked_add_signed(dx * i as isize);
+            let y = p.y.checked_add_signed(dy * i as isize);
+            let cell = y.and_then(|y| puzzle.grid.get(y)?.get(x?));
+            cell.is_some_and(|c| c.is_some_and(|c| grid::same_letter(c, letter)))
+        })
+    };
+    if let Some(p) = puzzle.placements.iter().find(|p| !in_place(p)) {
+        return Err(anyhow!(
+            "{} isn't where project file {:?} says",
+            p.word,
+            filename
+        ));
+    }
+    Ok(Project {
+        args,
+        seed: json["seed"].as_u64(),
+        words,
+        puzzle,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::merge_args;
+
+    #[test]
+    fn test_merge_args() {
+        let saved = [
+            "--seed",
+            "7",
+            "-c12",
+            "--save-project",
+            "p.wsproj",
+            "--coordinates",
+        ];
+        let current = ["wordsearch", "--project", "p.wsproj", "-c", "10"];
+        let args = merge_args(
+            saved.map(String::from).to_vec(),
+            current.map(Into::into).to_vec(),
+        )
+        .unwrap();
+        assert_eq!(Some(7), args.seed);
+        assert_eq!(Some(10), args.grid_width);
+        assert!(args.coordinates);
+        assert_eq!(None, args.save_project);
+        assert_eq!(Some(PathBuf::from("p.wsproj")), args.project);
+    }
+}
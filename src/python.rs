@@ -0,0 +1,106 @@
+//! Python bindings, so puzzles can be made from scripts and notebooks:
+//!
+//! ```python
+//! import wordsearch
+//! puzzle = wordsearch.generate(["Butte", "Helena"], columns=10, rows=10, seed=7)
+//! open("puzzle.png", "wb").write(puzzle["png"])
+//! ```
+
+use std::fmt::Display;
+use std::io::Cursor;
+
+use clap::Parser;
+use image::{ImageOutputFormat, RgbImage};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::config::Args;
+use crate::error::WordSearchError;
+use crate::grid;
+use crate::render;
+use crate::wordlist::Entry;
+
+/// Make a puzzle from the words. The options are the same as the command line's, with underscores for dashes: a
+/// flag is given as True, and anything else as its value, like `generate(words, columns=12, no_overlap=True)`.
+///
+/// Returns a dict with the puzzle's "seed", its "grid" as a list of rows, its "placements" as dicts with the "word",
+/// "x", "y", and "direction" of each, and the page as PNG images in "png" and, with the answers marked,
+/// "solution_png".
+#[pyfunction]
+#[pyo3(signature = (words, **options))]
+fn generate<'py>(
+    py: Python<'py>,
+    words: Vec<String>,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let mut argv = vec!["wordsearch".to_string()];
+    for (key, value) in options.into_iter().flat_map(|options| options.iter()) {
+        let flag = format!("--{}", key.extract::<String>()?.replace('_', "-"));
+        match value.extract::<bool>() {
+            Ok(true) => argv.push(flag),
+            Ok(false) => (),
+            Err(_) => argv.extend([flag, value.str()?.to_string()]),
+        }
+    }
+    let args = Args::try_parse_from(argv).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    let seed = args
+        .seed
+        .unwrap_or_else(|| rand::thread_rng().gen::<u32>() as u64);
+    let mut words: Vec<Entry> = words.into_iter().map(Entry::new).collect();
+    let grid_words = words.iter().map(Entry::grid_form).collect();
+    let puzzle = args
+        .new_grid(grid_words, args.grid_width, args.grid_height)
+        .map_err(runtime_error)?
+        .generate(&mut StdRng::seed_from_u64(seed))
+        .map_err(runtime_error)?;
+    words.retain(|w| !puzzle.dropped.contains(&w.grid_form()));
+
+    let grid: Vec<String> = puzzle
+        .grid
+        .iter()
+        .map(|row| row.iter().map(|c| c.unwrap_or(grid::EMPTY)).collect())
+        .collect();
+    let placements = puzzle
+        .placements
+        .iter()
+        .map(|p| {
+            let placement = PyDict::new(py);
+            placement.set_item("word", &p.word)?;
+            placement.set_item("x", p.x)?;
+            placement.set_item("y", p.y)?;
+            placement.set_item("direction", p.direction.to_string())?;
+            Ok(placement)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let png = |solution| {
+        let image = render::make_image(&words, &puzzle, &args, None, solution);
+        image.and_then(to_png).map_err(runtime_error)
+    };
+
+    let result = PyDict::new(py);
+    result.set_item("seed", seed)?;
+    result.set_item("grid", grid)?;
+    result.set_item("placements", placements)?;
+    result.set_item("png", PyBytes::new(py, &png(false)?))?;
+    result.set_item("solution_png", PyBytes::new(py, &png(true)?))?;
+    Ok(result)
+}
+
+fn to_png(image: RgbImage) -> Result<Vec<u8>, WordSearchError> {
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+fn runtime_error(e: impl Display) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+#[pymodule]
+fn wordsearch(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(generate, m)?)
+}
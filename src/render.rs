@@ -0,0 +1,1315 @@
+use std::cmp::{max, min};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use imageproc::drawing;
+use imageproc::point::Point;
+use imageproc::rect::Rect;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use rusttype::Scale;
+
+use crate::config::{
+    self, Args, Baseline, FrameAround, FrameStyle, Length, LetterColors, MarkStyle, Typeface,
+};
+use crate::error::WordSearchError;
+use crate::grid::{self, Cells, Puzzle};
+use crate::key;
+use crate::page::{self, Align, Group, Mark, Rectangle, Renderer, Text};
+use crate::script;
+use crate::typeset::{self, Font};
+use crate::wordlist::Entry;
+
+/// The color of answer marks on the mask that [`blend_mask`] paints with.
+const MARK: Luma<u8> = Luma([255]);
+
+/// The color of the text.
+pub const TEXT: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// How small the grid may get, compared to the size that fills the page, to make room for the key. Whatever doesn't
+/// fit in the key after that continues on another page.
+const MIN_SHRINK: f32 = 0.5;
+
+/// How many rows of the image [`write_png`] renders at a time.
+const BAND_ROWS: u32 = 256;
+
+/// With --theme high-contrast, the lines of boxes are this many times thinner than a cell is wide, and at least two
+/// pixels.
+const HIGH_CONTRAST_LINES: i32 = 16;
+
+/// Letters that reach below the line, with an "M" for the capitals.
+const DESCENDERS: &str = "Mgjpqy";
+
+/// How long crop marks are.
+const CROP_MARK_LENGTH: Length = Length::Millimeters(5.0);
+
+/// The colors for --letter-colors rainbow, dark enough to read on white and under the answer marks.
+const RAINBOW: [Rgb<u8>; 7] = [
+    Rgb([211, 47, 47]),
+    Rgb([230, 110, 0]),
+    Rgb([200, 150, 0]),
+    Rgb([46, 125, 50]),
+    Rgb([25, 118, 210]),
+    Rgb([57, 73, 171]),
+    Rgb([123, 31, 162]),
+];
+
+/// The label at the top of the pages the key continues onto.
+pub const CONTINUED: &str = "Words to find, continued";
+
+/// The sizes of things on the page.
+pub struct Layout {
+    /// Where the grid starts, below any header and the column labels.
+    pub top: i32,
+    /// Where the grid starts, right of any row labels.
+    pub left: i32,
+    /// The size of the letters in the grid.
+    pub scale: Scale,
+    /// The width and height of each cell in the grid.
+    pub grid_stride: i32,
+    /// How far below the top of its cell each letter is drawn.
+    pub letter_top: i32,
+    /// How far below the top of its cell the middle of each letter is, where the answer marks go.
+    pub middle: i32,
+    /// The size of the words in the key.
+    pub key_scale: Scale,
+    /// The height of each line of the key.
+    pub key_stride: i32,
+    /// The room between the grid and the key.
+    pub key_gap: i32,
+    /// How many columns the key is laid out in.
+    pub key_columns: usize,
+    /// How many lines of the key fit on the page, under the grid.
+    pub key_rows: usize,
+    /// Whether the grid has coordinate labels around it, in a margin one cell wide.
+    pub coordinates: bool,
+    /// How much room the frame around the grid takes on each side, if there is one, with its padding.
+    pub frame: i32,
+    /// Whether the text is bold, for --theme high-contrast.
+    pub bold: bool,
+    /// How thick the lines of boxes are.
+    pub line_width: i32,
+    /// The color of each letter of the grid, by row, for --letter-colors.
+    pub letter_colors: Option<Vec<Vec<Rgb<u8>>>>,
+}
+
+/// Return the color of each letter of the grid, by row, for --letter-colors. The colors of a palette go in turn
+/// along each diagonal. Random colors are seeded from the grid, so every copy of the puzzle matches.
+pub fn letter_colors(
+    grid: &Cells,
+    args: &Args,
+) -> Result<Option<Vec<Vec<Rgb<u8>>>>, WordSearchError> {
+    let palette = match &args.letter_colors {
+        None => return Ok(None),
+        Some(LetterColors::Rainbow | LetterColors::Random) => RAINBOW.to_vec(),
+        Some(LetterColors::Palette(path)) => {
+            let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            let colors = json
+                .as_array()
+                .ok_or_else(|| {
+                    WordSearchError::InvalidInput(format!(
+                        "Expected a list of colors in {:?}",
+                        path
+                    ))
+                })?
+                .iter()
+                .map(|color| {
+                    let color = color.as_str().ok_or_else(|| {
+                        WordSearchError::InvalidInput(format!(
+                            "Expected a list of colors in {:?}",
+                            path
+                        ))
+                    })?;
+                    config::parse_color(color)
+                        .map_err(|e| WordSearchError::InvalidInput(format!("In {:?}: {}", path, e)))
+                })
+                .collect::<Result<Vec<_>, WordSearchError>>()?;
+            if colors.is_empty() {
+                return Err(WordSearchError::InvalidInput(format!(
+                    "No colors in {:?}",
+                    path
+                )));
+            }
+            colors
+        }
+    };
+    let mut hasher = DefaultHasher::new();
+    grid.hash(&mut hasher);
+    let mut rng = StdRng::seed_from_u64(hasher.finish());
+    let colors = grid
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            (0..row.len())
+                .map(|x| match args.letter_colors {
+                    Some(LetterColors::Random) => *palette.choose(&mut rng).unwrap(),
+                    _ => palette[(x + y) % palette.len()],
+                })
+                .collect()
+        })
+        .collect();
+    Ok(Some(colors))
+}
+
+/// A piece of a decorative frame, in the same place in every kind of image.
+pub enum FramePart {
+    /// A band `thickness` wide, just inside this rectangle, with its outside corners rounded to `radius`.
+    Band {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        thickness: i32,
+        radius: i32,
+    },
+    /// A filled square with its top left corner here.
+    Square { x: i32, y: i32, size: i32 },
+    /// A filled diamond centered here.
+    Diamond { x: i32, y: i32, radius: i32 },
+}
+
+impl Layout {
+    /// Make the grid as large as possible, while still leaving room on the page for the header, the key, and the
+    /// score box. If the key won't fit without shrinking the grid too much, leave room for as much of it as fits.
+    /// The user can also fix the size of the grid letters or the key words instead. The coordinate labels, if any,
+    /// take up one more row and column of cells.
+    pub fn new(
+        font: &Font,
+        wordlist: &[Entry],
+        puzzle: &Puzzle,
+        args: &Args,
+        top: i32,
+    ) -> Result<Self, WordSearchError> {
+        let (width, height) = (args.image_width, args.image_height);
+        let grid = &puzzle.grid;
+        // Emoji are left to the viewer's emoji font, in the formats that have one.
+        let letters = grid.iter().flatten().flatten();
+        if let Some(c) = letters
+            .copied()
+            .find(|&c| !script::is_emoji(c) && font.glyph(c).id().0 == 0)
+        {
+            return Err(WordSearchError::Font(format!(
+                "The font has no letter {} to draw in the grid",
+                c
+            )));
+        }
+        let letter_colors = letter_colors(grid, args)?;
+        // Letters taller than the page can't be drawn, and huge ones overflow rusttype's arithmetic.
+        for (option, size) in [
+            ("--grid-font-size", args.grid_font_size),
+            ("--key-font-size", args.key_font_size),
+        ] {
+            if size.is_some_and(|size| size > height as f32) {
+                return Err(WordSearchError::InvalidInput(format!(
+                    "{} can't be more than the height of the page, {} pixels",
+                    option, height
+                )));
+            }
+        }
+        let margin = args.coordinates as i32;
+        let frame = match args.frame_around {
+            FrameAround::Grid | FrameAround::Both if args.frame.is_some() => {
+                (args.frame_thickness + args.frame_padding) as i32
+            }
+            _ => 0,
+        };
+        let mut desired_stride = min(
+            width.saturating_sub(2 * frame as u32) / (grid[0].len() as u32 + margin as u32),
+            height.saturating_sub(top as u32 + 2 * frame as u32)
+                / (grid.len() as u32 + margin as u32),
+        );
+        let min_stride = max(1, (desired_stride as f32 * MIN_SHRINK) as u32);
+        // Lowercase letters can hang below the bottom of an "M", so the cells of a mixed-case grid leave room for them.
+        let tallest = match args.mixed_case {
+            true => DESCENDERS,
+            false => "M",
+        };
+        loop {
+            let text_height =
+                compute_text_height(font, desired_stride as i32, args.letter_spacing(), tallest);
+            let scale = match args.grid_font_size {
+                Some(size) => font_scale(font, size),
+                None => Scale {
+                    x: text_height,
+                    y: text_height,
+                },
+            };
+            let (text_width, _) = typeset::size(scale, font, "M");
+            let (_, text_height) = typeset::size(scale, font, tallest);
+            let grid_stride = max(
+                (text_width as f32 * args.letter_spacing()) as i32,
+                text_height,
+            );
+            let key_scale = match args.key_font_size {
+                Some(size) => font_scale(font, size),
+                None => key_scale(text_height),
+            };
+            let (_, key_stride) = typeset::size(key_scale, font, "M");
+            // Text on a page too small to hold any is no pixels tall, but the key is still divided into lines.
+            let key_stride = max(1, key_stride);
+            let key_gap = args
+                .key_gap
+                .map_or(key_stride, |gap| gap.pixels(args.dpi) as i32);
+            let key_columns = key_columns(font, wordlist, key_scale, width);
+            let key_rows = key::rows(wordlist, key_columns) as i32;
+            // The tails of letters like "g" in the last line of the key hang below it.
+            let key_descent = (-font.v_metrics(key_scale).descent).ceil() as i32;
+            let mut needed = top
+                + 2 * frame
+                + (grid.len() as i32 + margin) * grid_stride
+                + key_gap
+                + key_rows * key_stride
+                + key_descent;
+            if args.score_box {
+                needed += 3 * key_stride;
+            } else if args.show_code || args.show_checksum {
+                // Leave room for the code and checksum under the key.
+                needed += 2 * key_stride;
+            }
+            let fixed = args.grid_font_size.is_some();
+            if needed <= height as i32 || desired_stride <= min_stride || fixed {
+                let overflow = max(0, needed - height as i32);
+                let key_rows = max(0, key_rows - (overflow + key_stride - 1) / key_stride) as usize;
+                let letter_top = match args.baseline {
+                    Baseline::Top => 0,
+                    Baseline::Center => grid_stride / 2 - letter_middle(font, scale),
+                };
+                return Ok(Self {
+                    top: top + frame + margin * grid_stride,
+                    left: frame + margin * grid_stride,
+                    scale,
+                    grid_stride,
+                    letter_top,
+                    middle: letter_top + letter_middle(font, scale),
+                    key_scale,
+                    key_stride,
+                    key_gap,
+                    key_columns,
+                    key_rows,
+                    coordinates: args.coordinates,
+                    frame,
+                    bold: args.high_contrast(),
+                    line_width: match args.high_contrast() {
+                        true => max(2, grid_stride / HIGH_CONTRAST_LINES),
+                        false => 1,
+                    },
+                    letter_colors,
+                });
+            }
+            desired_stride -= 1;
+        }
+    }
+
+    /// Where the key starts.
+    pub fn key_top(&self, grid_rows: usize) -> i32 {
+        self.top + grid_rows as i32 * self.grid_stride + self.frame + self.key_gap
+    }
+
+    /// The outside edge of the frame around the grid, if there is one: its left, top, width, and height.
+    pub fn grid_frame(&self, grid: &Cells) -> Option<(i32, i32, i32, i32)> {
+        if self.frame == 0 {
+            return None;
+        }
+        let margin = self.coordinates as i32 * self.grid_stride;
+        let (x, y) = (
+            self.left - margin - self.frame,
+            self.top - margin - self.frame,
+        );
+        let width = margin + grid[0].len() as i32 * self.grid_stride + 2 * self.frame;
+        let height = margin + grid.len() as i32 * self.grid_stride + 2 * self.frame;
+        Some((x, y, width, height))
+    }
+}
+
+/// With a frame around the page, return the options for drawing what goes inside it, and how far in from the edge
+/// of the page that starts.
+pub fn inside_frame(args: &Args) -> Option<(Args, u32)> {
+    match args.frame_around {
+        FrameAround::Page | FrameAround::Both if args.frame.is_some() => {
+            let inset = args.frame_thickness + args.frame_padding;
+            let mut inner = args.clone();
+            inner.image_width = args.image_width.saturating_sub(2 * inset).max(1);
+            inner.image_height = args.image_height.saturating_sub(2 * inset).max(1);
+            match args.frame_around {
+                FrameAround::Both => inner.frame_around = FrameAround::Grid,
+                _ => inner.frame = None,
+            }
+            Some((inner, inset))
+        }
+        _ => None,
+    }
+}
+
+/// Return the pieces of a frame in this style, whose outside edge is the rectangle at (x, y) with this width and
+/// height.
+pub fn frame_parts(
+    style: FrameStyle,
+    rect: (i32, i32, i32, i32),
+    thickness: i32,
+) -> Vec<FramePart> {
+    let (x, y, width, height) = rect;
+    let band = |inset: i32, thickness: i32, radius: i32| FramePart::Band {
+        x: x + inset,
+        y: y + inset,
+        width: width - 2 * inset,
+        height: height - 2 * inset,
+        thickness,
+        radius,
+    };
+    // The lines of a double frame are a third as thick as the frame, with the rest of it between them.
+    let line = max(1, thickness / 3);
+    match style {
+        FrameStyle::Simple => vec![band(0, thickness, 0)],
+        FrameStyle::Rounded => vec![band(0, thickness, 4 * thickness)],
+        FrameStyle::Double => vec![band(0, line, 0), band(thickness - line, line, 0)],
+        FrameStyle::Fancy => {
+            let mut parts = vec![band(0, line, 0), band(thickness - line, line, 0)];
+            let (right, bottom) = (x + width - thickness, y + height - thickness);
+            for (x, y) in [(x, y), (right, y), (x, bottom), (right, bottom)] {
+                parts.push(FramePart::Square {
+                    x,
+                    y,
+                    size: thickness,
+                });
+            }
+            // The diamonds reach from the outside edge of the frame as far in again as it's thick.
+            let (middle_x, middle_y) = (x + width / 2, y + height / 2);
+            for (x, y) in [
+                (middle_x, y + thickness),
+                (middle_x, bottom),
+                (x + thickness, middle_y),
+                (right, middle_y),
+            ] {
+                parts.push(FramePart::Diamond {
+                    x,
+                    y,
+                    radius: thickness,
+                });
+            }
+            parts
+        }
+    }
+}
+
+/// Render the whole page: the grid with the key below it. If `solution` is set, mark the answers in the grid.
+pub fn make_image(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<RgbImage, WordSearchError> {
+    let font = load_font(args)?;
+    let (_, height) = sheet_size(args);
+    render_rows(args, 0, height, &font, &|raster, args| {
+        page::draw_page(raster, &font, wordlist, puzzle, args, student, solution)
+    })
+}
+
+/// Write the page as a PNG, like [`make_image`] but rendered and encoded a band of rows at a time, so a poster-sized
+/// page never has to be in memory all at once.
+pub fn write_png<W: Write>(
+    out: W,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), WordSearchError> {
+    let font = load_font(args)?;
+    stream_png(out, args, &font, &|raster, args| {
+        page::draw_page(raster, &font, wordlist, puzzle, args, student, solution)
+    })
+}
+
+/// Return how many pages the puzzle takes: the page with the grid, and the pages the key continues onto, if any.
+pub fn page_count(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+) -> Result<usize, WordSearchError> {
+    let font = load_font(args)?;
+    let page = page_args(args);
+    let top = page_top(&font, &page, student);
+    let layout = Layout::new(&font, wordlist, puzzle, &page, top)?;
+    Ok(key_pages(wordlist, &layout, &page).len())
+}
+
+/// Render the pages the key continues onto, if it doesn't all fit on the page with the grid.
+pub fn make_continued_images(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+) -> Result<Vec<RgbImage>, WordSearchError> {
+    let font = load_font(args)?;
+    let (_, height) = sheet_size(args);
+    let page = page_args(args);
+    let top = page_top(&font, &page, student);
+    let layout = Layout::new(&font, wordlist, puzzle, &page, top)?;
+    key_pages(wordlist, &layout, &page)
+        .into_iter()
+        .skip(1)
+        .map(|words| {
+            render_rows(args, 0, height, &font, &|raster, args| {
+                page::draw_continued_page(raster, words, &layout, args.image_width)
+            })
+        })
+        .collect()
+}
+
+/// Write the pages the key continues onto as PNGs, a band of rows at a time like [`write_png`]. `out` opens the file
+/// for each page, counting from 0.
+pub fn write_continued_pngs<W: Write>(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    mut out: impl FnMut(usize) -> Result<W, WordSearchError>,
+) -> Result<(), WordSearchError> {
+    let font = load_font(args)?;
+    let page = page_args(args);
+    let top = page_top(&font, &page, student);
+    let layout = Layout::new(&font, wordlist, puzzle, &page, top)?;
+    for (i, words) in key_pages(wordlist, &layout, &page)
+        .into_iter()
+        .skip(1)
+        .enumerate()
+    {
+        stream_png(out(i)?, args, &font, &|raster, args| {
+            page::draw_continued_page(raster, words, &layout, args.image_width)
+        })?;
+    }
+    Ok(())
+}
+
+/// Return the size of the whole image: the page, and the margins for printing around it.
+pub fn sheet_size(args: &Args) -> (u32, u32) {
+    let (bleed, marks) = print_margins(args);
+    let edge = bleed + marks;
+    (args.image_width + 2 * edge, args.image_height + 2 * edge)
+}
+
+/// Return the options for drawing what's on the page, inside the margins for printing and the frame.
+fn page_args(args: &Args) -> Args {
+    match inside_print_margins(args) {
+        Some(inner) => page_args(&inner),
+        None => match inside_frame(args) {
+            Some((inner, _)) => page_args(&inner),
+            None => args.clone(),
+        },
+    }
+}
+
+/// Render `height` rows of the image, starting at row `top`: the margins for printing and the frame, if there are
+/// any, and the page inside them, drawn by `draw` with the options for the page.
+fn render_rows(
+    args: &Args,
+    top: u32,
+    height: u32,
+    font: &Font,
+    draw: &DrawPage,
+) -> Result<RgbImage, WordSearchError> {
+    let (width, _) = sheet_size(args);
+    let mut image = blank_image(width, height);
+    if let Some(inner) = inside_print_margins(args) {
+        let (bleed, marks) = print_margins(args);
+        let edge = bleed + marks;
+        paste_rows(&mut image, &inner, top, edge + bleed, font, draw)?;
+        if args.crop_marks {
+            // The marks are at the corners of the page, out in the margin beyond the bleed.
+            let (width, height) = sheet_size(args);
+            let (width, height) = (width as f32, height as f32);
+            let (near, far) = (edge as f32, (edge + args.image_width) as f32 - 1.0);
+            let (upper, lower) = (edge as f32, (edge + args.image_height) as f32 - 1.0);
+            let (marks, top) = (marks as f32, top as f32);
+            let mut line = |(x0, y0): (f32, f32), (x1, y1): (f32, f32)| {
+                drawing::draw_line_segment_mut(&mut image, (x0, y0 - top), (x1, y1 - top), TEXT)
+            };
+            for x in [near, far] {
+                line((x, 0.0), (x, marks));
+                line((x, height - marks), (x, height));
+            }
+            for y in [upper, lower] {
+                line((0.0, y), (marks, y));
+                line((width - marks, y), (width, y));
+            }
+        }
+    } else if let Some((inner, inset)) = inside_frame(args) {
+        paste_rows(&mut image, &inner, top, inset, font, draw)?;
+        if let Some(style) = args.frame {
+            let rect = (0, 0, args.image_width as i32, args.image_height as i32);
+            let mut raster = Raster::rows(&mut image, font, top as i32);
+            page::draw_frame(&mut raster, style, rect, args.frame_thickness as i32)?;
+        }
+    } else {
+        draw(&mut Raster::rows(&mut image, font, top as i32), args)?;
+    }
+    Ok(image)
+}
+
+/// Render the rows of the image for `inner` that fall among the rows of `image`, which start at row `top`, and put
+/// them `inset` in from the edges.
+fn paste_rows(
+    image: &mut RgbImage,
+    inner: &Args,
+    top: u32,
+    inset: u32,
+    font: &Font,
+    draw: &DrawPage,
+) -> Result<(), WordSearchError> {
+    let (_, inner_height) = sheet_size(inner);
+    let first = top.saturating_sub(inset);
+    let last = min((top + image.height()).saturating_sub(inset), inner_height);
+    if first < last {
+        let rows = render_rows(inner, first, last - first, font, draw)?;
+        let y = (inset + first) as i64 - top as i64;
+        image::imageops::replace(image, &rows, inset as i64, y);
+    }
+    Ok(())
+}
+
+/// Encode the image as a PNG a band of rows at a time, rendering each band as it's needed.
+fn stream_png<W: Write>(
+    out: W,
+    args: &Args,
+    font: &Font,
+    draw: &DrawPage,
+) -> Result<(), WordSearchError> {
+    let (width, height) = sheet_size(args);
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+    for top in (0..height).step_by(BAND_ROWS as usize) {
+        let rows = render_rows(args, top, min(BAND_ROWS, height - top), font, draw)?;
+        stream.write_all(rows.as_raw())?;
+    }
+    stream.finish()?;
+    Ok(())
+}
+
+/// Split the key into the part that fits on the page under the grid, then the parts for each page it continues
+/// onto. There's always at least the first part, though it may be empty.
+pub fn key_pages<'a>(wordlist: &'a [Entry], layout: &Layout, args: &Args) -> Vec<&'a [Entry]> {
+    // The continued pages have the label and a blank line at the top.
+    let rows = max(1, args.image_height as i32 / layout.key_stride - 3) as usize;
+    let mut pages = key::paginate(wordlist, layout.key_columns, layout.key_rows, rows);
+    if pages.is_empty() {
+        pages.push(&[]);
+    }
+    pages
+}
+
+/// Return where the grid starts on the page: below the title and the worksheet header, if there are any.
+pub fn page_top(font: &Font, args: &Args, student: Option<&str>) -> i32 {
+    let lines = args.title.is_some() as i32 + (args.worksheet_header || student.is_some()) as i32;
+    lines * header_height(font, header_scale(args.image_height))
+}
+
+/// Return how many times wider than it's tall the room for the grid is, once the header and the key have theirs, so a
+/// grid of about `cells` cells can be shaped to fill it.
+pub fn grid_aspect(wordlist: &[Entry], args: &Args, cells: usize) -> Result<f32, WordSearchError> {
+    let args = &page_args(args);
+    let font = load_font(args)?;
+    let top = page_top(&font, args, None);
+    // See how much room the key takes under a square grid of that size.
+    let side = max(1, (cells as f32).sqrt().ceil() as usize);
+    let puzzle = Puzzle {
+        grid: vec![vec![Some('M'); side]; side],
+        placements: vec![],
+        dropped: vec![],
+        attempts: vec![],
+    };
+    let layout = Layout::new(&font, wordlist, &puzzle, args, top)?;
+    let mut key_height = layout.key_gap + layout.key_rows as i32 * layout.key_stride;
+    if args.score_box {
+        key_height += 3 * layout.key_stride;
+    } else if args.show_code || args.show_checksum {
+        key_height += 2 * layout.key_stride;
+    }
+    // A key too long for the page continues onto more pages, instead of squashing the grid.
+    let height = max(
+        args.image_height as i32 - top - key_height,
+        args.image_height as i32 / 2,
+    );
+    Ok(args.image_width as f32 / height as f32)
+}
+
+/// Return the smallest page that everything fits on at the sizes it would be drawn at on this one: the grid, the
+/// widest line of the key, and at least one line of the key on the pages it continues onto. With --grid-font-size or
+/// --key-font-size, that can be bigger than the page.
+pub fn needed_size(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+) -> Result<(u32, u32), WordSearchError> {
+    // The margins for printing, and a frame around the page, take the same room on any size of page.
+    let inner = inside_print_margins(args).or_else(|| inside_frame(args).map(|(inner, _)| inner));
+    if let Some(inner) = inner {
+        let (width, height) = needed_size(wordlist, puzzle, &inner, student)?;
+        return Ok((
+            width + args.image_width - inner.image_width,
+            height + args.image_height - inner.image_height,
+        ));
+    }
+    let font = load_font(args)?;
+    let top = page_top(&font, args, student);
+    let layout = Layout::new(&font, wordlist, puzzle, args, top)?;
+    let grid = &puzzle.grid;
+    let grid_width = layout.left + grid[0].len() as i32 * layout.grid_stride + layout.frame;
+    let grid_height = layout.top + grid.len() as i32 * layout.grid_stride + layout.frame;
+    let width = max(grid_width, key_width(&font, wordlist, layout.key_scale));
+    // A continued page has its label, a blank line, and then the key.
+    let height = max(grid_height, 3 * layout.key_stride);
+    Ok((width as u32, height as u32))
+}
+
+/// Render only the grid, at the same size it would be on the page. If `solution` is set, mark the answers.
+pub fn make_grid_image(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    solution: bool,
+) -> Result<RgbImage, WordSearchError> {
+    let font = load_font(args)?;
+    let layout = Layout::new(&font, wordlist, puzzle, args, 0)?;
+    let width = layout.left + puzzle.grid[0].len() as i32 * layout.grid_stride + layout.frame;
+    let height = layout.top + puzzle.grid.len() as i32 * layout.grid_stride + layout.frame;
+    let mut image = blank_image(width as u32, height as u32);
+    page::draw_grid(
+        &mut Raster::new(&mut image, &font),
+        &font,
+        puzzle,
+        &layout,
+        args,
+        solution,
+    )?;
+    Ok(image)
+}
+
+/// Render only the key, at the same size it would be on the page.
+pub fn make_key_image(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+) -> Result<RgbImage, WordSearchError> {
+    let font = load_font(args)?;
+    let layout = Layout::new(&font, wordlist, puzzle, args, 0)?;
+    let key_rows = key::rows(wordlist, layout.key_columns) as i32;
+    let height = key_rows * layout.key_stride + layout.key_stride / 2;
+    let mut image = blank_image(args.image_width, height as u32);
+    let mut raster = Raster::new(&mut image, &font);
+    page::draw_key(&mut raster, wordlist, &layout, 0, args.image_width)?;
+    Ok(image)
+}
+
+/// The built-in font.
+const SANS: &[u8] = include_bytes!("../FreeSans.ttf");
+
+/// OpenDyslexic, for --font dyslexic.
+#[cfg(feature = "dyslexia-font")]
+const DYSLEXIC: &[u8] = include_bytes!("../fonts/OpenDyslexic-Regular.otf");
+
+/// Load the font the text is drawn in.
+pub fn load_font(args: &Args) -> Result<Font, WordSearchError> {
+    let data =
+        match args.typeface() {
+            Typeface::Sans => SANS,
+            #[cfg(feature = "dyslexia-font")]
+            Typeface::Dyslexic => DYSLEXIC,
+            #[cfg(not(feature = "dyslexia-font"))]
+            Typeface::Dyslexic => return Err(WordSearchError::Font(
+                "The dyslexic font isn't built in; build wordsearch with --features dyslexia-font"
+                    .to_string(),
+            )),
+        };
+    Font::new(data).ok_or(WordSearchError::Font(
+        "Couldn't parse built-in font data".to_string(),
+    ))
+}
+
+/// Mark the answers on an image that wasn't drawn here, like a photo of a puzzle, the way a solution marks them.
+pub fn mark_image(
+    image: &mut RgbImage,
+    marks: &[Mark],
+    args: &Args,
+) -> Result<(), WordSearchError> {
+    let font = load_font(args)?;
+    Raster::new(image, &font).marks(
+        marks,
+        args.mark_style,
+        args.mark_color(),
+        args.mark_opacity(),
+    )
+}
+
+/// Return how wide the bleed around the page is, and how long the crop marks outside it are, in pixels.
+fn print_margins(args: &Args) -> (u32, u32) {
+    let bleed = args.bleed.map_or(0, |bleed| bleed.pixels(args.dpi));
+    let marks = match args.crop_marks {
+        true => CROP_MARK_LENGTH.pixels(args.dpi),
+        false => 0,
+    };
+    (bleed, marks)
+}
+
+/// With --bleed or --crop-marks, return the options for drawing what goes on the page: everything but the margins
+/// for printing, kept as far inside the edge of the page as the bleed goes outside it.
+fn inside_print_margins(args: &Args) -> Option<Args> {
+    if args.bleed.is_none() && !args.crop_marks {
+        return None;
+    }
+    let (bleed, _) = print_margins(args);
+    let mut inner = args.clone();
+    inner.bleed = None;
+    inner.crop_marks = false;
+    inner.image_width = args.image_width.saturating_sub(2 * bleed).max(1);
+    inner.image_height = args.image_height.saturating_sub(2 * bleed).max(1);
+    Some(inner)
+}
+
+/// Draws what goes on a page, with the options for the page.
+type DrawPage<'a> = dyn Fn(&mut Raster, &Args) -> Result<(), WordSearchError> + 'a;
+
+/// Draws pages as raster images.
+struct Raster<'a> {
+    image: &'a mut RgbImage,
+    font: &'a Font,
+    /// Whether what's being drawn is hidden, like the answers on a page that isn't a solution.
+    hidden: bool,
+    /// The row of the page the image starts at, when it's only a band of the page.
+    top: i32,
+}
+
+impl<'a> Raster<'a> {
+    fn new(image: &'a mut RgbImage, font: &'a Font) -> Self {
+        Self::rows(image, font, 0)
+    }
+
+    /// Draw the rows of the page from `top` onto the image.
+    fn rows(image: &'a mut RgbImage, font: &'a Font, top: i32) -> Self {
+        Self {
+            image,
+            font,
+            hidden: false,
+            top,
+        }
+    }
+}
+
+impl Renderer for Raster<'_> {
+    fn text(&mut self, text: &Text) -> Result<(), WordSearchError> {
+        if self.hidden {
+            return Ok(());
+        }
+        let (font, scale) = (self.font, text.scale);
+        let (width, _) = typeset::size(scale, font, text.text);
+        let x = match text.align {
+            Align::Left => text.x,
+            Align::Center(space) => text.x + (space - width) / 2,
+            Align::Right => text.x - width,
+        };
+        let y = text.y - self.top;
+        let color = text.color.unwrap_or(TEXT);
+        typeset::draw(self.image, color, x, y, scale, font, text.text);
+        if text.bold {
+            // The font has no bold face, so thicken the text by drawing it again a little to the right.
+            let offset = max(1, (scale.x / 30.0) as i32);
+            typeset::draw(self.image, color, x + offset, y, scale, font, text.text);
+        }
+        Ok(())
+    }
+
+    fn rectangle(&mut self, rectangle: &Rectangle) -> Result<(), WordSearchError> {
+        if self.hidden {
+            return Ok(());
+        }
+        let (width, height) = (rectangle.width as u32, rectangle.height as u32);
+        let at = Rect::at(rectangle.x, rectangle.y - self.top);
+        if let Some(fill) = rectangle.fill {
+            drawing::draw_filled_rect_mut(self.image, at.of_size(width, height), fill);
+        }
+        // The outline is on the pixels along both edges, and thicker outlines spread out from them both ways.
+        if let Some(stroke) = rectangle.stroke {
+            let line_width = max(1, rectangle.line_width);
+            for d in -(line_width - 1) / 2..=line_width / 2 {
+                let (width, height) = (rectangle.width + 1 - 2 * d, rectangle.height + 1 - 2 * d);
+                if width > 0 && height > 0 {
+                    let at = Rect::at(rectangle.x + d, rectangle.y + d - self.top);
+                    drawing::draw_hollow_rect_mut(
+                        self.image,
+                        at.of_size(width as u32, height as u32),
+                        stroke,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn frame_part(&mut self, part: &FramePart) -> Result<(), WordSearchError> {
+        match *part {
+            FramePart::Band {
+                x,
+                y,
+                width,
+                height,
+                thickness,
+                radius,
+            } => {
+                let outer = (x, y, width, height, radius);
+                let inner = (
+                    x + thickness,
+                    y + thickness,
+                    width - 2 * thickness,
+                    height - 2 * thickness,
+                    max(0, radius - thickness),
+                );
+                let image_width = self.image.width() as i32;
+                let (left, right) = (max(0, x), min(x + width, image_width));
+                let top = max(0, y - self.top);
+                let bottom = min(y + height - self.top, self.image.height() as i32);
+                if left >= right || top >= bottom {
+                    return Ok(());
+                }
+                // Only the band's own rows are visited, each on its own thread.
+                let row_bytes = 3 * image_width as usize;
+                self.image.as_mut()[top as usize * row_bytes..bottom as usize * row_bytes]
+                    .par_chunks_mut(row_bytes)
+                    .enumerate()
+                    .for_each(|(i, row)| {
+                        let py = self.top + top + i as i32;
+                        for px in left..right {
+                            if in_rounded_rect((px, py), outer) && !in_rounded_rect((px, py), inner)
+                            {
+                                row[3 * px as usize..3 * px as usize + 3].copy_from_slice(&TEXT.0);
+                            }
+                        }
+                    });
+            }
+            FramePart::Square { x, y, size } => {
+                let square = Rect::at(x, y - self.top).of_size(size as u32, size as u32);
+                drawing::draw_filled_rect_mut(self.image, square, TEXT);
+            }
+            FramePart::Diamond { x, y, radius } => {
+                let y = y - self.top;
+                let points = [
+                    Point::new(x, y - radius),
+                    Point::new(x + radius, y),
+                    Point::new(x, y + radius),
+                    Point::new(x - radius, y),
+                ];
+                drawing::draw_polygon_mut(self.image, &points, TEXT);
+            }
+        }
+        Ok(())
+    }
+
+    fn marks(
+        &mut self,
+        marks: &[Mark],
+        style: MarkStyle,
+        color: Rgb<u8>,
+        opacity: f32,
+    ) -> Result<(), WordSearchError> {
+        if self.hidden {
+            return Ok(());
+        }
+        // Draw the marks onto a mask, then blend it over the letters so they stay readable where the marks overlap
+        // them.
+        let mut mask = GrayImage::new(self.image.width(), self.image.height());
+        for mark in marks {
+            draw_answer(&mut mask, mark, style, self.top);
+        }
+        blend_mask(self.image, &mask, color, opacity);
+        Ok(())
+    }
+
+    fn begin(&mut self, group: Group) -> Result<(), WordSearchError> {
+        if let Group::Solution { shown } = group {
+            self.hidden = !shown;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, group: Group) -> Result<(), WordSearchError> {
+        if let Group::Solution { .. } = group {
+            self.hidden = false;
+        }
+        Ok(())
+    }
+}
+
+/// Return whether the middle of this pixel is inside the rectangle at (x, y) with this width and height, and its
+/// corners rounded to this radius.
+fn in_rounded_rect(
+    (px, py): (i32, i32),
+    (x, y, width, height, radius): (i32, i32, i32, i32, i32),
+) -> bool {
+    if width <= 0 || height <= 0 {
+        return false;
+    }
+    let (px, py) = (px as f32 + 0.5, py as f32 + 0.5);
+    let radius = min(radius, min(width, height) / 2) as f32;
+    let (x, y, width, height) = (x as f32, y as f32, width as f32, height as f32);
+    let cx = px.clamp(x + radius, x + width - radius);
+    let cy = py.clamp(y + radius, y + height - radius);
+    let inside = (x..x + width).contains(&px) && (y..y + height).contains(&py);
+    inside && (px - cx).powi(2) + (py - cy).powi(2) <= radius * radius
+}
+
+fn blank_image(width: u32, height: u32) -> RgbImage {
+    let white = vec![255; 3 * width as usize * height as usize];
+    RgbImage::from_raw(width, height, white).expect("buffer is the size of the image")
+}
+
+/// Return how many columns of the key fit across the page, leaving the width of an "M" after the widest word or
+/// heading.
+fn key_columns(font: &Font, wordlist: &[Entry], scale: Scale, width: u32) -> usize {
+    let (gap, _) = typeset::size(scale, font, "M");
+    let widest = key_width(font, wordlist, scale);
+    let columns = width as usize / (widest + gap).max(1) as usize;
+    columns.clamp(1, wordlist.len().max(1))
+}
+
+/// Return the width of the widest word or heading in the key.
+fn key_width(font: &Font, wordlist: &[Entry], scale: Scale) -> i32 {
+    wordlist
+        .iter()
+        .flat_map(|e| [Some(&e.word), e.category.as_ref()])
+        .flatten()
+        .map(|text| typeset::size(scale, font, text).0)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Return the coordinate labels around the grid, with the top left corner of the cell in the margin each goes in:
+/// letters over the columns and numbers beside the rows.
+pub fn coordinate_labels(grid: &Cells, layout: &Layout) -> Vec<(i32, i32, String)> {
+    let stride = layout.grid_stride;
+    let columns = (0..grid[0].len()).map(|x| {
+        let label = grid::column_label(x);
+        (layout.left + x as i32 * stride, layout.top - stride, label)
+    });
+    let rows = (0..grid.len()).map(|y| {
+        let label = (y + 1).to_string();
+        (layout.left - stride, layout.top + y as i32 * stride, label)
+    });
+    columns.chain(rows).collect()
+}
+
+/// Return how far below the point where we draw a letter its middle ends up.
+pub fn letter_middle(font: &Font, scale: Scale) -> i32 {
+    let ascent = font.v_metrics(scale).ascent;
+    font.layout("M", scale, rusttype::point(0.0, ascent))
+        .next()
+        .and_then(|glyph| glyph.pixel_bounding_box())
+        .map_or(0, |bb| (bb.min.y + bb.max.y) / 2)
+}
+
+/// Paint the color over the image wherever the mask is set, a row at a time in parallel.
+fn blend_mask(image: &mut RgbImage, mask: &GrayImage, color: Rgb<u8>, opacity: f32) {
+    let width = image.width() as usize;
+    image
+        .par_chunks_mut(3 * width)
+        .zip(mask.par_chunks(width))
+        .for_each(|(row, mask)| {
+            for (pixel, &m) in row.chunks_exact_mut(3).zip(mask) {
+                if m == 0 {
+                    continue;
+                }
+                let alpha = opacity * m as f32 / 255.0;
+                for (channel, c) in pixel.iter_mut().zip(color.0) {
+                    *channel = (*channel as f32 * (1.0 - alpha) + c as f32 * alpha).round() as u8;
+                }
+            }
+        });
+}
+
+/// Mark where a word is in the grid, in the given style, by drawing onto a mask.
+fn draw_answer(image: &mut GrayImage, mark: &Mark, style: MarkStyle, top: i32) {
+    let (start, end, stride) = (mark.start, mark.end, mark.stride);
+    // The shapes are worked out where they are on the page, and then moved up to the band of it the mask holds.
+    let mut fill = |points: Vec<Point<i32>>| {
+        let points: Vec<_> = points.iter().map(|p| Point::new(p.x, p.y - top)).collect();
+        drawing::draw_polygon_mut(image, &points, MARK);
+    };
+    match style {
+        MarkStyle::Line => fill(capsule(start, end, stride / 12.0)),
+        MarkStyle::Highlight => fill(capsule(start, end, stride / 3.0)),
+        MarkStyle::Oval => {
+            // Trace the outline with short, thick line segments.
+            let outline = capsule(start, end, stride * 0.42);
+            let next = outline.iter().cycle().skip(1);
+            for (p, q) in outline.iter().zip(next) {
+                let (p, q) = ((p.x as f32, p.y as f32), (q.x as f32, q.y as f32));
+                fill(capsule(p, q, 1.5));
+            }
+        }
+        MarkStyle::CircleLetters => {
+            let radius = (stride * 0.45) as i32;
+            for &(cx, cy) in &mark.letters {
+                for r in [radius - 1, radius] {
+                    drawing::draw_hollow_circle_mut(image, (cx as i32, cy as i32 - top), r, MARK);
+                }
+            }
+        }
+    }
+}
+
+/// Return a polygon approximating a stadium shape: the points within `radius` of the line from `start` to `end`.
+fn capsule(start: (f32, f32), end: (f32, f32), radius: f32) -> Vec<Point<i32>> {
+    let ((x0, y0), (x1, y1)) = (start, end);
+    let angle = f32::atan2(y1 - y0, x1 - x0);
+    let steps = 16;
+    let mut points: Vec<Point<i32>> = vec![];
+    // Go halfway around a circle at the end, then halfway around a circle at the start.
+    for (cx, cy, from) in [
+        (x1, y1, angle - std::f32::consts::FRAC_PI_2),
+        (x0, y0, angle + std::f32::consts::FRAC_PI_2),
+    ] {
+        for step in 0..=steps {
+            let theta = from + std::f32::consts::PI * step as f32 / steps as f32;
+            let point = Point::new(
+                (cx + radius * theta.cos()).round() as i32,
+                (cy + radius * theta.sin()).round() as i32,
+            );
+            if points.last() != Some(&point) && points.first() != Some(&point) {
+                points.push(point);
+            }
+        }
+    }
+    points
+}
+
+/// The size of the text in the worksheet header.
+pub fn header_scale(page_height: u32) -> Scale {
+    let size = page_height as f32 / 32.0;
+    Scale { x: size, y: size }
+}
+
+/// How much room the worksheet header takes at the top of the page.
+pub fn header_height(font: &Font, scale: Scale) -> i32 {
+    let (_, text_height) = typeset::size(scale, font, "M");
+    text_height * 2
+}
+
+/// The name and date parts of the worksheet header.
+pub fn header_text(student: Option<&str>) -> (String, &'static str) {
+    let name = match student {
+        Some(student) => format!("Name: {}", student),
+        None => "Name: ____________________".to_string(),
+    };
+    (name, "Date: ____________")
+}
+
+/// The text in the score box.
+pub fn score_text(num_words: usize) -> String {
+    let blanks = "_".repeat(num_words.to_string().len() + 1);
+    format!("Time: ______   Found: {}/{}", blanks, num_words)
+}
+
+/// Convert a font size in pixels, which is the size of the em square, into a rusttype scale, which is the height
+/// from descent to ascent.
+pub fn font_scale(font: &Font, size: f32) -> Scale {
+    let v = font.v_metrics_unscaled();
+    let height = size * (v.ascent - v.descent) / font.units_per_em() as f32;
+    Scale {
+        x: height,
+        y: height,
+    }
+}
+
+/// The key is drawn a little smaller than the grid.
+fn key_scale(grid_text_height: i32) -> Scale {
+    Scale {
+        x: grid_text_height as f32 * 0.8,
+        y: grid_text_height as f32 * 0.8,
+    }
+}
+
+/// Return the biggest text height at which a cell, as wide as an "M" times `spacing` and as tall as the text above
+/// the bottom of the lowest of the `letters`, fits in `desired_stride`. The font's metrics grow in proportion to the
+/// text height, so it's worked out from their sizes at a height of one pixel.
+fn compute_text_height(font: &Font, desired_stride: i32, spacing: f32, letters: &str) -> f32 {
+    let unit = Scale::uniform(1.0);
+    let ascent = font.v_metrics(unit).ascent;
+    let bounds = |c| font.glyph(c).scaled(unit).exact_bounding_box();
+    let right = bounds('M').map_or(0.0, |bb| bb.max.x);
+    let bottom = letters
+        .chars()
+        .filter_map(bounds)
+        .map(|bb| bb.max.y)
+        .fold(0.0, f32::max);
+    // text_size rounds the edges of the "M" out to whole pixels, and the width times the spacing is rounded down.
+    let widest = ((desired_stride + 1) as f32 / spacing).ceil() - 1.0;
+    let by_width = match right > 0.0 {
+        true => widest / right,
+        false => f32::INFINITY,
+    };
+    let by_height = desired_stride as f32 / (ascent + bottom);
+    // Stay just inside, so rounding can't push the stride over.
+    by_width.min(by_height) * 0.9999
+}
+
+/// Return an iterator of (X, Y) coordinates in the specified number of columns.
+pub fn column_iter(
+    image_width: u32,
+    y_stride: u32,
+    num_columns: u32,
+    length: usize,
+) -> impl Iterator<Item = (i32, i32)> {
+    let mut result = vec![];
+    let col_width = image_width / num_columns;
+    for column in 0..num_columns {
+        let mut num_rows = length as u32 / num_columns;
+        if length as u32 % num_columns > column {
+            num_rows += 1;
+        }
+        for row in 0..num_rows {
+            result.push(((column * col_width) as i32, (row * y_stride) as i32));
+        }
+    }
+    result.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Error;
+
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rusttype::Scale;
+
+    use crate::config::Args;
+    use crate::grid::Grid;
+    use crate::page;
+    use crate::wordlist::Entry;
+
+    use super::{
+        column_iter, compute_text_height, in_rounded_rect, letter_colors, load_font, make_image,
+        render_rows, sheet_size, typeset, Layout, RAINBOW,
+    };
+
+    #[test]
+    fn test_letter_colors() {
+        let grid = vec![vec![Some('A'); 3]; 2];
+        let args = Args::try_parse_from(["wordsearch", "--letter-colors", "rainbow"]).unwrap();
+        let colors = letter_colors(&grid, &args).unwrap().unwrap();
+        assert_eq!(vec![RAINBOW[0], RAINBOW[1], RAINBOW[2]], colors[0]);
+        assert_eq!(RAINBOW[1], colors[1][0]);
+        let args = Args::try_parse_from(["wordsearch"]).unwrap();
+        assert!(letter_colors(&grid, &args).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_column_iter() -> Result<(), Error> {
+        let expecteds = [(0, 0), (33, 0), (66, 0)];
+        for len in 0..=expecteds.len() {
+            let observed: Vec<_> = column_iter(100, 10, 3, len).collect();
+            let expected = expecteds[0..len].to_vec();
+            assert_eq!(expected, observed);
+        }
+
+        let observed: Vec<_> = column_iter(100, 10, 3, 4).collect();
+        let expected = vec![(0, 0), (0, 10), (33, 0), (66, 0)];
+        assert_eq!(expected, observed);
+
+        let observed: Vec<_> = column_iter(100, 10, 3, 5).collect();
+        let expected = vec![(0, 0), (0, 10), (33, 0), (33, 10), (66, 0)];
+        assert_eq!(expected, observed);
+
+        let observed: Vec<_> = column_iter(100, 10, 3, 6).collect();
+        let expected = vec![(0, 0), (0, 10), (33, 0), (33, 10), (66, 0), (66, 10)];
+        assert_eq!(expected, observed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_rounded_rect() {
+        let square = (10, 10, 20, 20, 0);
+        assert!(in_rounded_rect((10, 10), square));
+        assert!(in_rounded_rect((29, 29), square));
+        assert!(!in_rounded_rect((30, 20), square));
+        let rounded = (10, 10, 20, 20, 8);
+        assert!(!in_rounded_rect((10, 10), rounded));
+        assert!(in_rounded_rect((10, 20), rounded));
+        assert!(in_rounded_rect((14, 14), rounded));
+    }
+
+    #[test]
+    fn test_render_rows() -> Result<(), Error> {
+        let words = ["BISHOP", "CASTLE"].map(|w| Entry::new(w.to_string()));
+        let grid_words = words.iter().map(Entry::grid_form).collect();
+        let puzzle =
+            Grid::new(grid_words, Some(8), Some(8)).generate(&mut StdRng::seed_from_u64(1))?;
+        let args = Args::try_parse_from([
+            "wordsearch",
+            "-x",
+            "300",
+            "-y",
+            "400",
+            "--frame",
+            "fancy",
+            "--bleed",
+            "4px",
+            "--crop-marks",
+            "--dpi",
+            "72",
+        ])?;
+        let whole = make_image(&words, &puzzle, &args, None, true)?;
+
+        // The bands, put together, are the whole image.
+        let font = load_font(&args)?;
+        let (width, height) = sheet_size(&args);
+        assert_eq!((width, height), whole.dimensions());
+        let mut rows = vec![];
+        for top in (0..height).step_by(37) {
+            let band = render_rows(&args, top, 37.min(height - top), &font, &|raster, args| {
+                page::draw_page(raster, &font, &words, &puzzle, args, None, true)
+            })?;
+            rows.extend_from_slice(band.as_raw());
+        }
+        assert!(rows == *whole.as_raw());
+        Ok(())
+    }
+
+    #[test]
+    fn test_font_size_limits() -> Result<(), Error> {
+        assert!(Args::try_parse_from(["wordsearch", "--grid-font-size", "inf"]).is_err());
+        assert!(Args::try_parse_from(["wordsearch", "--key-font-size", "NaN"]).is_err());
+
+        let puzzle = Grid::new(vec!["CAT".to_string()], Some(4), Some(4))
+            .generate(&mut StdRng::seed_from_u64(1))?;
+        let args = Args::try_parse_from(["wordsearch", "-y", "400", "--grid-font-size", "1e9"])?;
+        let font = load_font(&args)?;
+        assert!(Layout::new(&font, &[], &puzzle, &args, 0).is_err());
+        let args = Args::try_parse_from(["wordsearch", "-y", "400", "--key-font-size", "400"])?;
+        assert!(Layout::new(&font, &[], &puzzle, &args, 0).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_text_height() -> Result<(), Error> {
+        let font = load_font(&Args::try_parse_from(["wordsearch"])?)?;
+        for spacing in [1.0, 1.5, 2.0] {
+            for desired in 1..500 {
+                let height = compute_text_height(&font, desired, spacing, "M");
+                let (w, h) = typeset::size(Scale::uniform(height), &font, "M");
+                let stride = std::cmp::max((w as f32 * spacing) as i32, h);
+                assert!(stride <= desired, "{stride} > {desired} at {spacing}");
+                assert!(stride + 2 >= desired, "{stride} << {desired} at {spacing}");
+            }
+        }
+        Ok(())
+    }
+}
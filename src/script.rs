@@ -0,0 +1,172 @@
+//! The writing systems words can be in, so a puzzle can mix them.
+
+/// A writing system whose alphabet we know. Digits count as one, so words with numbers in them get digits in the
+/// filler too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Digits,
+}
+
+impl Script {
+    /// Return the script a letter is written in, if it's one we know.
+    pub fn of(c: char) -> Option<Self> {
+        match c as u32 {
+            0x0041..=0x024F | 0x1E00..=0x1EFF if c.is_alphabetic() => Some(Self::Latin),
+            0x0370..=0x03FF | 0x1F00..=0x1FFF if c.is_alphabetic() => Some(Self::Greek),
+            0x0400..=0x052F if c.is_alphabetic() => Some(Self::Cyrillic),
+            0x0030..=0x0039 => Some(Self::Digits),
+            _ => None,
+        }
+    }
+
+    /// The capital letters of the script's basic alphabet, or the ten digits.
+    pub fn alphabet(&self) -> Vec<char> {
+        match self {
+            Self::Latin => ('A'..='Z').collect(),
+            // There's no capital final sigma, so skip the gap where it would be.
+            Self::Greek => ('Α'..='Ω').filter(|c| c.is_alphabetic()).collect(),
+            Self::Cyrillic => ('А'..='Я').collect(),
+            Self::Digits => ('0'..='9').collect(),
+        }
+    }
+}
+
+/// Return the scripts the word is written in, in the order they first appear.
+pub fn scripts(word: &str) -> Vec<Script> {
+    let mut scripts = vec![];
+    for script in word.chars().filter_map(Script::of) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    scripts
+}
+
+/// Return the letters filler should be drawn from for a puzzle with these words: the whole alphabet of each script
+/// they're written in, plus any letters from scripts we don't know. A puzzle of numbers alone gets only digits. Without
+/// any letters at all, that's A to Z.
+pub fn filler_alphabet(words: &[String]) -> Vec<char> {
+    let mut known: Vec<Script> = words.iter().flat_map(|w| scripts(w)).collect();
+    known.sort();
+    known.dedup();
+    let mut letters: Vec<char> = known.iter().flat_map(Script::alphabet).collect();
+    for c in words.iter().flat_map(|w| w.chars()) {
+        if Script::of(c).is_none() && !letters.contains(&c) {
+            letters.push(c);
+        }
+    }
+    if letters.is_empty() {
+        letters = Script::Latin.alphabet();
+    }
+    letters
+}
+
+/// Return whether the character is an emoji that stands on its own, which can fill a cell of the grid like a letter.
+/// Skin tones and the halves of flags only mean something joined to another emoji, and a cell holds one character,
+/// so they don't count.
+pub fn is_emoji(c: char) -> bool {
+    match c as u32 {
+        0x1F1E6..=0x1F1FF | 0x1F3FB..=0x1F3FF => false,
+        0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x1F000..=0x1FAFF => true,
+        _ => false,
+    }
+}
+
+/// Return whether the character joins emoji together or changes one, making an emoji of more than one character
+/// that won't fit in a cell: the zero-width joiner of 👩‍💻, skin tones, the halves of flags, keycaps, and tags.
+pub fn is_emoji_part(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x200D | 0x20E3 | 0x1F1E6..=0x1F1FF | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F
+    )
+}
+
+/// Pairs of letters that are easily mistaken for each other, especially by dyslexic readers: mirror images, letters
+/// turned over, and letters that look like digits.
+const CONFUSABLE: [(char, char); 10] = [
+    ('b', 'd'),
+    ('p', 'q'),
+    ('n', 'u'),
+    ('m', 'w'),
+    ('M', 'W'),
+    ('N', 'Z'),
+    ('I', 'l'),
+    ('O', '0'),
+    ('l', '1'),
+    ('S', '5'),
+];
+
+/// Return the letters that are easily mistaken for a letter of one of these words, and aren't in any of them
+/// themselves, so filler can leave them out.
+pub fn confusable_with(words: &[String]) -> Vec<char> {
+    let used = |c: &char| words.iter().any(|w| w.contains(*c));
+    let mut letters: Vec<char> = CONFUSABLE
+        .iter()
+        .flat_map(|&(a, b)| [(a, b), (b, a)])
+        .filter(|(a, b)| used(a) && !used(b))
+        .map(|(_, b)| b)
+        .collect();
+    letters.sort();
+    letters.dedup();
+    letters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{confusable_with, filler_alphabet, is_emoji, is_emoji_part, scripts, Script};
+
+    #[test]
+    fn test_filler_alphabet() {
+        assert_eq!([Script::Greek].to_vec(), scripts("ΓΆΤΑ"));
+        assert_eq!(
+            [Script::Latin, Script::Cyrillic].to_vec(),
+            scripts("CAT КОТ")
+        );
+
+        let words = ["ΓΑΤΑ", "CAT"].map(String::from);
+        let alphabet = filler_alphabet(&words);
+        assert_eq!(26 + 24, alphabet.len());
+        assert_eq!(
+            ['A', 'Z', 'Α', 'Ω'],
+            [alphabet[0], alphabet[25], alphabet[26], alphabet[49]]
+        );
+        assert_eq!(
+            ('A'..='Z').collect::<Vec<_>>(),
+            filler_alphabet(&["CAFÉ".to_string()])
+        );
+        assert_eq!(4, filler_alphabet(&["שלום".to_string()]).len());
+        assert_eq!(26 + 10, filler_alphabet(&["ROUTE66".to_string()]).len());
+        assert_eq!(
+            ('0'..='9').collect::<Vec<_>>(),
+            filler_alphabet(&["314159".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_is_emoji() {
+        assert!(is_emoji('🐶'));
+        assert!(is_emoji('❤'));
+        assert!(!is_emoji('A'));
+        assert!(!is_emoji('\u{1F3FD}'));
+        assert!(!is_emoji('\u{FE0F}'));
+        assert_eq!(vec!['🐶', '🐱'], filler_alphabet(&["🐶🐱".to_string()]));
+
+        assert!(is_emoji_part('\u{200D}'));
+        assert!(is_emoji_part('\u{1F3FD}'));
+        assert!(is_emoji_part('\u{1F1FA}'));
+        assert!(!is_emoji_part('🐶'));
+        assert!(!is_emoji_part('\u{FE0F}'));
+    }
+
+    #[test]
+    fn test_confusable_with() {
+        assert_eq!(
+            ['0', 'W', 'Z'].to_vec(),
+            confusable_with(&["MOON".to_string()])
+        );
+        assert!(confusable_with(&["MAW".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+//! A simulated solver, to estimate how long a puzzle takes a person to solve.
+//!
+//! For each word in the key, the solver picks a letter to look for: the first one, or the one that's rarest in the
+//! grid. It scans the grid row by row for that letter, and wherever it finds it, checks each direction for the rest
+//! of the word, starting with the ones people read most easily. Like a person, it sometimes overlooks a letter or
+//! misreads a word, and has to keep scanning or start over.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::grid::{self, Direction, Puzzle};
+
+/// How many times to solve the puzzle, to average out the solver's luck.
+const RUNS: usize = 20;
+
+/// Seconds to read a word in the key and decide what to look for.
+const READ_TIME: f32 = 1.5;
+
+/// Seconds to glance at a cell while scanning for a letter.
+const SCAN_TIME: f32 = 0.12;
+
+/// Seconds to check whether the word goes on from a letter in one direction.
+const CHECK_TIME: f32 = 0.35;
+
+/// The chance of overlooking the letter being looked for.
+const MISS_RATE: f32 = 0.1;
+
+/// The chance of misreading a word that's there, by direction: words are easiest to see left to right, then top to
+/// bottom, and hardest backwards and on the diagonals.
+fn misread_rate(direction: Direction) -> f32 {
+    match direction {
+        Direction::East => 0.02,
+        Direction::South => 0.05,
+        Direction::Southeast | Direction::Northeast => 0.1,
+        Direction::West | Direction::North => 0.15,
+        Direction::Southwest | Direction::Northwest => 0.2,
+    }
+}
+
+/// The order the solver checks the directions in, from the easiest to read.
+const CHECK_ORDER: [Direction; 8] = [
+    Direction::East,
+    Direction::South,
+    Direction::Southeast,
+    Direction::Northeast,
+    Direction::West,
+    Direction::North,
+    Direction::Southwest,
+    Direction::Northwest,
+];
+
+/// After scanning the whole grid this many times without finding a word, the solver gives up on it.
+const MAX_PASSES: usize = 10;
+
+/// Estimate how long the puzzle takes to solve, on average. The estimate is the same each time for the same puzzle.
+pub fn solve_time(puzzle: &Puzzle) -> Duration {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    // Letters count the same whatever case they're in.
+    for letter in puzzle.grid.iter().flatten().flatten() {
+        *counts
+            .entry(letter.to_uppercase().next().unwrap_or(*letter))
+            .or_default() += 1;
+    }
+    let total: f32 = (0..RUNS)
+        .map(|_| {
+            puzzle
+                .placements
+                .iter()
+                .map(|p| find_word(puzzle, &p.word, &counts, &mut rng))
+                .sum::<f32>()
+        })
+        .sum();
+    Duration::from_secs_f32(total / RUNS as f32)
+}
+
+/// Return how many seconds it took to find the word.
+fn find_word(
+    puzzle: &Puzzle,
+    word: &str,
+    counts: &HashMap<char, usize>,
+    rng: &mut impl Rng,
+) -> f32 {
+    let letters: Vec<char> = word.chars().collect();
+    let same =
+        |cell: Option<char>, letter: char| cell.is_some_and(|c| grid::same_letter(c, letter));
+    let count = |i: &usize| counts.get(&letters[*i]).copied().unwrap_or(0);
+    // Some solvers always look for the first letter; others look for the one that stands out.
+    let anchor = match rng.gen_bool(0.5) {
+        true => 0,
+        false => (0..letters.len()).min_by_key(count).unwrap_or(0),
+    };
+    let (width, height) = (puzzle.grid[0].len() as isize, puzzle.grid.len() as isize);
+    let mut time = READ_TIME;
+    for _ in 0..MAX_PASSES {
+        for y in 0..height {
+            for x in 0..width {
+                time += SCAN_TIME;
+                if !same(puzzle.grid[y as usize][x as usize], letters[anchor])
+                    || rng.gen::<f32>() < MISS_RATE
+                {
+                    continue;
+                }
+                for direction in CHECK_ORDER {
+                    time += CHECK_TIME;
+                    let (dx, dy) = direction.next();
+                    let found = letters.iter().enumerate().all(|(i, letter)| {
+                        let offset = i as isize - anchor as isize;
+                        let (cx, cy) = (x + dx * offset, y + dy * offset);
+                        (0..width).contains(&cx)
+                            && (0..height).contains(&cy)
+                            && same(puzzle.grid[cy as usize][cx as usize], *letter)
+                    });
+                    if found && rng.gen::<f32>() >= misread_rate(direction) {
+                        return time;
+                    }
+                }
+            }
+        }
+    }
+    time
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::grid::{Direction, Grid};
+
+    use super::solve_time;
+
+    #[test]
+    fn test_solve_time() {
+        let words = vec![
+            "KNIGHT".to_string(),
+            "BISHOP".to_string(),
+            "CASTLE".to_string(),
+        ];
+        let puzzle = |directions: &[Direction]| {
+            Grid::new(words.clone(), Some(12), Some(12))
+                .with_directions(directions)
+                .generate(&mut StdRng::seed_from_u64(1))
+                .unwrap()
+        };
+        let easy = puzzle(&[Direction::East]);
+        let hard = puzzle(&[Direction::West, Direction::Northwest, Direction::Southwest]);
+        assert_eq!(solve_time(&easy), solve_time(&easy));
+        assert!(solve_time(&easy) < solve_time(&hard));
+    }
+}
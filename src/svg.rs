@@ -0,0 +1,298 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use image::Rgb;
+use rusttype::Scale;
+
+use crate::config::{Args, MarkStyle};
+use crate::error::WordSearchError;
+use crate::grid::Puzzle;
+use crate::page::{self, escape, hex, Align, Group, Mark, Rectangle, Renderer, Text};
+use crate::render::{self, FramePart, Layout};
+use crate::typeset::Font;
+use crate::wordlist::Entry;
+
+/// Write the whole page as an SVG image, laid out the same way as the raster image. The answers are in their own
+/// layer, with the id "solution", which is hidden unless `solution` is set. It can be shown in an editor, or by adding
+/// the "show-solution" class to the SVG element.
+pub fn write_svg<W: Write>(
+    mut out: W,
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+    solution: bool,
+) -> Result<(), WordSearchError> {
+    let font = render::load_font(args)?;
+    let mut svg = Svg::new(&font, args)?;
+    match render::inside_frame(args) {
+        Some((inner, inset)) => {
+            svg.framed(args, inset, |svg| {
+                page::draw_page(svg, &font, wordlist, puzzle, &inner, student, solution)
+            })?;
+        }
+        None => page::draw_page(&mut svg, &font, wordlist, puzzle, args, student, solution)?,
+    }
+    out.write_all(svg.finish()?.as_bytes())?;
+    Ok(())
+}
+
+/// Write the pages the key continues onto, if it doesn't all fit on the page with the grid, as separate SVG images.
+pub fn continued_pages(
+    wordlist: &[Entry],
+    puzzle: &Puzzle,
+    args: &Args,
+    student: Option<&str>,
+) -> Result<Vec<String>, WordSearchError> {
+    let font = render::load_font(args)?;
+    let inner = render::inside_frame(args);
+    let page_args = inner.as_ref().map_or(args, |(inner, _)| inner);
+    let top = render::page_top(&font, page_args, student);
+    let layout = Layout::new(&font, wordlist, puzzle, page_args, top)?;
+    let mut pages = vec![];
+    for page in render::key_pages(wordlist, &layout, page_args)
+        .into_iter()
+        .skip(1)
+    {
+        let mut svg = Svg::new(&font, args)?;
+        let width = page_args.image_width;
+        match &inner {
+            Some((_, inset)) => svg.framed(args, *inset, |svg| {
+                page::draw_continued_page(svg, page, &layout, width)
+            })?,
+            None => page::draw_continued_page(&mut svg, page, &layout, width)?,
+        }
+        pages.push(svg.finish()?);
+    }
+    Ok(pages)
+}
+
+/// Draws pages as SVG images.
+struct Svg<'a> {
+    svg: String,
+    font: &'a Font,
+}
+
+impl<'a> Svg<'a> {
+    /// Start an SVG image: the root element, the style that shows the solution, and a white background.
+    fn new(font: &'a Font, args: &Args) -> Result<Self, WordSearchError> {
+        let (width, height) = (args.image_width, args.image_height);
+        let family = args.typeface().css_family();
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="{width}" height="{height}" viewBox="0 0 {width} {height}" xml:space="preserve" font-family="{family}">"#
+        )?;
+        writeln!(
+            svg,
+            "<style>svg.show-solution #solution {{ display: inline !important; }}</style>"
+        )?;
+        writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+        Ok(Self { svg, font })
+    }
+
+    /// Draw a frame around the page, and then what's inside it, moved in from the edge.
+    fn framed(
+        &mut self,
+        args: &Args,
+        inset: u32,
+        draw: impl FnOnce(&mut Self) -> Result<(), WordSearchError>,
+    ) -> Result<(), WordSearchError> {
+        if let Some(style) = args.frame {
+            let rect = (0, 0, args.image_width as i32, args.image_height as i32);
+            page::draw_frame(self, style, rect, args.frame_thickness as i32)?;
+        }
+        writeln!(self.svg, r#"<g transform="translate({inset} {inset})">"#)?;
+        draw(self)?;
+        writeln!(self.svg, "</g>")?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<String, WordSearchError> {
+        writeln!(self.svg, "</svg>")?;
+        Ok(self.svg)
+    }
+}
+
+impl Renderer for Svg<'_> {
+    fn text(&mut self, text: &Text) -> Result<(), WordSearchError> {
+        let (x, anchor) = match text.align {
+            Align::Left => (text.x as f32, "start"),
+            Align::Center(width) => (text.x as f32 + width as f32 / 2.0, "middle"),
+            Align::Right => (text.x as f32, "end"),
+        };
+        let y = text.y as f32 + self.font.v_metrics(text.scale).ascent;
+        if text.bold {
+            writeln!(self.svg, r#"<g font-weight="bold">"#)?;
+        }
+        let fill = text
+            .color
+            .map_or(String::new(), |color| format!(r#" fill="{}""#, rgb(color)));
+        writeln!(
+            self.svg,
+            r#"<text x="{x}" y="{y}" font-size="{}" text-anchor="{anchor}"{fill}>{}</text>"#,
+            font_size(self.font, text.scale),
+            escape(text.text)
+        )?;
+        if text.bold {
+            writeln!(self.svg, "</g>")?;
+        }
+        Ok(())
+    }
+
+    fn rectangle(&mut self, rectangle: &Rectangle) -> Result<(), WordSearchError> {
+        let Rectangle {
+            x,
+            y,
+            width,
+            height,
+            ..
+        } = *rectangle;
+        let fill = rectangle.fill.map_or("none".to_string(), rgb);
+        let stroke = match (rectangle.stroke, rectangle.line_width) {
+            (Some(stroke), 1) => format!(r#" stroke="{}""#, rgb(stroke)),
+            (Some(stroke), width) => format!(r#" stroke="{}" stroke-width="{width}""#, rgb(stroke)),
+            (None, _) => String::new(),
+        };
+        writeln!(
+            self.svg,
+            r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" fill="{fill}"{stroke}/>"#
+        )?;
+        Ok(())
+    }
+
+    fn frame_part(&mut self, part: &FramePart) -> Result<(), WordSearchError> {
+        match *part {
+            // The stroke is centered on the outline, so it goes halfway in from the band's edges.
+            FramePart::Band {
+                x,
+                y,
+                width,
+                height,
+                thickness,
+                radius,
+            } => {
+                let half = thickness as f32 / 2.0;
+                let radius = (radius as f32 - half).max(0.0);
+                writeln!(
+                    self.svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{radius}" fill="none" stroke="black" stroke-width="{thickness}"/>"#,
+                    x as f32 + half,
+                    y as f32 + half,
+                    width - thickness,
+                    height - thickness,
+                )?;
+            }
+            FramePart::Square { x, y, size } => {
+                writeln!(
+                    self.svg,
+                    r#"<rect x="{x}" y="{y}" width="{size}" height="{size}"/>"#
+                )?;
+            }
+            FramePart::Diamond { x, y, radius } => {
+                writeln!(
+                    self.svg,
+                    r#"<polygon points="{x},{} {},{y} {x},{} {},{y}"/>"#,
+                    y - radius,
+                    x + radius,
+                    y + radius,
+                    x - radius,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn marks(
+        &mut self,
+        marks: &[Mark],
+        style: MarkStyle,
+        color: Rgb<u8>,
+        opacity: f32,
+    ) -> Result<(), WordSearchError> {
+        writeln!(
+            self.svg,
+            r##"<g opacity="{opacity}" fill="none" stroke="#{}">"##,
+            hex(color),
+        )?;
+        for mark in marks {
+            answer(&mut self.svg, mark, style)?;
+        }
+        writeln!(self.svg, "</g>")?;
+        Ok(())
+    }
+
+    fn begin(&mut self, group: Group) -> Result<(), WordSearchError> {
+        match group {
+            Group::Grid => writeln!(self.svg, r#"<g id="grid">"#)?,
+            Group::Coordinates => writeln!(self.svg, r#"<g id="coordinates">"#)?,
+            Group::Key => writeln!(self.svg, r#"<g id="key">"#)?,
+            Group::Frame => writeln!(self.svg, r#"<g class="frame" fill="black">"#)?,
+            Group::Solution { shown } => {
+                let display = if shown { "inline" } else { "none" };
+                writeln!(
+                    self.svg,
+                    r#"<g id="solution" inkscape:groupmode="layer" inkscape:label="Solution" style="display:{display}">"#,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, _group: Group) -> Result<(), WordSearchError> {
+        writeln!(self.svg, "</g>")?;
+        Ok(())
+    }
+}
+
+/// Draw one of the answer marks, matching the geometry of the raster marks.
+fn answer(svg: &mut String, mark: &Mark, style: MarkStyle) -> Result<(), WordSearchError> {
+    let ((x0, y0), (x1, y1), stride) = (mark.start, mark.end, mark.stride);
+    match style {
+        MarkStyle::Line | MarkStyle::Highlight => {
+            let thickness = match style {
+                MarkStyle::Line => stride / 6.0,
+                _ => stride * 2.0 / 3.0,
+            };
+            writeln!(
+                svg,
+                r#"<line x1="{x0}" y1="{y0}" x2="{x1}" y2="{y1}" stroke-width="{thickness}" stroke-linecap="round"/>"#
+            )?;
+        }
+        MarkStyle::Oval => {
+            let radius = stride * 0.42;
+            let length = f32::hypot(x1 - x0, y1 - y0);
+            let angle = f32::atan2(y1 - y0, x1 - x0).to_degrees();
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{radius}" stroke-width="3" transform="rotate({angle} {x0} {y0})"/>"#,
+                x0 - radius,
+                y0 - radius,
+                length + 2.0 * radius,
+                2.0 * radius,
+            )?;
+        }
+        MarkStyle::CircleLetters => {
+            for (cx, cy) in &mark.letters {
+                writeln!(
+                    svg,
+                    r#"<circle cx="{cx}" cy="{cy}" r="{}" stroke-width="2"/>"#,
+                    stride * 0.45
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Convert a rusttype scale, which is the height from descent to ascent, into a CSS font size, which is the size of
+/// the em square.
+fn font_size(font: &Font, scale: Scale) -> f32 {
+    let v = font.v_metrics_unscaled();
+    scale.y * font.units_per_em() as f32 / (v.ascent - v.descent)
+}
+
+fn rgb(color: Rgb<u8>) -> String {
+    let Rgb([r, g, b]) = color;
+    format!("rgb({},{},{})", r, g, b)
+}
@@ -0,0 +1,163 @@
+//! Laying out a line of text in a built-in font, for measuring it and drawing it. With the "shaping" feature, the
+//! text is shaped with rustybuzz, so letters join up in scripts like Arabic and Devanagari and the key is kerned.
+//! Without it, each character is drawn as a glyph of its own.
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use image::{Rgb, RgbImage};
+use imageproc::pixelops::weighted_sum;
+use rusttype::{point, PositionedGlyph, Rect, Scale};
+
+/// A built-in font, and the data it was read from, which tells it apart from the others.
+#[derive(Clone)]
+pub struct Font {
+    font: rusttype::Font<'static>,
+    data: &'static [u8],
+}
+
+impl Font {
+    /// Read a font from a TrueType or OpenType file built into the program.
+    pub fn new(data: &'static [u8]) -> Option<Self> {
+        let font = rusttype::Font::try_from_bytes(data)?;
+        Some(Self { font, data })
+    }
+}
+
+impl Deref for Font {
+    type Target = rusttype::Font<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.font
+    }
+}
+
+/// The font's data, a glyph, its scale, and where it starts within a pixel, which together decide how it's
+/// rasterized.
+type GlyphKey = (usize, u16, [u32; 4]);
+
+/// Glyphs already rasterized: how much of each pixel of its bounding box each one covers, row by row. It lasts as
+/// long as the program, so a batch of puzzles rasterizes each letter once at each size, instead of once per cell.
+static COVERAGE: LazyLock<Mutex<HashMap<GlyphKey, Arc<Vec<f32>>>>> =
+    LazyLock::new(Default::default);
+
+/// Return the width and height of a line of text, like [`imageproc::drawing::text_size`].
+pub fn size(scale: Scale, font: &Font, text: &str) -> (i32, i32) {
+    layout(scale, font, text, |_, _| {})
+}
+
+/// Draw a line of text with its top left corner at (`x`, `y`), like [`imageproc::drawing::draw_text_mut`].
+pub fn draw(
+    image: &mut RgbImage,
+    color: Rgb<u8>,
+    x: i32,
+    y: i32,
+    scale: Scale,
+    font: &Font,
+    text: &str,
+) {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    layout(scale, font, text, |glyph, bb| {
+        // Text too small to see has glyphs with no pixels.
+        if bb.width() <= 0 {
+            return;
+        }
+        let coverage = rasterize(font, glyph, bb);
+        for (gy, row) in coverage.chunks(bb.width() as usize).enumerate() {
+            let image_y = y + bb.min.y + gy as i32;
+            if !(0..height).contains(&image_y) {
+                continue;
+            }
+            for (gx, &coverage) in row.iter().enumerate() {
+                let image_x = x + bb.min.x + gx as i32;
+                // Drawing with no coverage leaves the pixel as it was.
+                if coverage > 0.0 && (0..width).contains(&image_x) {
+                    let pixel = image.get_pixel_mut(image_x as u32, image_y as u32);
+                    *pixel = weighted_sum(*pixel, color, 1.0 - coverage, coverage);
+                }
+            }
+        }
+    });
+}
+
+/// Return how much the glyph covers each pixel of its bounding box, from [`COVERAGE`] if it's been drawn before.
+fn rasterize(font: &Font, glyph: &PositionedGlyph, bb: Rect<i32>) -> Arc<Vec<f32>> {
+    let (scale, position) = (glyph.scale(), glyph.position());
+    let key = (
+        font.data.as_ptr() as usize,
+        glyph.id().0,
+        [scale.x, scale.y, position.x.fract(), position.y.fract()].map(f32::to_bits),
+    );
+    if let Some(coverage) = COVERAGE.lock().unwrap().get(&key) {
+        return coverage.clone();
+    }
+    let mut coverage = vec![0.0; (bb.width() * bb.height()) as usize];
+    glyph.draw(|gx, gy, v| coverage[(gy as i32 * bb.width() + gx as i32) as usize] = v);
+    let coverage = Arc::new(coverage);
+    COVERAGE.lock().unwrap().insert(key, coverage.clone());
+    coverage
+}
+
+/// Call `f` with each glyph that has something to draw, and return how far right and down they reach.
+fn layout(
+    scale: Scale,
+    font: &Font,
+    text: &str,
+    mut f: impl FnMut(&PositionedGlyph, Rect<i32>),
+) -> (i32, i32) {
+    let (mut width, mut height) = (0, 0);
+    for glyph in glyphs(scale, font, text) {
+        if let Some(bb) = glyph.pixel_bounding_box() {
+            width = width.max(bb.max.x);
+            height = height.max(bb.max.y);
+            f(&glyph, bb);
+        }
+    }
+    (width, height)
+}
+
+#[cfg(not(feature = "shaping"))]
+fn glyphs(scale: Scale, font: &Font, text: &str) -> Vec<PositionedGlyph<'static>> {
+    let ascent = font.v_metrics(scale).ascent;
+    font.layout(text, scale, point(0.0, ascent)).collect()
+}
+
+/// Shape the text with rustybuzz, and place the glyphs it chose where it says. Its positions are in font units, with
+/// y going up.
+#[cfg(feature = "shaping")]
+fn glyphs(scale: Scale, font: &Font, text: &str) -> Vec<PositionedGlyph<'static>> {
+    let face = match rustybuzz::Face::from_slice(font.data, 0) {
+        Some(face) => face,
+        None => return vec![],
+    };
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    // A vowel sign on its own in a cell of the grid is drawn as it is, without a dotted circle to stand on.
+    buffer.set_flags(rustybuzz::BufferFlags::DO_NOT_INSERT_DOTTED_CIRCLE);
+    let shaped = rustybuzz::shape(&face, &[], buffer);
+
+    let v = font.v_metrics_unscaled();
+    let (x_scale, y_scale) = (
+        scale.x / (v.ascent - v.descent),
+        scale.y / (v.ascent - v.descent),
+    );
+    let ascent = font.v_metrics(scale).ascent;
+    let mut pen = 0.0;
+    shaped
+        .glyph_infos()
+        .iter()
+        .zip(shaped.glyph_positions())
+        .map(|(info, position)| {
+            let at = point(
+                pen + position.x_offset as f32 * x_scale,
+                ascent - position.y_offset as f32 * y_scale,
+            );
+            pen += position.x_advance as f32 * x_scale;
+            font.glyph(rusttype::GlyphId(info.glyph_id as u16))
+                .scaled(scale)
+                .positioned(at)
+        })
+        .collect()
+}
@@ -0,0 +1,358 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::error::WordSearchError;
+use crate::script;
+
+/// One of the words to hide in the puzzle, along with what the key says about it.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// The word as it's listed in the key, with its spaces and punctuation, like "T-Rex".
+    pub word: String,
+    /// The heading it's listed under in the key, if any.
+    pub category: Option<String>,
+    /// How likely the word is to be kept when they don't all fit. Words without a weight are always kept.
+    pub weight: Option<f32>,
+    /// What the key shows instead of the word, for the solver to work out, like "Says meow" for "cat".
+    pub clue: Option<String>,
+    /// What the word means in the solver's own language, for --key bilingual, like "dog" for "perro".
+    pub translation: Option<String>,
+    /// The word as it's hidden in the grid, like "TREX".
+    grid: String,
+}
+
+impl Entry {
+    pub fn new(word: String) -> Self {
+        let word = word.trim().to_string();
+        Self {
+            grid: grid_form(&word),
+            word,
+            category: None,
+            weight: None,
+            clue: None,
+            translation: None,
+        }
+    }
+
+    pub fn with_category(mut self, category: Option<String>) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: Option<f32>) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_clue(mut self, clue: Option<String>) -> Self {
+        self.clue = clue;
+        self
+    }
+
+    pub fn with_translation(mut self, translation: Option<String>) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// The word as it's hidden in the grid.
+    pub fn grid_form(&self) -> String {
+        self.grid.clone()
+    }
+}
+
+/// A word list, along with the title its file gives the puzzle, if any.
+pub struct WordList {
+    pub title: Option<String>,
+    pub entries: Vec<Entry>,
+    /// The entries left out because they have no letters to hide, for the caller to tell the user about.
+    pub left_out: Vec<Entry>,
+}
+
+/// Read the word list. A file ending in ".csv" has a header row naming its columns: "word", and optionally
+/// "category", "weight", "clue", and "translation". Anything else has one word per line, as `read_text` reads it.
+/// Blank lines, spaces around words, and control characters are tidied away, unless `strict` is set, when they're
+/// errors, along with words that have no letters, which are otherwise set aside in `left_out`.
+pub fn read_list(filename: &Path, strict: bool) -> Result<WordList, WordSearchError> {
+    let is_csv = filename
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let mut list = match is_csv {
+        true => read_csv(filename, strict)?,
+        false => read_text(filename, strict)?,
+    };
+    if let Some(entry) = list.entries.iter().find(|e| e.grid.is_empty()) {
+        if strict {
+            return Err(WordSearchError::WordList(format!(
+                "{:?} has no letters to hide: {:?}",
+                filename, entry.word
+            )));
+        }
+    }
+    let (entries, left_out) = list.entries.into_iter().partition(|e| !e.grid.is_empty());
+    list.entries = entries;
+    list.left_out = left_out;
+    if list.entries.is_empty() {
+        return Err(WordSearchError::WordList(format!(
+            "Empty word list: {:?}",
+            filename
+        )));
+    }
+    Ok(list)
+}
+
+/// Read just the title a plain text word list gives the puzzle, if any.
+pub fn read_title(filename: &Path) -> Result<Option<String>, WordSearchError> {
+    let is_csv = filename
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    match is_csv {
+        true => Ok(None),
+        false => Ok(read_text(filename, false)?.title),
+    }
+}
+
+/// A CSV file that can't be read is a word list that can't be read, unless the file itself couldn't be.
+impl From<csv::Error> for WordSearchError {
+    fn from(e: csv::Error) -> Self {
+        match e.is_io_error() {
+            true => io::Error::from(e).into(),
+            false => WordSearchError::WordList(e.to_string()),
+        }
+    }
+}
+
+/// Read a word list in CSV format, with a header row naming its columns.
+fn read_csv(filename: &Path, strict: bool) -> Result<WordList, WordSearchError> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(filename)?;
+    let column = |name: &str, headers: &csv::StringRecord| {
+        headers.iter().position(|h| h.eq_ignore_ascii_case(name))
+    };
+    let headers = rdr.headers()?.clone();
+    let word = column("word", &headers).ok_or_else(|| {
+        WordSearchError::WordList(format!("No \"word\" column in {:?}", filename))
+    })?;
+    let category = column("category", &headers);
+    let weight = column("weight", &headers);
+    let clue = column("clue", &headers);
+    let translation = column("translation", &headers);
+    let mut entries = vec![];
+    for record in rdr.records() {
+        let record = record?;
+        let field = |i: Option<usize>| {
+            i.and_then(|i| record.get(i))
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        if strict {
+            // Empty fields are fine: they're just left out.
+            let tidied = |f: &str| f.is_empty() || clean(f).as_deref() == Some(f);
+            if let Some(i) = record.iter().position(|f| !tidied(f)) {
+                return Err(WordSearchError::WordList(format!(
+                    "Line {} of {:?}: {}",
+                    record.position().map_or(0, |p| p.line()),
+                    filename,
+                    problem(&record[i])
+                )));
+            }
+        }
+        if let Some(word) = field(Some(word)).and_then(|w| clean(&w)) {
+            let weight = match field(weight) {
+                Some(w) => match w.parse::<f32>() {
+                    Ok(w) if w > 0.0 && w.is_finite() => Some(w),
+                    _ => {
+                        return Err(WordSearchError::WordList(format!(
+                            "Bad weight {:?} for {} in {:?}",
+                            w, word, filename
+                        )))
+                    }
+                },
+                None => None,
+            };
+            entries.push(
+                Entry::new(word)
+                    .with_category(field(category))
+                    .with_weight(weight)
+                    .with_clue(field(clue))
+                    .with_translation(field(translation)),
+            );
+        }
+    }
+    if entries.is_empty() {
+        return Err(WordSearchError::WordList(format!(
+            "Empty word list: {:?}",
+            filename
+        )));
+    }
+    Ok(WordList {
+        title: None,
+        entries,
+        left_out: vec![],
+    })
+}
+
+/// Read a plain text word list, with one word per line. Lines starting with "#" are comments. Lines like
+/// "!title: Pets" are directives: "!title:" gives the puzzle a title, "!category:" puts the words after it in that
+/// category, until the next one, and "!clue:" gives the word on the next line a clue for the key to show instead.
+fn read_text(filename: &Path, strict: bool) -> Result<WordList, WordSearchError> {
+    let (mut title, mut category, mut clue) = (None, None, None);
+    let mut entries = vec![];
+    for line in read_lines(filename, strict)? {
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((name, value)) = directive(&line) {
+            let value = (!value.is_empty()).then(|| value.to_string());
+            match name.to_lowercase().as_str() {
+                "title" => title = value,
+                "category" => category = value,
+                "clue" => clue = value,
+                _ => {
+                    return Err(WordSearchError::WordList(format!(
+                        "Unknown directive {:?} in {:?}",
+                        line, filename
+                    )))
+                }
+            }
+            continue;
+        }
+        entries.push(
+            Entry::new(line)
+                .with_category(category.clone())
+                .with_clue(clue.take()),
+        );
+    }
+    if let Some(clue) = clue {
+        return Err(WordSearchError::WordList(format!(
+            "No word after the clue {:?} in {:?}",
+            clue, filename
+        )));
+    }
+    if entries.is_empty() {
+        return Err(WordSearchError::WordList(format!(
+            "Empty word list: {:?}",
+            filename
+        )));
+    }
+    Ok(WordList {
+        title,
+        entries,
+        left_out: vec![],
+    })
+}
+
+/// Split a directive like "!title: Pets" into its name and value.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.strip_prefix('!')?.split_once(':')?;
+    let is_name = !name.is_empty() && name.chars().all(char::is_alphabetic);
+    is_name.then(|| (name, value.trim()))
+}
+
+/// Read a file with one entry per line. A byte order mark at the start, and Windows line endings, are fine. Blank
+/// lines are skipped, and the others are tidied with `clean`, unless `strict` is set, when they're errors.
+pub fn read_lines(filename: &Path, strict: bool) -> Result<Vec<String>, WordSearchError> {
+    let file = File::open(filename)?;
+    let rdr = BufReader::new(file);
+    let mut lines = vec![];
+    for (i, line) in rdr.lines().enumerate() {
+        let line = line?;
+        let line = match i {
+            0 => line.strip_prefix('\u{feff}').unwrap_or(&line),
+            _ => &line,
+        };
+        match clean(line) {
+            Some(cleaned) if cleaned == line || !strict => lines.push(cleaned),
+            None if !strict => (),
+            _ => {
+                return Err(WordSearchError::WordList(format!(
+                    "Line {} of {:?}: {}",
+                    i + 1,
+                    filename,
+                    problem(line)
+                )))
+            }
+        }
+    }
+    if lines.is_empty() {
+        return Err(WordSearchError::WordList(format!(
+            "Empty word list: {:?}",
+            filename
+        )));
+    }
+    Ok(lines)
+}
+
+/// Tidy up one entry of a word list: take out control characters and the spaces around it. Return None if that
+/// leaves nothing.
+fn clean(entry: &str) -> Option<String> {
+    let entry: String = entry.chars().filter(|c| !c.is_control()).collect();
+    let entry = entry.trim();
+    (!entry.is_empty()).then(|| entry.to_string())
+}
+
+/// Say what `clean` had to tidy up in the entry.
+fn problem(entry: &str) -> String {
+    if let Some(c) = entry.chars().find(|c| c.is_control()) {
+        return format!("control character {:?} in {:?}", c, entry);
+    }
+    match clean(entry) {
+        None => "blank line".to_string(),
+        Some(_) => format!("spaces around {:?}", entry),
+    }
+}
+
+/// Convert the word to the form it takes in the grid: capitalized, without spaces or punctuation. Letters of any
+/// script are kept, so a puzzle can mix them, and so are digits, for words like "ROUTE66", and emoji, which each take a
+/// cell like a letter. The parts of emoji made of more than one character are kept too, rather than changing the word
+/// by leaving them out, so making the puzzle can refuse them.
+pub fn grid_form(word: &str) -> String {
+    word.to_uppercase()
+        .chars()
+        .filter(|&c| c.is_alphanumeric() || script::is_emoji(c) || script::is_emoji_part(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean, directive, problem, Entry};
+
+    #[test]
+    fn test_entry() {
+        let entry = Entry::new(" O'Brien ".to_string());
+        assert_eq!("O'Brien", entry.word);
+        assert_eq!("OBRIEN", entry.grid_form());
+        assert_eq!("TREX", Entry::new("T-Rex".to_string()).grid_form());
+        assert_eq!("🐶", Entry::new("🐶\u{FE0F}".to_string()).grid_form());
+        assert_eq!(
+            "👩\u{200D}💻",
+            Entry::new("👩\u{200D}💻".to_string()).grid_form()
+        );
+    }
+
+    #[test]
+    fn test_clean() {
+        assert_eq!(Some("cat".to_string()), clean("cat"));
+        assert_eq!(Some("ice cream".to_string()), clean(" ice cream \r"));
+        assert_eq!(Some("emu".to_string()), clean("em\u{7}u"));
+        assert_eq!(None, clean(" \t "));
+        assert_eq!("blank line", problem(""));
+        assert_eq!("spaces around \"dog \"", problem("dog "));
+        assert!(problem("em\u{7}u").starts_with("control character"));
+    }
+
+    #[test]
+    fn test_directive() {
+        assert_eq!(
+            Some(("title", "At the Zoo")),
+            directive("!title: At the Zoo")
+        );
+        assert_eq!(Some(("category", "")), directive("!category:"));
+        assert_eq!(None, directive("!Kung"));
+        assert_eq!(None, directive("! title: x"));
+        assert_eq!(None, directive("cat"));
+    }
+}